@@ -1,212 +1,327 @@
 use solana_sdk::pubkey::Pubkey;
-use solana_client::rpc_client::RpcClient;
-use std::collections::{HashMap, HashSet};
+use std::collections::HashMap;
+use std::ops::Range;
 use std::sync::Arc;
-use log::info;
+use log::{debug, info};
+use rayon::prelude::*;
+use crate::cycles::{CycleIndex, Hop};
 use crate::dex::PoolMints;
+use crate::error::ArbError;
+use crate::pool_registry::PoolRegistry;
+use crate::prefilter::candidate_cycles;
 
-/// Строит граф арбитража на основе HashMap пулов.
+/// Строит граф арбитража на основе реестра пулов.
 /// 
 /// # Arguments
 /// * `start_mint` - начальный mint токена
 /// * `start_amount` - количество токенов начального минта
-/// * `pools_map` - HashMap, где ключ - mint адрес, значение - вектор пулов, содержащих этот mint
-/// * `client` - RPC клиент для получения актуальных данных пулов
+/// * `registry` - реестр пулов, индексированный по обоим mint каждого пула
+/// * `max_slot_spread` - максимальная разница слотов состояния между пулами цепочки
+/// * `max_depth` - максимум обменов (свапов) в цепочке
 /// 
 /// # Returns
 /// Вектор цепочек арбитража. Каждая цепочка - это последовательность пулов (Vec<Arc<dyn PoolMints>>),
 /// представляющая путь от начального минта обратно к начальному минту через серию свопов.
 /// 
 /// # Правила построения графа:
-/// 1. Максимум 4 обмена (свапа) в цепочке
+/// 1. Максимум `max_depth` обменов (свапов) в цепочке
 /// 2. Первый пул в цепочке должен быть связан с начальным минтом
 /// 3. Пулы в цепочке не должны повторяться (Pubkey этих пулов должны быть уникальными)
 /// 4. Завершаться цепочка должна получением токена, минт которого совпадает с начальным
+/// 5. Состояния пулов цепочки должны быть из слотов, отстоящих не более чем на `max_slot_spread`
+///
+/// Полностью котируются только циклы, прошедшие логарифмический префильтр
+/// ([`candidate_cycles`]): без отрицательной суммы `-ln` маржинальных курсов цикл не прибылен.
 pub fn build_arbitrage_graph(
     start_mint: &Pubkey,
     start_amount: u64,
-    pools_map: &HashMap<Pubkey, Vec<Arc<dyn PoolMints>>>,
-    client: &RpcClient,
-) -> Result<Vec<Vec<Arc<dyn PoolMints>>>, Box<dyn std::error::Error>> {
+    registry: &PoolRegistry,
+    max_slot_spread: u64,
+    max_depth: usize,
+) -> Result<Vec<Vec<Arc<dyn PoolMints>>>, ArbError> {
+    let candidates = candidate_cycles(start_mint, start_amount, registry, max_depth);
+    quote_cycles(&candidates, start_amount, registry, max_slot_spread)
+}
+
+/// Перекотировать предвычисленные циклы по текущему состоянию пулов.
+///
+/// Котируются все циклы индекса без повторного обхода графа; прибыльные цепочки возвращаются
+/// в порядке номеров циклов. Префильтр не применяется — это дело того, кто строит индекс.
+pub fn quote_cycles(
+    index: &CycleIndex,
+    start_amount: u64,
+    registry: &PoolRegistry,
+    max_slot_spread: u64,
+) -> Result<Vec<Vec<Arc<dyn PoolMints>>>, ArbError> {
+    let start_mint = index.start_mint();
     info!("Starting arbitrage graph building");
     info!("Start mint: {}, Start amount: {}", start_mint, start_amount);
-    info!("Available mints in pools_map: {}", pools_map.len());
-    
-    let mut result: Vec<Vec<Arc<dyn PoolMints>>> = Vec::new();
-    let mut current_path: Vec<Arc<dyn PoolMints>> = Vec::new();
-    let mut used_pools: HashSet<Pubkey> = HashSet::new();
-
-    // Вспомогательная рекурсивная функция для поиска цепочек
-    fn dfs(
-        current_mint: &Pubkey,
-        current_amount: u64,
-        start_mint: &Pubkey,
-        start_amount: u64,
-        pools_map: &HashMap<Pubkey, Vec<Arc<dyn PoolMints>>>,
-        client: &RpcClient,
-        current_path: &mut Vec<Arc<dyn PoolMints>>,
-        used_pools: &mut HashSet<Pubkey>,
-        depth: usize,
-        max_depth: usize,
-        result: &mut Vec<Vec<Arc<dyn PoolMints>>>,
-    ) -> Result<(), Box<dyn std::error::Error>> {
-        // Если достигли максимальной глубины, прекращаем поиск
-        if depth >= max_depth {
-            return Ok(());
+    info!("Available mints in registry: {}, pools: {}, cycles: {}", registry.mint_count(), registry.len(), index.len());
+
+    let quote = Quote { index, start_amount, registry, max_slot_spread: Some(max_slot_spread) };
+
+    // Группы циклов с общим первым пулом независимы; они котируются в пуле потоков rayon,
+    // а результаты склеиваются в порядке групп — так же, как при последовательном обходе
+    let groups: Vec<Range<usize>> = index.groups().collect();
+    let branch = Branch::merge(groups.into_par_iter().map(|group| quote.cycles(group)).collect());
+    let result: Vec<Vec<Arc<dyn PoolMints>>> =
+        branch.profitable.iter().map(|(cycle, _)| quote.chain(*cycle)).collect();
+    let skipped = branch.skipped;
+
+    for (chain_idx, chain) in result.iter().enumerate() {
+        log_chain(chain_idx + 1, chain, start_mint, start_amount);
+    }
+
+    info!("Arbitrage graph building completed. Found {} chains", result.len());
+    if !skipped.is_empty() {
+        let mut reasons: Vec<_> = skipped.iter().collect();
+        reasons.sort();
+        info!("Skipped pool quotes by reason: {:?}", reasons);
+    }
+    if result.is_empty() {
+        info!("No arbitrage opportunities found for mint {} with amount {}", start_mint, start_amount);
+    } else {
+        info!("=== Summary of all arbitrage chains ===");
+        for (chain_idx, chain) in result.iter().enumerate() {
+            info!("Chain #{}: {} pools", chain_idx + 1, chain.len());
+            for (pool_idx, pool) in chain.iter().enumerate() {
+                info!("  Pool {}: {}", pool_idx + 1, pool.pool_pubkey());
+            }
         }
+    }
 
-        // Получаем все пулы, которые содержат текущий mint
-        let pools = match pools_map.get(current_mint) {
-            Some(pools) => pools,
-            None => return Ok(()), // Нет пулов для этого минта
-        };
+    Ok(result)
+}
 
-        // Перебираем все доступные пулы
-        for pool in pools {
-            let pool_pubkey = *pool.pool_pubkey();
+/// Неизменяемые параметры котировки, общие для всех потоков
+pub(crate) struct Quote<'a> {
+    pub(crate) index: &'a CycleIndex,
+    pub(crate) start_amount: u64,
+    pub(crate) registry: &'a PoolRegistry,
+    /// `None` — состояние всех пулов актуально (живые подписки), разброс слотов не проверяется
+    pub(crate) max_slot_spread: Option<u64>,
+}
 
-            // Проверяем, что пул еще не использован в текущей цепочке
-            if used_pools.contains(&pool_pubkey) {
-                continue;
+/// Результат котировки набора циклов
+#[derive(Default)]
+pub(crate) struct Branch {
+    /// Прибыльные циклы по возрастанию номера и итоговая сумма каждого
+    pub(crate) profitable: Vec<(usize, u64)>,
+    /// Количество пропущенных пулов по причине ошибки (ArbError::reason)
+    pub(crate) skipped: HashMap<&'static str, usize>,
+}
+
+impl Branch {
+    /// Склеить результаты в порядке следования
+    pub(crate) fn merge(branches: Vec<Branch>) -> Branch {
+        let mut merged = Branch::default();
+        for branch in branches {
+            merged.profitable.extend(branch.profitable);
+            for (reason, count) in branch.skipped {
+                *merged.skipped.entry(reason).or_insert(0) += count;
             }
+        }
+        merged
+    }
+}
 
-            // Определяем, какой токен мы можем обменять в этом пуле
-            let (token_in, token_out) = if *pool.mint_a() == *current_mint {
-                (*pool.mint_a(), *pool.mint_b())
-            } else if *pool.mint_b() == *current_mint {
-                (*pool.mint_b(), *pool.mint_a())
-            } else {
-                continue; // Этот пул не содержит текущий mint (не должно происходить, но на всякий случай)
-            };
-
-            // Рассчитываем количество выходных токенов
-            let amount_out = match pool.amount_out(client, current_amount, &token_in) {
-                Ok(amount) => amount,
-                Err(_) => continue, // Пропускаем пул, если не удалось рассчитать amount_out
-            };
-
-            if amount_out == 0 {
-                continue; // Пропускаем пулы с нулевым выходом
+impl Quote<'_> {
+    /// Котировка циклов по возрастанию номера. Соседние циклы делят префикс: суммы по общему
+    /// префиксу не пересчитываются, а цикл с уже отвергнутым префиксом пропускается без котировки
+    pub(crate) fn cycles(&self, cycles: impl IntoIterator<Item = usize>) -> Branch {
+        let mut branch = Branch::default();
+        let mut previous: &[Hop] = &[];
+        // amounts[k] — сумма после шага k предыдущего цикла
+        let mut amounts: Vec<u64> = Vec::with_capacity(self.index.max_depth());
+        // Шаг, на котором предыдущий цикл был отвергнут
+        let mut rejected_at: Option<usize> = None;
+
+        for i in cycles {
+            let cycle = self.index.cycle(i);
+            let common = previous.iter().zip(cycle).take_while(|(a, b)| a == b).count();
+            previous = cycle;
+            if rejected_at.is_some_and(|step| step < common) {
+                continue;
             }
 
-            // Добавляем пул в текущий путь
-            current_path.push(Arc::clone(pool));
-            used_pools.insert(pool_pubkey);
-
-            // Проверяем, вернулись ли мы к начальному минту
-            if token_out == *start_mint {
-                if (amount_out > start_amount) {
-                    // Нашли завершенную цепочку арбитража
-                    result.push(current_path.clone());
-                    info!("Found arbitrage chain #{} with {} pools:", result.len(), current_path.len());
-                    
-                    // Пересчитываем путь для детального логирования
-                    let mut chain_amount = start_amount;
-                    let mut current_token = *start_mint;
-                    
-                    for (idx, pool) in current_path.iter().enumerate() {
-                        let pool_pubkey = pool.pool_pubkey();
-                        let mint_a = pool.mint_a();
-                        let mint_b = pool.mint_b();
-                        
-                        // Определяем направление обмена
-                        let (token_in, token_out) = if *mint_a == current_token {
-                            (*mint_a, *mint_b)
-                        } else if *mint_b == current_token {
-                            (*mint_b, *mint_a)
-                        } else {
-                            // Это не должно происходить, но на всякий случай
-                            info!("  Step {}: Pool {} - ERROR: token mismatch", idx + 1, pool_pubkey);
-                            break;
-                        };
-                        
-                        // Рассчитываем amount_out для логирования
-                        let amount_out = match pool.amount_out(client, chain_amount, &token_in) {
-                            Ok(amt) => amt,
-                            Err(e) => {
-                                info!("  Step {}: Pool {} - ERROR calculating amount_out: {}", idx + 1, pool_pubkey, e);
-                                break;
-                            }
-                        };
-                        
-                        info!("  Step {}", 
-                            idx + 1
-                        );
-
-                        info!("    Pool {} ", 
-                            pool_pubkey
-                        );
-
-                        info!("    {} -> {}", 
-                            token_in, 
-                            token_out,
-                        );
-
-                        info!("    amount_in: {}, amount_out: {}", 
-                            chain_amount,
-                            amount_out
-                        );
-                        
-                        chain_amount = amount_out;
-                        current_token = token_out;
+            amounts.truncate(common.min(amounts.len()));
+            rejected_at = None;
+            for step in amounts.len()..cycle.len() {
+                match self.step(cycle, step, &amounts, &mut branch) {
+                    Some(amount_out) => amounts.push(amount_out),
+                    None => {
+                        rejected_at = Some(step);
+                        break;
                     }
-                    
-                    let profit: i64 = chain_amount as i64 - start_amount as i64;
-                    info!("  Chain summary: start_amount={}, final_amount={}, profit={}", 
-                        start_amount, chain_amount, profit);
-                }  
-            } else {
-                // Продолжаем поиск с новым токеном
-                dfs(
-                    &token_out,
-                    amount_out,
-                    start_mint,
-                    start_amount,
-                    pools_map,
-                    client,
-                    current_path,
-                    used_pools,
-                    depth + 1,
-                    max_depth,
-                    result,
-                )?;
+                }
             }
 
-            // Откатываем изменения (backtracking)
-            current_path.pop();
-            used_pools.remove(&pool_pubkey);
+            let final_amount = amounts.last().copied().filter(|_| rejected_at.is_none());
+            if let Some(amount) = final_amount.filter(|amount| *amount > self.start_amount) {
+                // Нашли завершенную цепочку арбитража
+                branch.profitable.push((i, amount));
+            }
         }
+        branch
+    }
 
-        Ok(())
+    /// Пулы `i`-го цикла
+    pub(crate) fn chain(&self, i: usize) -> Vec<Arc<dyn PoolMints>> {
+        self.index.cycle(i).iter().map(|hop| Arc::clone(self.pool(hop))).collect()
     }
 
-    // Запускаем поиск с начального минта
-    dfs(
-        start_mint,
-        start_amount,
-        start_mint,
-        start_amount,
-        pools_map,
-        client,
-        &mut current_path,
-        &mut used_pools,
-        0,
-        4, // Максимум 4 обмена
-        &mut result,
-    )?;
+    // Своп на шаге `step` цикла; `None` — цикл отвергнут на этом шаге
+    fn step(&self, cycle: &[Hop], step: usize, amounts: &[u64], branch: &mut Branch) -> Option<u64> {
+        let hop = &cycle[step];
+        let pool = self.pool(hop);
+        let token_in = if hop.a_to_b { pool.mint_a() } else { pool.mint_b() };
+        let amount_in = if step == 0 { self.start_amount } else { amounts[step - 1] };
 
-    info!("Arbitrage graph building completed. Found {} chains", result.len());
-    if result.is_empty() {
-        info!("No arbitrage opportunities found for mint {} with amount {}", start_mint, start_amount);
-    } else {
-        info!("=== Summary of all arbitrage chains ===");
-        for (chain_idx, chain) in result.iter().enumerate() {
-            info!("Chain #{}: {} pools", chain_idx + 1, chain.len());
-            for (pool_idx, pool) in chain.iter().enumerate() {
-                info!("  Pool {}: {}", pool_idx + 1, pool.pool_pubkey());
+        // Пулы из слишком далёких друг от друга слотов дают фантомный арбитраж
+        if self.max_slot_spread.is_some_and(|max| slot_spread(cycle[..=step].iter().map(|hop| self.pool(hop))) > max) {
+            *branch.skipped.entry("slot_spread").or_insert(0) += 1;
+            return None;
+        }
+
+        // Рассчитываем количество выходных токенов
+        match pool.amount_out(amount_in, token_in) {
+            // Пропускаем пулы с нулевым выходом
+            Ok(0) => None,
+            Ok(amount_out) => Some(amount_out),
+            Err(e) => {
+                // Пропускаем пул, если не удалось рассчитать amount_out
+                debug!("Skipping pool {}: {}", pool.pool_pubkey(), e);
+                *branch.skipped.entry(e.reason()).or_insert(0) += 1;
+                None
             }
         }
     }
 
-    Ok(result)
-}
\ No newline at end of file
+    fn pool(&self, hop: &Hop) -> &Arc<dyn PoolMints> {
+        // Индекс построен по этому реестру, а пулы из реестра не удаляются
+        self.registry.pool(hop.pool).expect("cycle index does not match the registry")
+    }
+}
+
+/// Подробный лог найденной цепочки: пересчёт свопов по шагам
+fn log_chain(number: usize, chain: &[Arc<dyn PoolMints>], start_mint: &Pubkey, start_amount: u64) {
+    info!("Found arbitrage chain #{} with {} pools:", number, chain.len());
+
+    let mut chain_amount = start_amount;
+    let mut current_token = *start_mint;
+
+    for (idx, pool) in chain.iter().enumerate() {
+        let pool_pubkey = pool.pool_pubkey();
+        let mint_a = pool.mint_a();
+        let mint_b = pool.mint_b();
+
+        // Определяем направление обмена
+        let (token_in, token_out) = if *mint_a == current_token {
+            (*mint_a, *mint_b)
+        } else if *mint_b == current_token {
+            (*mint_b, *mint_a)
+        } else {
+            // Это не должно происходить, но на всякий случай
+            info!("  Step {}: Pool {} - ERROR: token mismatch", idx + 1, pool_pubkey);
+            break;
+        };
+
+        // Рассчитываем amount_out для логирования
+        let amount_out = match pool.amount_out(chain_amount, &token_in) {
+            Ok(amt) => amt,
+            Err(e) => {
+                info!("  Step {}: Pool {} - ERROR calculating amount_out: {}", idx + 1, pool_pubkey, e);
+                break;
+            }
+        };
+
+        info!("  Step {}", idx + 1);
+        info!("    Pool {} ", pool_pubkey);
+        info!("    {} -> {}", token_in, token_out);
+        info!("    amount_in: {}, amount_out: {}", chain_amount, amount_out);
+
+        chain_amount = amount_out;
+        current_token = token_out;
+    }
+
+    let profit: i64 = chain_amount as i64 - start_amount as i64;
+    info!("  Chain summary: start_amount={}, final_amount={}, profit={}", start_amount, chain_amount, profit);
+}
+
+/// Разница между наибольшим и наименьшим слотом состояния пулов цепочки
+fn slot_spread<'a>(pools: impl Iterator<Item = &'a Arc<dyn PoolMints>>) -> u64 {
+    let (min, max) = pools
+        .map(|pool| pool.state_slot())
+        .fold((u64::MAX, 0), |(min, max), slot| (min.min(slot), max.max(slot)));
+    max - min
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cycles::MAX_DEPTH;
+    use crate::test_utils::{chain_keys, pair_pools, stub_registry, StubPool};
+
+    /// Два пула SOL/USDC с курсом 1:1.1 в обе стороны и заданными слотами состояния
+    fn registry(slots: [u64; 2]) -> (Pubkey, PoolRegistry) {
+        let (sol, usdc) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let registry = slots
+            .into_iter()
+            .map(|slot| -> Arc<dyn PoolMints> { Arc::new(StubPool::new(sol, usdc).with_rates(11_000, 11_000).with_slot(slot)) })
+            .collect();
+        (sol, registry)
+    }
+
+    #[test]
+    fn test_parallel_search_matches_sequential_order() {
+        let mints: Vec<Pubkey> = (0..4).map(|_| Pubkey::new_unique()).collect();
+        // Полный граф на 4 mint, по два пула на пару, курс 1:1.1
+        let pools = pair_pools(&mints, 2);
+        pools.iter().for_each(|pool| pool.set_rate(11_000));
+        let registry = stub_registry(&pools);
+
+        let run = |threads: usize| {
+            let pool = rayon::ThreadPoolBuilder::new().num_threads(threads).build().unwrap();
+            pool.install(|| build_arbitrage_graph(&mints[0], 1_000, &registry, 2, MAX_DEPTH)).unwrap()
+        };
+        let sequential = chain_keys(&run(1));
+        assert!(!sequential.is_empty());
+        for _ in 0..5 {
+            assert_eq!(chain_keys(&run(4)), sequential);
+        }
+    }
+
+    #[test]
+    fn test_quote_cycles_tracks_state_without_rebuilding_index() {
+        let mints: Vec<Pubkey> = (0..3).map(|_| Pubkey::new_unique()).collect();
+        let pools = pair_pools(&mints, 2);
+        let registry = stub_registry(&pools);
+        let index = CycleIndex::build(&mints[0], &registry);
+
+        // Курс 1:1 — прибыльных циклов нет
+        assert!(quote_cycles(&index, 1_000, &registry, 2).unwrap().is_empty());
+
+        // Изменение состояния видно без перестроения индекса, результат совпадает с полным поиском
+        pools[0].set_rate(10_100);
+        let quoted = chain_keys(&quote_cycles(&index, 1_000, &registry, 2).unwrap());
+        assert!(!quoted.is_empty());
+        assert!(quoted.iter().all(|chain| chain.contains(pools[0].pool_pubkey())));
+        assert_eq!(quoted, chain_keys(&build_arbitrage_graph(&mints[0], 1_000, &registry, 2, MAX_DEPTH).unwrap()));
+
+        // Нулевой выход второго шага отвергает все циклы с этим префиксом
+        pools[2].set_rate(0);
+        let quoted = chain_keys(&quote_cycles(&index, 1_000, &registry, 2).unwrap());
+        assert_eq!(quoted, chain_keys(&build_arbitrage_graph(&mints[0], 1_000, &registry, 2, MAX_DEPTH).unwrap()));
+        assert!(quoted.iter().all(|chain| !chain.contains(pools[2].pool_pubkey())));
+    }
+
+    #[test]
+    fn test_rejects_cycles_across_distant_slots() {
+        let (sol, pools) = registry([100, 101]);
+        assert_eq!(build_arbitrage_graph(&sol, 1_000, &pools, 2, MAX_DEPTH).unwrap().len(), 2);
+
+        let (sol, pools) = registry([100, 110]);
+        assert!(build_arbitrage_graph(&sol, 1_000, &pools, 2, MAX_DEPTH).unwrap().is_empty());
+        assert_eq!(build_arbitrage_graph(&sol, 1_000, &pools, 10, MAX_DEPTH).unwrap().len(), 2);
+    }
+}
@@ -0,0 +1,148 @@
+use solana_sdk::account::Account;
+use solana_sdk::pubkey::Pubkey;
+use std::path::Path;
+use std::sync::{RwLock, RwLockReadGuard, RwLockWriteGuard};
+use crate::error::ArbError;
+use crate::metadata::MetadataCache;
+use crate::provider::{fetch_snapshot, AccountProvider, AccountSnapshot};
+
+/// Общий кэш аккаунтов с привязкой к слоту.
+///
+/// Все загрузки и обновления проходят через кэш: аккаунт, уже полученный для одного пула
+/// (mint, AmmConfig, vault), не запрашивается повторно для другого, а все пулы читают
+/// одну и ту же версию данных. Данные из более старого слота не перезаписывают более свежие.
+///
+/// Статические зависимости пулов дополнительно хранятся в персистентном [`MetadataCache`]:
+/// после перезапуска загрузчик берёт их оттуда, а не из сети.
+#[derive(Debug, Default)]
+pub struct AccountCache {
+    accounts: RwLock<AccountSnapshot>,
+    metadata: RwLock<MetadataCache>,
+}
+
+impl AccountCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Кэш с метаданными пулов, сохранёнными при прошлом запуске
+    pub fn with_metadata(metadata: MetadataCache) -> Self {
+        Self { metadata: RwLock::new(metadata), ..Self::default() }
+    }
+
+    /// Метаданные пулов, собранные загрузчиком
+    pub fn metadata(&self) -> RwLockReadGuard<'_, MetadataCache> {
+        self.metadata.read().unwrap()
+    }
+
+    pub(crate) fn metadata_mut(&self) -> RwLockWriteGuard<'_, MetadataCache> {
+        self.metadata.write().unwrap()
+    }
+
+    /// Сохранить метаданные пулов для следующего запуска
+    pub fn save_metadata<P: AsRef<Path>>(&self, path: P) -> Result<(), ArbError> {
+        self.metadata().save(path)
+    }
+
+    /// Доступ на чтение ко всему кэшу — адаптеры строятся и обновляются из него
+    pub fn read(&self) -> RwLockReadGuard<'_, AccountSnapshot> {
+        self.accounts.read().unwrap()
+    }
+
+    /// Записать аккаунты снапшота в кэш
+    pub fn update(&self, snapshot: AccountSnapshot) {
+        self.accounts.write().unwrap().extend(snapshot);
+    }
+
+    /// Записать один аккаунт, полученный в слоте `slot`
+    pub fn insert(&self, pubkey: Pubkey, account: Account, slot: u64) {
+        self.accounts.write().unwrap().insert(pubkey, account, slot);
+    }
+
+    pub fn get(&self, pubkey: &Pubkey) -> Option<Account> {
+        self.read().get(pubkey).cloned()
+    }
+
+    /// Слот, в котором получена закэшированная версия аккаунта
+    pub fn slot(&self, pubkey: &Pubkey) -> Option<u64> {
+        self.read().slot(pubkey)
+    }
+
+    pub fn contains(&self, pubkey: &Pubkey) -> bool {
+        self.read().contains_key(pubkey)
+    }
+
+    /// Наибольший слот среди закэшированных аккаунтов
+    pub fn max_slot(&self) -> Option<u64> {
+        self.read().max_slot()
+    }
+
+    pub fn len(&self) -> usize {
+        self.read().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.read().is_empty()
+    }
+
+    /// Ключи, которых ещё нет в кэше
+    pub fn missing(&self, pubkeys: &[Pubkey]) -> Vec<Pubkey> {
+        let accounts = self.read();
+        pubkeys.iter().copied().filter(|key| !accounts.contains_key(key)).collect()
+    }
+
+    /// Догрузить аккаунты, которых ещё нет в кэше.
+    ///
+    /// Запрос идёт с `min_context_slot` не ниже самого свежего слота кэша.
+    pub fn load_missing(&self, provider: &dyn AccountProvider, pubkeys: &[Pubkey]) -> Result<(), ArbError> {
+        let missing = self.missing(pubkeys);
+        if missing.is_empty() {
+            return Ok(());
+        }
+
+        self.update(fetch_snapshot(provider, &missing, self.max_slot())?);
+        Ok(())
+    }
+
+    /// Перезапросить указанные аккаунты, даже если они уже есть в кэше
+    pub fn refresh(&self, provider: &dyn AccountProvider, pubkeys: &[Pubkey]) -> Result<(), ArbError> {
+        let min_context_slot = self.read().max_slot_of(pubkeys);
+        self.update(fetch_snapshot(provider, pubkeys, min_context_slot)?);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::provider::InMemoryAccountProvider;
+
+    #[test]
+    fn test_cache_loads_missing_and_refreshes() {
+        let (mint, vault) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let mut provider = InMemoryAccountProvider::new(100);
+        provider.insert(mint, Account { lamports: 1, ..Account::default() });
+        provider.insert(vault, Account { lamports: 10, ..Account::default() });
+
+        let cache = AccountCache::new();
+        cache.load_missing(&provider, &[mint, vault]).unwrap();
+        assert_eq!(cache.len(), 2);
+
+        // Уже закэшированный аккаунт повторно не запрашивается
+        provider.insert(vault, Account { lamports: 20, ..Account::default() });
+        provider.set_slot(101);
+        cache.load_missing(&provider, &[mint, vault]).unwrap();
+        assert_eq!(cache.get(&vault).unwrap().lamports, 10);
+        assert_eq!(cache.slot(&vault), Some(100));
+
+        cache.refresh(&provider, &[vault]).unwrap();
+        assert_eq!(cache.get(&vault).unwrap().lamports, 20);
+        assert_eq!(cache.slot(&vault), Some(101));
+        assert_eq!(cache.slot(&mint), Some(100));
+
+        // Источник, отстающий от кэша, не откатывает данные назад
+        provider.set_slot(99);
+        assert!(matches!(cache.refresh(&provider, &[vault]), Err(ArbError::StaleSnapshot(_))));
+        assert_eq!(cache.get(&vault).unwrap().lamports, 20);
+    }
+}
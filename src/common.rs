@@ -1,19 +1,71 @@
 use solana_sdk::pubkey::Pubkey;
 use solana_sdk::account::Account;
-use solana_client::rpc_client::RpcClient;
-use std::str::FromStr;
+use crate::provider::{snapshot_account, AccountSnapshot};
+use crate::error::ArbError;
 
 // Чтение u64 (LE)
 pub fn read_u64(data: &[u8], offset: usize) -> u64 {
     u64::from_le_bytes(data[offset..offset + 8].try_into().unwrap())
 }
 
-// Чтение amount из SPL Token Account
-pub fn read_spl_amount(acc: &Account) -> u64 {
-    read_u64(&acc.data, 64)
+// Чтение amount из SPL Token Account; закрытый аккаунт приходит с пустыми данными
+pub fn read_spl_amount(acc: &Account) -> Result<u64, ArbError> {
+    if acc.data.len() < 72 {
+        return Err(ArbError::Decode("Invalid data length for SPL token account".into()));
+    }
+    Ok(read_u64(&acc.data, 64))
 }
 
 // Чтение decimals из Mint Account
 pub fn read_mint_decimals(acc: &Account) -> u8 {
     acc.data[44]
-}
\ No newline at end of file
+}
+
+// Чтение u16 (LE)
+pub fn read_u16(data: &[u8], offset: usize) -> u16 {
+    u16::from_le_bytes(data[offset..offset + 2].try_into().unwrap())
+}
+
+// Чтение u32 (LE)
+pub fn read_u32(data: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes(data[offset..offset + 4].try_into().unwrap())
+}
+
+// Чтение u128 (LE)
+pub fn read_u128(data: &[u8], offset: usize) -> u128 {
+    u128::from_le_bytes(data[offset..offset + 16].try_into().unwrap())
+}
+
+// Чтение Pubkey (32 байта)
+pub fn read_pubkey(data: &[u8], offset: usize) -> Pubkey {
+    Pubkey::new_from_array(data[offset..offset + 32].try_into().unwrap())
+}
+
+/// Срез sysvar Clock, нужный для расчёта комиссий и рампов, зависящих от времени
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct ClockInfo {
+    pub slot: u64,
+    pub epoch: u64,
+    pub unix_timestamp: i64,
+}
+
+impl ClockInfo {
+    // Разбор sysvar Clock (bincode: slot, epoch_start_timestamp, epoch, leader_schedule_epoch, unix_timestamp)
+    pub fn from_account_data(data: &[u8]) -> Result<Self, ArbError> {
+        if data.len() < 40 {
+            return Err(ArbError::Decode("Invalid data length for Clock sysvar".into()));
+        }
+
+        Ok(Self {
+            slot: read_u64(data, 0),
+            epoch: read_u64(data, 16),
+            unix_timestamp: read_u64(data, 32) as i64,
+        })
+    }
+}
+
+// Чтение sysvar Clock из снапшота аккаунтов
+pub fn snapshot_clock(accounts: &AccountSnapshot) -> Result<ClockInfo, ArbError> {
+    let acc = snapshot_account(accounts, &solana_sdk::sysvar::clock::id())?;
+    ClockInfo::from_account_data(&acc.data)
+}
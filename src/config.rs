@@ -1,12 +1,21 @@
+use log::warn;
 use serde::Deserialize;
 use std::{path::Path, collections::HashMap, sync::Arc};
-use solana_client::rpc_client::RpcClient;
+use crate::cache::AccountCache;
+use crate::provider::AccountProvider;
 use solana_sdk::pubkey::Pubkey;
 
 use crate::dex::PoolMints;
-use crate::dex::raydium::amm::RaydiumAmmPoolInfo;
-use crate::dex::raydium::clmm::RaydiumClmmPoolInfo;
-use crate::dex::meteora::dlmm::MeteoraDlmmPoolInfo;
+use crate::pipeline::PipelineConfig;
+use crate::pool_registry::PoolRegistry;
+use crate::rpc::RpcConfig;
+use crate::stream::StreamConfig;
+use crate::cycles::MAX_DEPTH;
+use crate::daemon::DaemonConfig;
+use crate::geyser::GeyserConfig;
+use crate::loader::{load_pools, load_pools_by_address, PoolKind};
+use crate::metadata::DEFAULT_METADATA_PATH;
+use crate::error::ArbError;
 
 #[derive(Debug, Deserialize)]
 pub struct PoolConfig {
@@ -17,60 +26,163 @@ pub struct PoolConfig {
     pub raydium_clmm: Vec<String>,
     #[serde(default)]
     pub meteora_dlmm: Vec<String>,
+    #[serde(default)]
+    pub meteora_dbc: Vec<String>,
+    #[serde(default)]
+    pub phoenix: Vec<String>,
+    #[serde(default)]
+    pub openbook: Vec<String>,
+    #[serde(default)]
+    pub saber: Vec<String>,
+    #[serde(default)]
+    pub stake_pool: Vec<String>,
+}
+
+/// Допустимый по умолчанию разброс слотов между пулами одной цепочки
+pub const DEFAULT_MAX_SLOT_SPREAD: u64 = 2;
+
+fn default_max_slot_spread() -> u64 {
+    DEFAULT_MAX_SLOT_SPREAD
+}
+
+fn default_max_depth() -> usize {
+    MAX_DEPTH
+}
+
+fn default_metadata_cache() -> String {
+    DEFAULT_METADATA_PATH.to_string()
 }
 
 #[derive(Debug, Deserialize)]
 pub struct Config {
+    /// Максимальная разница слотов состояния между пулами одной цепочки (кроме потоковых режимов)
+    #[serde(default = "default_max_slot_spread")]
+    pub max_slot_spread: u64,
+    /// Максимум обменов (свапов) в цепочке
+    #[serde(default = "default_max_depth")]
+    pub max_depth: usize,
+    /// Адреса пулов без указания типа: адаптер определяется по программе-владельцу
+    #[serde(default)]
+    pub pool_addresses: Vec<String>,
+    /// Файл кэша статических метаданных пулов
+    #[serde(default = "default_metadata_cache")]
+    pub metadata_cache: String,
+    /// RPC endpoint'ы, лимиты и повторы
+    #[serde(default)]
+    pub rpc: RpcConfig,
+    /// Параметры async-пайплайна
+    #[serde(default)]
+    pub pipeline: PipelineConfig,
+    /// Параметры режима демона
+    #[serde(default)]
+    pub daemon: DaemonConfig,
+    /// Параметры потокового режима
+    #[serde(default)]
+    pub stream: StreamConfig,
+    /// Параметры источника Yellowstone gRPC
+    #[serde(default)]
+    pub geyser: GeyserConfig,
+    #[serde(default)]
     pub pools: Vec<PoolConfig>,
 }
 
 impl Config {
-    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self, Box<dyn std::error::Error>> {
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self, ArbError> {
         let content = std::fs::read_to_string(path)?;
-        let cfg: Config = toml::from_str(&content)?;
+        Self::from_toml(&content)
+    }
+
+    /// Разобрать конфиг из TOML и проверить значения, которые не ограничены типами полей
+    pub fn from_toml(content: &str) -> Result<Self, ArbError> {
+        let cfg: Config = toml::from_str(content)?;
+        if cfg.max_depth == 0 {
+            return Err(ArbError::Config("max_depth must be at least 1".to_string()));
+        }
         Ok(cfg)
     }
 
-    /// Строит HashMap, где ключ - mint адрес, значение - вектор указателей на объекты трейта PoolMints
+    /// Строит реестр пулов: каждый пул хранится один раз и доступен под обоими своими mint
     /// 
-    /// Структура конфига: для каждого mint указываются списки пулов разных типов (raydium_amm, raydium_clmm, meteora_dlmm)
-    pub fn build_pools_hashmap(
+    /// Структура конфига: для каждого mint указываются списки пулов разных типов (raydium_amm, raydium_clmm, meteora_dlmm, meteora_dbc, phoenix, openbook, saber, stake_pool),
+    /// плюс плоский список `pool_addresses`, где тип определяется по программе-владельцу.
+    ///
+    /// Пулы загружаются пакетно через `load_pools`; загруженные аккаунты остаются в `cache` для последующих обновлений.
+    pub fn build_pool_registry(
         &self,
-        client: &RpcClient,
-    ) -> Result<HashMap<Pubkey, Vec<Arc<dyn PoolMints>>>, Box<dyn std::error::Error>> {
-        let mut pools_map: HashMap<Pubkey, Vec<Arc<dyn PoolMints>>> = HashMap::new();
+        provider: &dyn AccountProvider,
+        cache: &AccountCache,
+    ) -> Result<PoolRegistry, ArbError> {
+        let entries = self.typed_pools()?;
+        let requests: Vec<(PoolKind, Pubkey)> = entries.iter().map(|(_, kind, pubkey)| (*kind, *pubkey)).collect();
+        let loaded = load_pools(provider, cache, &requests)?;
+        let detected = load_pools_by_address(provider, cache, &self.parsed_pool_addresses()?)?;
+
+        Ok(assemble_registry(&entries, loaded, detected))
+    }
+
+    /// Пулы из типизированных списков: (mint секции, тип адаптера, адрес пула)
+    pub fn typed_pools(&self) -> Result<Vec<(Pubkey, PoolKind, Pubkey)>, ArbError> {
+        let mut entries: Vec<(Pubkey, PoolKind, Pubkey)> = Vec::new();
 
         for pool_config in &self.pools {
             let mint_key: Pubkey = pool_config.mint.parse()?;
-            let mut pools_for_mint: Vec<Arc<dyn PoolMints>> = Vec::new();
+            let lists = [
+                (PoolKind::RaydiumAmm, &pool_config.raydium_amm),
+                (PoolKind::RaydiumClmm, &pool_config.raydium_clmm),
+                (PoolKind::MeteoraDlmm, &pool_config.meteora_dlmm),
+                (PoolKind::MeteoraDbc, &pool_config.meteora_dbc),
+                (PoolKind::Phoenix, &pool_config.phoenix),
+                (PoolKind::Openbook, &pool_config.openbook),
+                (PoolKind::Saber, &pool_config.saber),
+                (PoolKind::StakePool, &pool_config.stake_pool),
+            ];
 
-            // Создаем AMM пулы
-            for amm_address in &pool_config.raydium_amm {
-                let pool_pubkey: Pubkey = amm_address.parse()?;
-                let amm_pool = RaydiumAmmPoolInfo::create(pool_pubkey, client)?;
-                pools_for_mint.push(Arc::new(amm_pool));
+            for (kind, addresses) in lists {
+                for address in addresses {
+                    entries.push((mint_key, kind, address.parse()?));
+                }
             }
+        }
 
-            // Создаем CLMM пулы
-            for clmm_address in &pool_config.raydium_clmm {
-                let pool_pubkey: Pubkey = clmm_address.parse()?;
-                let clmm_pool = RaydiumClmmPoolInfo::create(pool_pubkey, client)?;
-                pools_for_mint.push(Arc::new(clmm_pool));
-            }
+        Ok(entries)
+    }
 
-            // Создаем DLMM пулы
-            for dlmm_address in &pool_config.meteora_dlmm {
-                let pool_pubkey: Pubkey = dlmm_address.parse()?;
-                let dlmm_pool = MeteoraDlmmPoolInfo::create(pool_pubkey, client)?;
-                pools_for_mint.push(Arc::new(dlmm_pool));
-            }
+    /// Адреса из плоского списка `pool_addresses`
+    pub fn parsed_pool_addresses(&self) -> Result<Vec<Pubkey>, ArbError> {
+        Ok(self
+            .pool_addresses
+            .iter()
+            .map(|address| address.parse())
+            .collect::<Result<Vec<Pubkey>, _>>()?)
+    }
+}
 
-            // Добавляем все пулы для данного mint в HashMap
-            if !pools_for_mint.is_empty() {
-                pools_map.insert(mint_key, pools_for_mint);
-            }
+/// Собрать реестр из пулов типизированных списков и пулов, определённых по владельцу.
+///
+/// Пул, указанный под mint, которым он не торгует, остаётся в реестре, но с предупреждением.
+pub fn assemble_registry(
+    entries: &[(Pubkey, PoolKind, Pubkey)],
+    loaded: HashMap<Pubkey, Arc<dyn PoolMints>>,
+    detected: HashMap<Pubkey, Arc<dyn PoolMints>>,
+) -> PoolRegistry {
+    for (mint_key, _, pool_pubkey) in entries {
+        let pool = &loaded[pool_pubkey];
+        if pool.mint_a() != mint_key && pool.mint_b() != mint_key {
+            warn!("Pool {} is listed under mint {} but trades {} / {}", pool_pubkey, mint_key, pool.mint_a(), pool.mint_b());
         }
+    }
+
+    loaded.into_values().chain(detected.into_values()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-        Ok(pools_map)
+    #[test]
+    fn test_config_rejects_zero_max_depth() {
+        assert_eq!(Config::from_toml("").unwrap().max_depth, MAX_DEPTH);
+        assert_eq!(Config::from_toml("max_depth = 6").unwrap().max_depth, 6);
+        assert!(matches!(Config::from_toml("max_depth = 0"), Err(ArbError::Config(_))));
     }
 }
@@ -0,0 +1,296 @@
+use solana_sdk::pubkey::Pubkey;
+use std::collections::HashMap;
+use std::ops::Range;
+use std::sync::Arc;
+
+use crate::dex::PoolMints;
+use crate::pool_registry::PoolRegistry;
+
+/// Максимум обменов (свапов) в цепочке по умолчанию
+pub const MAX_DEPTH: usize = 4;
+
+/// Шаг цикла: номер пула в реестре и направление свопа
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Hop {
+    pub pool: u32,
+    /// `true` — mint_a -> mint_b, `false` — mint_b -> mint_a
+    pub a_to_b: bool,
+}
+
+/// Предвычисленные циклы одного стартового mint.
+///
+/// Топология циклов зависит только от набора пулов, а не от их состояния, поэтому она
+/// перечисляется один раз; каждый скан лишь перекотирует готовые последовательности.
+/// Циклы хранятся подряд в одном массиве шагов в порядке обхода DFS: циклы с общим первым
+/// пулом образуют непрерывную группу, а соседние циклы группы часто делят префикс.
+///
+/// Правила те же, что у поиска: не более `max_depth` свопов (по умолчанию [`MAX_DEPTH`]), пулы в цикле не повторяются,
+/// цикл заканчивается при первом возвращении к стартовому mint.
+#[derive(Clone, Debug, Default)]
+pub struct CycleIndex {
+    start_mint: Pubkey,
+    /// Число пулов реестра при построении: реестр только растёт, другое число означает новые пулы
+    pool_count: usize,
+    /// Предел числа свопов, с которым перечислялись циклы
+    max_depth: usize,
+    hops: Vec<Hop>,
+    /// Конец каждого цикла в `hops`
+    cycle_ends: Vec<u32>,
+    /// Конец каждой группы в `cycle_ends`
+    group_ends: Vec<u32>,
+    /// Обратный индекс: номера циклов, проходящих через пул, по возрастанию
+    by_pool: HashMap<Pubkey, Vec<u32>>,
+}
+
+impl CycleIndex {
+    /// Перечислить все циклы из `start_mint` по пулам реестра
+    pub fn build(start_mint: &Pubkey, registry: &PoolRegistry) -> Self {
+        Self::with_max_depth(start_mint, registry, MAX_DEPTH)
+    }
+
+    /// Перечислить все циклы не длиннее `max_depth` свопов
+    pub fn with_max_depth(start_mint: &Pubkey, registry: &PoolRegistry, max_depth: usize) -> Self {
+        Self::build_pruned(start_mint, registry, max_depth, &|_: &[Hop], _: &Pubkey| true)
+    }
+
+    /// Перечислить циклы не длиннее `max_depth` свопов, отсекая ветви обхода.
+    ///
+    /// `keep(path, token_out)` вызывается после каждого шага: `false` отбрасывает путь и все его
+    /// продолжения (для пути, вернувшегося к стартовому mint, — сам цикл). Если отсечение
+    /// зависит от состояния пулов, индекс годится только для этого состояния.
+    pub fn build_pruned<F>(start_mint: &Pubkey, registry: &PoolRegistry, max_depth: usize, keep: &F) -> Self
+    where
+        F: Fn(&[Hop], &Pubkey) -> bool,
+    {
+        let mut index = Self { start_mint: *start_mint, pool_count: registry.len(), max_depth, ..Self::default() };
+        let mut path: Vec<Hop> = Vec::with_capacity(max_depth);
+        let walk = Walk { registry, max_depth, keep };
+
+        for pool in registry.pools_for_mint(start_mint) {
+            if let Some(hop) = hop(registry, pool.pool_pubkey(), start_mint) {
+                index.walk(&walk, hop, &mut path);
+            }
+            if index.group_ends.last().copied().unwrap_or(0) as usize != index.cycle_ends.len() {
+                index.group_ends.push(index.cycle_ends.len() as u32);
+            }
+        }
+        index
+    }
+
+    // Шаг через пул и продолжение обхода с полученным mint
+    fn walk<F>(&mut self, walk: &Walk<'_, F>, hop: Hop, path: &mut Vec<Hop>)
+    where
+        F: Fn(&[Hop], &Pubkey) -> bool,
+    {
+        if path.iter().any(|used| used.pool == hop.pool) {
+            return;
+        }
+
+        let registry = walk.registry;
+        let pool = registry.pool(hop.pool).unwrap();
+        let token_out = if hop.a_to_b { *pool.mint_b() } else { *pool.mint_a() };
+        path.push(hop);
+        if !(walk.keep)(path, &token_out) {
+            path.pop();
+            return;
+        }
+
+        if token_out == self.start_mint {
+            let cycle = self.cycle_ends.len() as u32;
+            for used in path.iter() {
+                let pubkey = *registry.pool(used.pool).unwrap().pool_pubkey();
+                self.by_pool.entry(pubkey).or_default().push(cycle);
+            }
+            self.hops.extend_from_slice(path);
+            self.cycle_ends.push(self.hops.len() as u32);
+        } else if path.len() < walk.max_depth {
+            for next in registry.pools_for_mint(&token_out) {
+                if let Some(next) = self::hop(registry, next.pool_pubkey(), &token_out) {
+                    self.walk(walk, next, path);
+                }
+            }
+        }
+
+        path.pop();
+    }
+
+    /// Циклы индекса, для которых `keep(cycle)` истинно, с прежним порядком и группами.
+    ///
+    /// Граф заново не обходится, поэтому отбор по состоянию пулов дешевле `build_pruned`.
+    pub fn filter<F>(&self, keep: F) -> Self
+    where
+        F: Fn(&[Hop]) -> bool,
+    {
+        let mut filtered =
+            Self { start_mint: self.start_mint, pool_count: self.pool_count, max_depth: self.max_depth, ..Self::default() };
+        // Новый номер каждого оставленного цикла
+        let mut renumbered: Vec<Option<u32>> = vec![None; self.len()];
+        for group in self.groups() {
+            for i in group {
+                let cycle = self.cycle(i);
+                if keep(cycle) {
+                    renumbered[i] = Some(filtered.cycle_ends.len() as u32);
+                    filtered.hops.extend_from_slice(cycle);
+                    filtered.cycle_ends.push(filtered.hops.len() as u32);
+                }
+            }
+            if filtered.group_ends.last().copied().unwrap_or(0) as usize != filtered.cycle_ends.len() {
+                filtered.group_ends.push(filtered.cycle_ends.len() as u32);
+            }
+        }
+        filtered.by_pool = self
+            .by_pool
+            .iter()
+            .filter_map(|(pubkey, cycles)| {
+                let kept: Vec<u32> = cycles.iter().filter_map(|i| renumbered[*i as usize]).collect();
+                (!kept.is_empty()).then_some((*pubkey, kept))
+            })
+            .collect();
+        filtered
+    }
+
+    pub fn start_mint(&self) -> &Pubkey {
+        &self.start_mint
+    }
+
+    /// Предел числа свопов в цикле
+    pub fn max_depth(&self) -> usize {
+        self.max_depth
+    }
+
+    /// Индекс построен по текущему набору пулов реестра
+    pub fn is_current(&self, registry: &PoolRegistry) -> bool {
+        self.pool_count == registry.len()
+    }
+
+    /// Число циклов
+    pub fn len(&self) -> usize {
+        self.cycle_ends.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cycle_ends.is_empty()
+    }
+
+    /// Шаги `i`-го цикла
+    pub fn cycle(&self, i: usize) -> &[Hop] {
+        let start = if i == 0 { 0 } else { self.cycle_ends[i - 1] as usize };
+        &self.hops[start..self.cycle_ends[i] as usize]
+    }
+
+    /// Все циклы в порядке обхода
+    pub fn cycles(&self) -> impl Iterator<Item = &[Hop]> {
+        (0..self.len()).map(|i| self.cycle(i))
+    }
+
+    /// Номера циклов, проходящих через пул
+    pub fn cycles_with_pool(&self, pubkey: &Pubkey) -> &[u32] {
+        self.by_pool.get(pubkey).map(Vec::as_slice).unwrap_or(&[])
+    }
+
+    /// Номера циклов, котировка которых могла измениться после обновления пулов, по возрастанию
+    pub fn affected(&self, updated: &[Arc<dyn PoolMints>]) -> Vec<usize> {
+        let mut cycles: Vec<usize> = updated
+            .iter()
+            .flat_map(|pool| self.cycles_with_pool(pool.pool_pubkey()))
+            .map(|i| *i as usize)
+            .collect();
+        cycles.sort_unstable();
+        cycles.dedup();
+        cycles
+    }
+
+    /// Диапазоны номеров циклов с общим первым пулом — независимые единицы перекотировки
+    pub fn groups(&self) -> impl Iterator<Item = Range<usize>> + '_ {
+        let starts = std::iter::once(0).chain(self.group_ends.iter().map(|end| *end as usize));
+        starts.zip(self.group_ends.iter().map(|end| *end as usize)).map(|(start, end)| start..end)
+    }
+}
+
+// Неизменяемые параметры обхода
+struct Walk<'a, F> {
+    registry: &'a PoolRegistry,
+    max_depth: usize,
+    keep: &'a F,
+}
+
+// Шаг из `mint` через пул; `None`, если пул не торгует этим mint
+fn hop(registry: &PoolRegistry, pool_pubkey: &Pubkey, mint: &Pubkey) -> Option<Hop> {
+    let index = registry.index_of(pool_pubkey)?;
+    let pool = registry.pool(index)?;
+    if pool.mint_a() == mint {
+        Some(Hop { pool: index, a_to_b: true })
+    } else if pool.mint_b() == mint {
+        Some(Hop { pool: index, a_to_b: false })
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::pool;
+
+    #[test]
+    fn test_cycle_index_enumerates_topology_once() {
+        let (sol, usdc, bonk) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+        // 0, 1: SOL/USDC; 2: USDC/BONK; 3: BONK/SOL
+        let mut registry: PoolRegistry =
+            vec![pool(sol, usdc), pool(usdc, sol), pool(usdc, bonk), pool(bonk, sol)].into_iter().collect();
+
+        let index = CycleIndex::build(&sol, &registry);
+        let cycles: Vec<Vec<(u32, bool)>> =
+            index.cycles().map(|cycle| cycle.iter().map(|hop| (hop.pool, hop.a_to_b)).collect()).collect();
+        assert_eq!(
+            cycles,
+            vec![
+                vec![(0, true), (1, true)],
+                vec![(0, true), (2, true), (3, true)],
+                vec![(1, false), (0, false)],
+                vec![(1, false), (2, true), (3, true)],
+                vec![(3, false), (2, false), (0, false)],
+                vec![(3, false), (2, false), (1, true)],
+            ]
+        );
+        assert_eq!(index.groups().collect::<Vec<_>>(), vec![0..2, 2..4, 4..6]);
+        assert!(index.is_current(&registry));
+
+        // Обратный индекс: пул 2 (USDC/BONK) входит во все треугольники
+        let usdc_bonk = Arc::clone(registry.pool(2).unwrap());
+        assert_eq!(index.cycles_with_pool(usdc_bonk.pool_pubkey()), &[1, 3, 4, 5]);
+        let sol_usdc = Arc::clone(registry.pool(0).unwrap());
+        assert_eq!(index.affected(&[sol_usdc, usdc_bonk]), vec![0, 1, 2, 3, 4, 5]);
+        assert_eq!(index.affected(&[pool(sol, bonk)]), Vec::<usize>::new());
+
+        // Новый пул меняет топологию — индекс надо перестроить
+        registry.insert(pool(bonk, usdc));
+        assert!(!index.is_current(&registry));
+        assert!(CycleIndex::build(&sol, &registry).len() > index.len());
+
+        // Mint без пулов — пустой индекс
+        assert!(CycleIndex::build(&Pubkey::new_unique(), &registry).is_empty());
+    }
+
+    #[test]
+    fn test_filter_matches_pruned_walk() {
+        let (sol, usdc, bonk) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+        let registry: PoolRegistry =
+            vec![pool(sol, usdc), pool(usdc, sol), pool(usdc, bonk), pool(bonk, sol)].into_iter().collect();
+        let index = CycleIndex::build(&sol, &registry);
+
+        // Без пула 1 (USDC/SOL): отбор из готового индекса совпадает с отсечением при обходе
+        let filtered = index.filter(|cycle| cycle.iter().all(|hop| hop.pool != 1));
+        let pruned = CycleIndex::build_pruned(&sol, &registry, MAX_DEPTH, &|path: &[Hop], _: &Pubkey| {
+            path.last().is_some_and(|hop| hop.pool != 1)
+        });
+        assert_eq!(filtered.cycles().collect::<Vec<_>>(), pruned.cycles().collect::<Vec<_>>());
+        assert_eq!(filtered.groups().collect::<Vec<_>>(), vec![0..1, 1..2]);
+        assert_eq!(filtered.groups().collect::<Vec<_>>(), pruned.groups().collect::<Vec<_>>());
+        for pool in registry.pools() {
+            assert_eq!(filtered.cycles_with_pool(pool.pool_pubkey()), pruned.cycles_with_pool(pool.pool_pubkey()));
+        }
+        assert!(filtered.is_current(&registry));
+        assert_eq!(filtered.max_depth(), MAX_DEPTH);
+    }
+}
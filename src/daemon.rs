@@ -0,0 +1,236 @@
+use log::{info, warn};
+use serde::Deserialize;
+use std::fmt;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::watch;
+
+use crate::cache::AccountCache;
+use crate::dex::PoolMints;
+use crate::error::ArbError;
+use crate::pipeline::{refresh_pools_async, AsyncAccountProvider, PipelineConfig};
+use crate::pool_registry::PoolRegistry;
+
+/// Период циклов по умолчанию, мс
+pub const DEFAULT_INTERVAL_MS: u64 = 1_000;
+
+/// Параметры режима демона (секция `[daemon]` конфига)
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct DaemonConfig {
+    /// Повторять циклы обновление → поиск → отчёт до сигнала остановки
+    pub enabled: bool,
+    /// Период между началами циклов, мс; цикл дольше периода сразу сменяется следующим
+    pub interval_ms: u64,
+}
+
+impl Default for DaemonConfig {
+    fn default() -> Self {
+        Self { enabled: false, interval_ms: DEFAULT_INTERVAL_MS }
+    }
+}
+
+impl DaemonConfig {
+    pub fn interval(&self) -> Duration {
+        Duration::from_millis(self.interval_ms)
+    }
+}
+
+/// Запрос остановки, общий для обработчика сигналов и рабочих циклов.
+///
+/// Остановка кооперативная: текущий цикл доводится до конца, новый не начинается.
+#[derive(Clone, Debug)]
+pub struct Shutdown {
+    requested: Arc<watch::Sender<bool>>,
+}
+
+impl Default for Shutdown {
+    fn default() -> Self {
+        Self { requested: Arc::new(watch::Sender::new(false)) }
+    }
+}
+
+impl Shutdown {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn trigger(&self) {
+        self.requested.send_replace(true);
+    }
+
+    pub fn is_requested(&self) -> bool {
+        *self.requested.borrow()
+    }
+
+    /// Дождаться запроса остановки; сразу завершается, если он уже был
+    pub async fn wait(&self) {
+        let mut requested = self.requested.subscribe();
+        // Отправитель живёт в самом `Shutdown`, поэтому канал не закрывается
+        let _ = requested.wait_for(|requested| *requested).await;
+    }
+
+    /// Запросить остановку по SIGINT или SIGTERM
+    pub fn listen_for_signals(&self) {
+        let shutdown = self.clone();
+        tokio::spawn(async move {
+            wait_for_signal().await;
+            info!("Shutdown requested, finishing in-flight work");
+            shutdown.trigger();
+        });
+    }
+}
+
+#[cfg(unix)]
+async fn wait_for_signal() {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    match signal(SignalKind::terminate()) {
+        Ok(mut terminate) => {
+            tokio::select! {
+                _ = tokio::signal::ctrl_c() => {}
+                _ = terminate.recv() => {}
+            }
+        }
+        Err(e) => {
+            warn!("Failed to install SIGTERM handler: {}, only SIGINT stops the daemon", e);
+            let _ = tokio::signal::ctrl_c().await;
+        }
+    }
+}
+
+#[cfg(not(unix))]
+async fn wait_for_signal() {
+    let _ = tokio::signal::ctrl_c().await;
+}
+
+/// Время и результат одного цикла
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct CycleReport {
+    pub cycle: u64,
+    pub refresh: Duration,
+    pub search: Duration,
+    pub chains: usize,
+    /// Пачки аккаунтов, пропущенные при обновлении
+    pub skipped_batches: usize,
+}
+
+impl CycleReport {
+    pub fn total(&self) -> Duration {
+        self.refresh + self.search
+    }
+}
+
+impl fmt::Display for CycleReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "cycle {}: {} chains in {:?} (refresh {:?}, search {:?}), {} skipped batches",
+            self.cycle,
+            self.chains,
+            self.total(),
+            self.refresh,
+            self.search,
+            self.skipped_batches
+        )
+    }
+}
+
+/// Режим демона: циклы обновление → поиск → отчёт с периодом `config.interval()`.
+///
+/// Ошибка цикла не останавливает демон: она пишется в лог, следующий цикл идёт по расписанию.
+/// `search` ищет цепочки по обновлённому реестру, `on_cycle` получает отчёт о каждом
+/// завершённом цикле. После запроса остановки текущий цикл доводится до конца;
+/// возвращается число выполненных циклов.
+#[allow(clippy::too_many_arguments)]
+pub async fn run_daemon<P, S, R>(
+    config: &DaemonConfig,
+    registry: &PoolRegistry,
+    cache: &AccountCache,
+    provider: &P,
+    pipeline: &PipelineConfig,
+    shutdown: &Shutdown,
+    mut search: S,
+    mut on_cycle: R,
+) -> Result<u64, ArbError>
+where
+    P: AsyncAccountProvider,
+    S: FnMut() -> Result<Vec<Vec<Arc<dyn PoolMints>>>, ArbError>,
+    R: FnMut(&CycleReport),
+{
+    let mut cycle = 0;
+    while !shutdown.is_requested() {
+        let started = Instant::now();
+        cycle += 1;
+
+        let mut report = CycleReport { cycle, ..CycleReport::default() };
+        match refresh_pools_async(registry, provider, cache, pipeline).await {
+            Ok(skipped_batches) => {
+                report.skipped_batches = skipped_batches;
+                report.refresh = started.elapsed();
+                let search_started = Instant::now();
+                match search() {
+                    Ok(chains) => {
+                        report.search = search_started.elapsed();
+                        report.chains = chains.len();
+                        on_cycle(&report);
+                    }
+                    Err(e) => warn!("Cycle {}: search failed: {}", cycle, e),
+                }
+            }
+            Err(e) => warn!("Cycle {}: refresh failed: {}", cycle, e),
+        }
+
+        tokio::select! {
+            _ = tokio::time::sleep(config.interval().saturating_sub(started.elapsed())) => {}
+            _ = shutdown.wait() => {}
+        }
+    }
+
+    info!("Daemon stopped after {} cycles", cycle);
+    Ok(cycle)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::provider::InMemoryAccountProvider;
+
+    #[tokio::test]
+    async fn test_daemon_finishes_cycle_and_stops_on_shutdown() {
+        let registry: PoolRegistry = Vec::<Arc<dyn PoolMints>>::new().into_iter().collect();
+        let provider = InMemoryAccountProvider::new(100);
+        let (cache, pipeline) = (AccountCache::new(), PipelineConfig::default());
+        let shutdown = Shutdown::new();
+
+        // Остановка посреди третьего цикла: цикл доводится до отчёта, четвёртый не начинается
+        let config = DaemonConfig { enabled: true, interval_ms: 0 };
+        let mut reports = Vec::new();
+        let mut searches = 0;
+        let search = || {
+            searches += 1;
+            if searches == 3 {
+                shutdown.trigger();
+            }
+            Ok(Vec::new())
+        };
+        let on_cycle = |report: &CycleReport| reports.push(report.cycle);
+        let cycles = run_daemon(&config, &registry, &cache, &provider, &pipeline, &shutdown, search, on_cycle)
+            .await
+            .unwrap();
+        assert_eq!(cycles, 3);
+        assert_eq!(reports, vec![1, 2, 3]);
+
+        // Запрос во время ожидания следующего цикла прерывает паузу
+        let shutdown = Shutdown::new();
+        let config = DaemonConfig { enabled: true, interval_ms: 3_600_000 };
+        let trigger = shutdown.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            trigger.trigger();
+        });
+        let run = run_daemon(&config, &registry, &cache, &provider, &pipeline, &shutdown, || Ok(Vec::new()), |_: &CycleReport| {});
+        let cycles = tokio::time::timeout(Duration::from_secs(5), run).await.unwrap().unwrap();
+        assert_eq!(cycles, 1);
+    }
+}
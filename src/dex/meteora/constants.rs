@@ -17,6 +17,10 @@ pub fn vault_program_id() -> Pubkey {
     Pubkey::from_str("24Uqj9JCLxUeoC3hGfh5W3s9FM9uCHDS2SG3LYwBpyTi").unwrap()
 }
 
+pub fn dbc_program_id() -> Pubkey {
+    Pubkey::from_str("dbcij3LWUppWqq96dh6gJWwBifmcGfLSB5D4DuSMaqN").unwrap()
+}
+
 pub fn damm_v2_program_id() -> Pubkey {
     Pubkey::from_str("cpamdpZCGKUy5JxQXB4dcpGPiikHawvSWAd6mEn1sGG").unwrap()
 }
@@ -30,3 +34,6 @@ pub fn damm_v2_pool_authority() -> Pubkey {
 }
 
 pub const BIN_ARRAY: &[u8] = b"bin_array";
+
+/// Discriminator события `Swap`: sha256("event:Swap")[..8]
+pub const DLMM_SWAP_EVENT_DISCRIMINATOR: [u8; 8] = [81, 108, 227, 190, 205, 208, 10, 196];
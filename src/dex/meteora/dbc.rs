@@ -0,0 +1,772 @@
+use crate::common::{snapshot_clock, read_pubkey, read_u128, read_u16, read_u32, read_u64, ClockInfo};
+use crate::dex::{load_pool, PoolLoader, PoolMints, StateSlot};
+use crate::provider::{snapshot_account, AccountProvider, AccountSnapshot};
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::sysvar::clock;
+use spl_math::uint::U256;
+use std::sync::RwLock;
+use log::debug;
+use crate::error::ArbError;
+
+/*
+Meteora Dynamic Bonding Curve (DBC) — стартовая кривая токена до миграции в DAMM.
+Кривая задаётся набором точек (sqrt_price, liquidity): сегмент i действует на
+интервале (curve[i-1].sqrt_price, curve[i].sqrt_price], для сегмента 0 нижняя граница — sqrt_start_price.
+Внутри сегмента работает математика концентрированной ликвидности (как в DAMM v2),
+sqrt_price хранится в Q64.64, liquidity масштабирована на 2^64.
+Base — токен кривой, quote — токен, в котором растёт цена (обычно WSOL).
+*/
+
+/// Знаменатель комиссий DBC (fee_numerator / 10^9)
+pub const FEE_DENOMINATOR: u64 = 1_000_000_000;
+/// Максимальная комиссия — 99%
+pub const MAX_FEE_NUMERATOR: u64 = 990_000_000;
+/// Количество точек кривой в PoolConfig
+pub const MAX_CURVE_POINT: usize = 20;
+const BASIS_POINT_MAX: u64 = 10_000;
+
+// Offsets внутри аккаунта VirtualPool (с учётом 8 байт discriminator).
+// volatility_tracker: last_update_timestamp(8) + padding(8) + sqrt_price_reference(16)
+//                     + volatility_accumulator(16) + volatility_reference(16)
+const POOL_VOLATILITY_ACCUMULATOR_OFFSET: usize = 40;
+const POOL_CONFIG_OFFSET: usize = 72;
+const POOL_BASE_MINT_OFFSET: usize = 136;
+const POOL_BASE_VAULT_OFFSET: usize = 168;
+const POOL_QUOTE_VAULT_OFFSET: usize = 200;
+const POOL_BASE_RESERVE_OFFSET: usize = 232;
+const POOL_QUOTE_RESERVE_OFFSET: usize = 240;
+const POOL_SQRT_PRICE_OFFSET: usize = 280;
+const POOL_ACTIVATION_POINT_OFFSET: usize = 296;
+const POOL_IS_MIGRATED_OFFSET: usize = 305;
+const VIRTUAL_POOL_MIN_LEN: usize = 312;
+
+// Offsets внутри аккаунта PoolConfig (с учётом 8 байт discriminator).
+const CONFIG_QUOTE_MINT_OFFSET: usize = 8;
+// pool_fees.base_fee
+const CONFIG_CLIFF_FEE_NUMERATOR_OFFSET: usize = 104;
+const CONFIG_SECOND_FACTOR_OFFSET: usize = 112;
+const CONFIG_THIRD_FACTOR_OFFSET: usize = 120;
+const CONFIG_FIRST_FACTOR_OFFSET: usize = 128;
+const CONFIG_BASE_FEE_MODE_OFFSET: usize = 130;
+// pool_fees.dynamic_fee
+const CONFIG_DYNAMIC_FEE_INITIALIZED_OFFSET: usize = 136;
+const CONFIG_MAX_VOLATILITY_ACCUMULATOR_OFFSET: usize = 144;
+const CONFIG_VARIABLE_FEE_CONTROL_OFFSET: usize = 148;
+const CONFIG_BIN_STEP_OFFSET: usize = 152;
+const CONFIG_COLLECT_FEE_MODE_OFFSET: usize = 232;
+const CONFIG_ACTIVATION_TYPE_OFFSET: usize = 234;
+const CONFIG_TOKEN_DECIMAL_OFFSET: usize = 235;
+const CONFIG_MIGRATION_QUOTE_THRESHOLD_OFFSET: usize = 264;
+const CONFIG_MIGRATION_SQRT_PRICE_OFFSET: usize = 280;
+const CONFIG_SQRT_START_PRICE_OFFSET: usize = 392;
+const CONFIG_CURVE_OFFSET: usize = 408;
+const CURVE_POINT_SIZE: usize = 32;
+const POOL_CONFIG_MIN_LEN: usize = CONFIG_CURVE_OFFSET + MAX_CURVE_POINT * CURVE_POINT_SIZE;
+
+/// Режим базовой комиссии (BaseFeeMode)
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BaseFeeMode {
+    /// Комиссия линейно снижается на reduction_factor каждый период
+    FeeSchedulerLinear,
+    /// Комиссия снижается в (1 - reduction_factor / 10_000) раз каждый период
+    FeeSchedulerExponential,
+    /// Комиссия зависит от размера свопа (rate limiter)
+    RateLimiter,
+}
+
+/// Базовая комиссия (fee scheduler) из PoolConfig
+#[derive(Clone, Copy, Debug)]
+pub struct BaseFeeConfig {
+    pub cliff_fee_numerator: u64,
+    /// Для fee scheduler — number_of_period
+    pub first_factor: u16,
+    /// Для fee scheduler — period_frequency
+    pub second_factor: u64,
+    /// Для fee scheduler — reduction_factor
+    pub third_factor: u64,
+    pub base_fee_mode: BaseFeeMode,
+}
+
+/// Динамическая (волатильностная) комиссия из PoolConfig
+#[derive(Clone, Copy, Debug)]
+pub struct DynamicFeeConfig {
+    pub initialized: bool,
+    pub max_volatility_accumulator: u32,
+    pub variable_fee_control: u32,
+    pub bin_step: u16,
+}
+
+/// Точка кривой ликвидности: до sqrt_price действует liquidity
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct LiquidityDistributionPoint {
+    pub sqrt_price: u128,
+    pub liquidity: u128,
+}
+
+/// Способ взимания комиссии (collect_fee_mode)
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CollectFeeMode {
+    /// Комиссия всегда берётся в quote-токене
+    QuoteToken,
+    /// Комиссия берётся с выходного токена
+    OutputToken,
+}
+
+/// Декодированный PoolConfig DBC — параметры кривой и комиссий, общие для пулов одного конфига
+#[derive(Clone, Debug)]
+pub struct DbcConfig {
+    pub quote_mint: Pubkey,
+    pub base_fee: BaseFeeConfig,
+    pub dynamic_fee: DynamicFeeConfig,
+    pub collect_fee_mode: CollectFeeMode,
+    /// 0 — activation_point в слотах, 1 — в unix timestamp
+    pub activation_type: u8,
+    pub token_decimal: u8,
+    pub migration_quote_threshold: u64,
+    pub migration_sqrt_price: u128,
+    pub sqrt_start_price: u128,
+    pub curve: Vec<LiquidityDistributionPoint>,
+}
+
+/// Декодированное состояние VirtualPool
+#[derive(Clone, Debug)]
+pub struct VirtualPoolState {
+    pub config: Pubkey,
+    pub base_mint: Pubkey,
+    pub base_vault: Pubkey,
+    pub quote_vault: Pubkey,
+    pub base_reserve: u64,
+    pub quote_reserve: u64,
+    pub sqrt_price: u128,
+    pub activation_point: u64,
+    pub is_migrated: bool,
+    pub volatility_accumulator: u128,
+}
+
+impl DbcConfig {
+    pub fn load_checked(data: &[u8]) -> Result<Self, ArbError> {
+        if data.len() < POOL_CONFIG_MIN_LEN {
+            return Err(ArbError::Decode("Invalid data length for DBC PoolConfig".into()));
+        }
+
+        let base_fee_mode = match data[CONFIG_BASE_FEE_MODE_OFFSET] {
+            0 => BaseFeeMode::FeeSchedulerLinear,
+            1 => BaseFeeMode::FeeSchedulerExponential,
+            2 => BaseFeeMode::RateLimiter,
+            _ => return Err(ArbError::Decode("Unknown DBC base fee mode".into())),
+        };
+
+        let collect_fee_mode = match data[CONFIG_COLLECT_FEE_MODE_OFFSET] {
+            0 => CollectFeeMode::QuoteToken,
+            1 => CollectFeeMode::OutputToken,
+            _ => return Err(ArbError::Decode("Unknown DBC collect fee mode".into())),
+        };
+
+        let mut curve = Vec::with_capacity(MAX_CURVE_POINT);
+        for i in 0..MAX_CURVE_POINT {
+            let offset = CONFIG_CURVE_OFFSET + i * CURVE_POINT_SIZE;
+            curve.push(LiquidityDistributionPoint {
+                sqrt_price: read_u128(data, offset),
+                liquidity: read_u128(data, offset + 16),
+            });
+        }
+
+        Ok(Self {
+            quote_mint: read_pubkey(data, CONFIG_QUOTE_MINT_OFFSET),
+            base_fee: BaseFeeConfig {
+                cliff_fee_numerator: read_u64(data, CONFIG_CLIFF_FEE_NUMERATOR_OFFSET),
+                first_factor: read_u16(data, CONFIG_FIRST_FACTOR_OFFSET),
+                second_factor: read_u64(data, CONFIG_SECOND_FACTOR_OFFSET),
+                third_factor: read_u64(data, CONFIG_THIRD_FACTOR_OFFSET),
+                base_fee_mode,
+            },
+            dynamic_fee: DynamicFeeConfig {
+                initialized: data[CONFIG_DYNAMIC_FEE_INITIALIZED_OFFSET] != 0,
+                max_volatility_accumulator: read_u32(data, CONFIG_MAX_VOLATILITY_ACCUMULATOR_OFFSET),
+                variable_fee_control: read_u32(data, CONFIG_VARIABLE_FEE_CONTROL_OFFSET),
+                bin_step: read_u16(data, CONFIG_BIN_STEP_OFFSET),
+            },
+            collect_fee_mode,
+            activation_type: data[CONFIG_ACTIVATION_TYPE_OFFSET],
+            token_decimal: data[CONFIG_TOKEN_DECIMAL_OFFSET],
+            migration_quote_threshold: read_u64(data, CONFIG_MIGRATION_QUOTE_THRESHOLD_OFFSET),
+            migration_sqrt_price: read_u128(data, CONFIG_MIGRATION_SQRT_PRICE_OFFSET),
+            sqrt_start_price: read_u128(data, CONFIG_SQRT_START_PRICE_OFFSET),
+            curve,
+        })
+    }
+
+    /// Точка отсчёта для активации и fee scheduler: слот или timestamp в зависимости от activation_type
+    pub fn current_point(&self, clock: &ClockInfo) -> u64 {
+        if self.activation_type == 0 {
+            clock.slot
+        } else {
+            clock.unix_timestamp.max(0) as u64
+        }
+    }
+
+    /// Базовая комиссия (числитель к FEE_DENOMINATOR) в момент current_point
+    pub fn base_fee_numerator(&self, current_point: u64, activation_point: u64) -> Result<u64, ArbError> {
+        let fee = &self.base_fee;
+        if fee.base_fee_mode == BaseFeeMode::RateLimiter {
+            // Rate limiter действует только в окне max_limiter_duration после активации,
+            // дальше комиссия равна cliff_fee_numerator.
+            if current_point >= activation_point.saturating_add(fee.second_factor) {
+                return Ok(fee.cliff_fee_numerator);
+            }
+            return Err(ArbError::PoolDisabled("DBC rate limiter fee mode is not supported".into()));
+        }
+
+        let number_of_period = fee.first_factor as u64;
+        let period_frequency = fee.second_factor;
+        let reduction_factor = fee.third_factor;
+
+        // До активации действует максимальная (cliff) комиссия
+        let period = if current_point < activation_point || period_frequency == 0 {
+            0
+        } else {
+            ((current_point - activation_point) / period_frequency).min(number_of_period)
+        };
+
+        let numerator = match fee.base_fee_mode {
+            BaseFeeMode::FeeSchedulerLinear => fee
+                .cliff_fee_numerator
+                .saturating_sub(period.saturating_mul(reduction_factor)),
+            BaseFeeMode::FeeSchedulerExponential => {
+                if reduction_factor >= BASIS_POINT_MAX {
+                    0
+                } else {
+                    // cliff * (1 - reduction / 10_000)^period в Q64.64
+                    let base = ((BASIS_POINT_MAX - reduction_factor) as u128) << 64;
+                    let base = base / BASIS_POINT_MAX as u128;
+                    let factor = pow_q64(base, period);
+                    ((U256::from(fee.cliff_fee_numerator) * factor) >> 64).as_u64()
+                }
+            }
+            BaseFeeMode::RateLimiter => unreachable!(),
+        };
+
+        Ok(numerator)
+    }
+
+    /// Динамическая комиссия: ((volatility_accumulator * bin_step)^2 * variable_fee_control) / 10^11 (с округлением вверх)
+    pub fn variable_fee_numerator(&self, volatility_accumulator: u128) -> u64 {
+        if !self.dynamic_fee.initialized {
+            return 0;
+        }
+
+        let square_vfa_bin = U256::from(volatility_accumulator) * U256::from(self.dynamic_fee.bin_step);
+        let v_fee = square_vfa_bin * square_vfa_bin * U256::from(self.dynamic_fee.variable_fee_control);
+        let scaled = (v_fee + U256::from(99_999_999_999u64)) / U256::from(100_000_000_000u64);
+
+        if scaled > U256::from(u64::MAX) {
+            u64::MAX
+        } else {
+            scaled.as_u64()
+        }
+    }
+}
+
+impl VirtualPoolState {
+    pub fn load_checked(data: &[u8]) -> Result<Self, ArbError> {
+        if data.len() < VIRTUAL_POOL_MIN_LEN {
+            return Err(ArbError::Decode("Invalid data length for DBC VirtualPool".into()));
+        }
+
+        Ok(Self {
+            config: read_pubkey(data, POOL_CONFIG_OFFSET),
+            base_mint: read_pubkey(data, POOL_BASE_MINT_OFFSET),
+            base_vault: read_pubkey(data, POOL_BASE_VAULT_OFFSET),
+            quote_vault: read_pubkey(data, POOL_QUOTE_VAULT_OFFSET),
+            base_reserve: read_u64(data, POOL_BASE_RESERVE_OFFSET),
+            quote_reserve: read_u64(data, POOL_QUOTE_RESERVE_OFFSET),
+            sqrt_price: read_u128(data, POOL_SQRT_PRICE_OFFSET),
+            activation_point: read_u64(data, POOL_ACTIVATION_POINT_OFFSET),
+            is_migrated: data[POOL_IS_MIGRATED_OFFSET] != 0,
+            volatility_accumulator: read_u128(data, POOL_VOLATILITY_ACCUMULATOR_OFFSET),
+        })
+    }
+}
+
+/// Возведение Q64.64 числа в целую степень (square-and-multiply)
+fn pow_q64(base: u128, mut exp: u64) -> U256 {
+    let one = U256::one() << 64;
+    let mut result = one;
+    let mut base = U256::from(base);
+    while exp > 0 {
+        if exp & 1 == 1 {
+            result = (result * base) >> 64;
+        }
+        base = (base * base) >> 64;
+        exp >>= 1;
+    }
+    result
+}
+
+fn u256_to_u64(value: U256) -> Result<u64, ArbError> {
+    if value > U256::from(u64::MAX) {
+        return Err(ArbError::MathOverflow("DBC math overflow".into()));
+    }
+    Ok(value.as_u64())
+}
+
+/// Δbase = L * (upper - lower) / (lower * upper)
+fn get_delta_amount_base(lower: u128, upper: u128, liquidity: u128, round_up: bool) -> Result<u64, ArbError> {
+    let numerator = U256::from(liquidity) * U256::from(upper - lower);
+    let denominator = U256::from(lower) * U256::from(upper);
+    if denominator.is_zero() {
+        return Err(ArbError::MathOverflow("DBC sqrt price is 0".into()));
+    }
+    let (quotient, remainder) = numerator.div_mod(denominator);
+    let result = if round_up && !remainder.is_zero() { quotient + 1 } else { quotient };
+    u256_to_u64(result)
+}
+
+/// Δquote = L * (upper - lower) / 2^128
+fn get_delta_amount_quote(lower: u128, upper: u128, liquidity: u128, round_up: bool) -> Result<u64, ArbError> {
+    let product = U256::from(liquidity) * U256::from(upper - lower);
+    let result = if round_up {
+        (product + ((U256::one() << 128) - 1)) >> 128
+    } else {
+        product >> 128
+    };
+    u256_to_u64(result)
+}
+
+/// Новая sqrt_price после входа quote: sqrt + amount * 2^128 / L (цена растёт)
+fn get_next_sqrt_price_from_quote_in(sqrt_price: u128, liquidity: u128, amount: u64) -> Result<u128, ArbError> {
+    let quotient = (U256::from(amount) << 128) / U256::from(liquidity);
+    let next = U256::from(sqrt_price) + quotient;
+    if next > U256::from(u128::MAX) {
+        return Err(ArbError::MathOverflow("DBC math overflow".into()));
+    }
+    Ok(next.as_u128())
+}
+
+/// Новая sqrt_price после входа base: L * sqrt / (L + amount * sqrt) с округлением вверх (цена падает)
+fn get_next_sqrt_price_from_base_in(sqrt_price: u128, liquidity: u128, amount: u64) -> Result<u128, ArbError> {
+    if amount == 0 {
+        return Ok(sqrt_price);
+    }
+    let numerator = U256::from(liquidity) * U256::from(sqrt_price);
+    let denominator = U256::from(liquidity) + U256::from(amount) * U256::from(sqrt_price);
+    let (quotient, remainder) = numerator.div_mod(denominator);
+    let next = if remainder.is_zero() { quotient } else { quotient + 1 };
+    Ok(next.as_u128())
+}
+
+/// Комиссия с округлением вверх: amount * fee_numerator / FEE_DENOMINATOR
+fn fee_on_amount(amount: u64, fee_numerator: u64) -> u64 {
+    let fee = (amount as u128 * fee_numerator as u128).div_ceil(FEE_DENOMINATOR as u128);
+    fee as u64
+}
+
+/// Изменяемая часть DBC-пула, которая обновляется из снапшота
+#[derive(Clone, Debug)]
+pub struct DbcState {
+    pub pool: VirtualPoolState,
+    /// Текущий слот или timestamp (в зависимости от activation_type) на момент снапшота
+    pub current_point: u64,
+}
+
+/// Минимальная структура DBC-пула, достаточная для off-chain расчётов арбитража.
+#[derive(Debug)]
+pub struct MeteoraDbcPoolInfo {
+    pub pubkey: Pubkey,
+    mint_a: Pubkey,
+    mint_b: Pubkey,
+    pub config: DbcConfig,
+    state: RwLock<DbcState>,
+    /// Слот снапшота, к которому относится состояние
+    slot: StateSlot,
+}
+
+impl PoolMints for MeteoraDbcPoolInfo {
+    fn pool_pubkey(&self) -> &Pubkey {
+        &self.pubkey
+    }
+
+    fn mint_a(&self) -> &Pubkey {
+        &self.mint_a
+    }
+
+    fn mint_b(&self) -> &Pubkey {
+        &self.mint_b
+    }
+
+    fn required_accounts(&self) -> Vec<Pubkey> {
+        vec![self.pubkey, clock::id()]
+    }
+
+    fn ingest(&self, accounts: &AccountSnapshot) -> Result<(), ArbError> {
+        let mut state = self.state.write().unwrap();
+        if let Some(acc) = accounts.get(&self.pubkey) {
+            state.pool = VirtualPoolState::load_checked(&acc.data)?;
+        }
+        if let Some(acc) = accounts.get(&clock::id()) {
+            state.current_point = self.config.current_point(&ClockInfo::from_account_data(&acc.data)?);
+        }
+        self.slot.update(accounts, &self.required_accounts());
+        Ok(())
+    }
+
+    fn state_slot(&self) -> u64 {
+        self.slot.get()
+    }
+
+    /// Расчёт amount_out для свопа по кривой DBC с проходом по сегментам кривой.
+    ///
+    /// `token_in == mint_a()` (base) — продажа base, цена идёт вниз;
+    /// `token_in == mint_b()` (quote) — покупка base, цена идёт вверх.
+    fn amount_out(
+        &self,
+        amount_in: u64,
+        token_in: &Pubkey,
+    ) -> Result<u64, ArbError> {
+        if amount_in == 0 {
+            return Ok(0);
+        }
+
+        let state = self.state.read().unwrap();
+        if state.pool.is_migrated {
+            return Err(ArbError::PoolDisabled("DBC pool is already migrated".into()));
+        }
+        // До activation_point своп программой отклоняется
+        if state.current_point < state.pool.activation_point {
+            return Err(ArbError::PoolDisabled("DBC pool is not activated yet".into()));
+        }
+
+        let quote_to_base = if *token_in == *self.mint_b() {
+            true
+        } else if *token_in == *self.mint_a() {
+            false
+        } else {
+            return Err(ArbError::UnsupportedMint("Token in is not mint_a or mint_b".into()));
+        };
+
+        let fee_numerator = self.trade_fee_numerator(&state)?;
+
+        // В режиме QuoteToken при покупке комиссия снимается с входящего quote,
+        // во всех остальных случаях — с выходного токена.
+        let fee_on_input = quote_to_base && self.config.collect_fee_mode == CollectFeeMode::QuoteToken;
+
+        let actual_amount_in = if fee_on_input {
+            amount_in - fee_on_amount(amount_in, fee_numerator)
+        } else {
+            amount_in
+        };
+
+        let output = if quote_to_base {
+            self.swap_quote_to_base(&state.pool, actual_amount_in)?
+        } else {
+            self.swap_base_to_quote(&state.pool, actual_amount_in)?
+        };
+
+        let amount_out = if fee_on_input {
+            output
+        } else {
+            output - fee_on_amount(output, fee_numerator)
+        };
+
+        Ok(amount_out)
+    }
+}
+
+impl PoolLoader for MeteoraDbcPoolInfo {
+    /// PoolConfig пула (кривая и комиссии)
+    fn dependent_accounts(pool_data: &[u8]) -> Result<Vec<Pubkey>, ArbError> {
+        Ok(vec![VirtualPoolState::load_checked(pool_data)?.config])
+    }
+
+    fn from_snapshot(pool_pubkey: Pubkey, accounts: &AccountSnapshot) -> Result<Self, ArbError> {
+        let pool_data = &snapshot_account(accounts, &pool_pubkey)?.data;
+        let config = VirtualPoolState::load_checked(pool_data)?.config;
+        let config_data = &snapshot_account(accounts, &config)?.data;
+        Self::from_accounts(pool_pubkey, pool_data, config_data, &snapshot_clock(accounts)?)
+    }
+}
+
+impl MeteoraDbcPoolInfo {
+    /// Создать структуру пула из данных аккаунтов VirtualPool и PoolConfig.
+    pub fn from_accounts(
+        pool_pubkey: Pubkey,
+        pool_data: &[u8],
+        config_data: &[u8],
+        clock: &ClockInfo,
+    ) -> Result<Self, ArbError> {
+        let pool = VirtualPoolState::load_checked(pool_data)?;
+        let config = DbcConfig::load_checked(config_data)?;
+
+        let current_point = config.current_point(clock);
+
+        debug!(
+            "Parsed DBC Pool: \
+             \n\tpool={}, \
+             \n\tconfig={}, \
+             \n\tbase_mint={}, \
+             \n\tquote_mint={}, \
+             \n\tbase_reserve={}, \
+             \n\tquote_reserve={}, \
+             \n\tsqrt_price={}, \
+             \n\tmigrated={}",
+            pool_pubkey,
+            pool.config,
+            pool.base_mint,
+            config.quote_mint,
+            pool.base_reserve,
+            pool.quote_reserve,
+            pool.sqrt_price,
+            pool.is_migrated
+        );
+
+        Ok(Self {
+            pubkey: pool_pubkey,
+            mint_a: pool.base_mint,
+            mint_b: config.quote_mint,
+            config,
+            state: RwLock::new(DbcState { pool, current_point }),
+            slot: StateSlot::default(),
+        })
+    }
+
+    /// Создать структуру пула напрямую из аккаунта пула (дочитывает PoolConfig).
+    pub fn create(pool_pubkey: Pubkey, provider: &dyn AccountProvider) -> Result<Self, ArbError> {
+        load_pool(pool_pubkey, provider)
+    }
+
+    /// Текущее состояние пула из последнего снапшота
+    pub fn state(&self) -> DbcState {
+        self.state.read().unwrap().clone()
+    }
+
+    /// Итоговая комиссия свопа: базовая (fee scheduler) + динамическая, не более MAX_FEE_NUMERATOR
+    pub fn trade_fee_numerator(&self, state: &DbcState) -> Result<u64, ArbError> {
+        let base = self.config.base_fee_numerator(state.current_point, state.pool.activation_point)?;
+        let variable = self.config.variable_fee_numerator(state.pool.volatility_accumulator);
+        Ok(base.saturating_add(variable).min(MAX_FEE_NUMERATOR))
+    }
+
+    /// Покупка base за quote: цена поднимается вверх по точкам кривой
+    fn swap_quote_to_base(&self, pool: &VirtualPoolState, amount_in: u64) -> Result<u64, ArbError> {
+        let mut total_out: u64 = 0;
+        let mut remaining = amount_in;
+        let mut sqrt_price = pool.sqrt_price;
+
+        for point in &self.config.curve {
+            if point.sqrt_price == 0 || point.liquidity == 0 {
+                break;
+            }
+            if point.sqrt_price <= sqrt_price {
+                continue;
+            }
+
+            let max_amount_in = get_delta_amount_quote(sqrt_price, point.sqrt_price, point.liquidity, true)?;
+            if remaining < max_amount_in {
+                let next_sqrt_price = get_next_sqrt_price_from_quote_in(sqrt_price, point.liquidity, remaining)?;
+                total_out = add_amount(total_out, get_delta_amount_base(sqrt_price, next_sqrt_price, point.liquidity, false)?)?;
+                remaining = 0;
+                break;
+            }
+
+            total_out = add_amount(total_out, get_delta_amount_base(sqrt_price, point.sqrt_price, point.liquidity, false)?)?;
+            sqrt_price = point.sqrt_price;
+            remaining -= max_amount_in;
+        }
+
+        if remaining != 0 {
+            return Err(ArbError::InsufficientLiquidity("Not enough liquidity in DBC curve".into()));
+        }
+        if total_out > pool.base_reserve {
+            return Err(ArbError::InsufficientLiquidity("Amount out exceeds DBC base reserve".into()));
+        }
+
+        Ok(total_out)
+    }
+
+    /// Продажа base за quote: цена опускается вниз по точкам кривой до sqrt_start_price
+    fn swap_base_to_quote(&self, pool: &VirtualPoolState, amount_in: u64) -> Result<u64, ArbError> {
+        let curve = &self.config.curve;
+        let mut total_out: u64 = 0;
+        let mut remaining = amount_in;
+        let mut sqrt_price = pool.sqrt_price;
+
+        // Граница curve[i].sqrt_price отделяет сегмент i (ниже) от сегмента i + 1 (выше)
+        for i in (0..MAX_CURVE_POINT - 1).rev() {
+            if curve[i].sqrt_price == 0 || curve[i].liquidity == 0 {
+                continue;
+            }
+            if curve[i].sqrt_price >= sqrt_price {
+                continue;
+            }
+
+            let liquidity = curve[i + 1].liquidity;
+            if liquidity == 0 {
+                continue;
+            }
+
+            let max_amount_in = get_delta_amount_base(curve[i].sqrt_price, sqrt_price, liquidity, true)?;
+            if remaining < max_amount_in {
+                let next_sqrt_price = get_next_sqrt_price_from_base_in(sqrt_price, liquidity, remaining)?;
+                total_out = add_amount(total_out, get_delta_amount_quote(next_sqrt_price, sqrt_price, liquidity, false)?)?;
+                remaining = 0;
+                break;
+            }
+
+            total_out = add_amount(total_out, get_delta_amount_quote(curve[i].sqrt_price, sqrt_price, liquidity, false)?)?;
+            sqrt_price = curve[i].sqrt_price;
+            remaining -= max_amount_in;
+        }
+
+        // Оставшийся объём проходит по сегменту 0 до sqrt_start_price
+        if remaining != 0 {
+            let liquidity = curve[0].liquidity;
+            let lower = self.config.sqrt_start_price;
+            if liquidity == 0 || sqrt_price <= lower {
+                return Err(ArbError::InsufficientLiquidity("Not enough liquidity in DBC curve".into()));
+            }
+
+            let max_amount_in = get_delta_amount_base(lower, sqrt_price, liquidity, true)?;
+            if remaining > max_amount_in {
+                return Err(ArbError::InsufficientLiquidity("Not enough liquidity in DBC curve".into()));
+            }
+
+            let next_sqrt_price = get_next_sqrt_price_from_base_in(sqrt_price, liquidity, remaining)?;
+            total_out = add_amount(total_out, get_delta_amount_quote(next_sqrt_price, sqrt_price, liquidity, false)?)?;
+        }
+
+        if total_out > pool.quote_reserve {
+            return Err(ArbError::InsufficientLiquidity("Amount out exceeds DBC quote reserve".into()));
+        }
+
+        Ok(total_out)
+    }
+}
+
+// Сумма выходных объёмов сегментов кривой
+fn add_amount(total: u64, amount: u64) -> Result<u64, ArbError> {
+    total.checked_add(amount).ok_or_else(|| ArbError::MathOverflow("DBC math overflow".into()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{write_u128, write_u64};
+
+    const Q64: u128 = 1u128 << 64;
+
+    /// Пул с одним сегментом кривой: sqrt_price 1.0 -> 2.0, текущая 1.5, ликвидность 10^12 (в реальных единицах)
+    fn test_pool(cliff_fee_numerator: u64, collect_fee_mode: u8) -> MeteoraDbcPoolInfo {
+        let base_mint = Pubkey::new_unique();
+        let quote_mint = Pubkey::new_unique();
+
+        let mut pool_data = vec![0u8; VIRTUAL_POOL_MIN_LEN];
+        pool_data[POOL_BASE_MINT_OFFSET..POOL_BASE_MINT_OFFSET + 32].copy_from_slice(base_mint.as_ref());
+        write_u64(&mut pool_data, POOL_BASE_RESERVE_OFFSET, u64::MAX / 2);
+        write_u64(&mut pool_data, POOL_QUOTE_RESERVE_OFFSET, u64::MAX / 2);
+        write_u128(&mut pool_data, POOL_SQRT_PRICE_OFFSET, 3 * Q64 / 2);
+
+        let mut config_data = vec![0u8; POOL_CONFIG_MIN_LEN];
+        config_data[CONFIG_QUOTE_MINT_OFFSET..CONFIG_QUOTE_MINT_OFFSET + 32].copy_from_slice(quote_mint.as_ref());
+        write_u64(&mut config_data, CONFIG_CLIFF_FEE_NUMERATOR_OFFSET, cliff_fee_numerator);
+        config_data[CONFIG_COLLECT_FEE_MODE_OFFSET] = collect_fee_mode;
+        write_u128(&mut config_data, CONFIG_SQRT_START_PRICE_OFFSET, Q64);
+        write_u128(&mut config_data, CONFIG_CURVE_OFFSET, 2 * Q64);
+        write_u128(&mut config_data, CONFIG_CURVE_OFFSET + 16, 1_000_000_000_000 * Q64);
+
+        MeteoraDbcPoolInfo::from_accounts(
+            Pubkey::new_unique(),
+            &pool_data,
+            &config_data,
+            &ClockInfo::default(),
+        )
+        .expect("Failed to decode DBC pool")
+    }
+
+    #[test]
+    fn test_dbc_swap_matches_constant_product_inside_segment() {
+        let pool = test_pool(0, 0);
+
+        // Внутри сегмента кривая эквивалентна x*y=k с виртуальными резервами
+        // x = L / sqrtP, y = L * sqrtP.
+        let liquidity = 1_000_000_000_000f64;
+        let sqrt_p = 1.5f64;
+        let (x, y) = (liquidity / sqrt_p, liquidity * sqrt_p);
+
+        let quote_in = 1_000_000_000u64;
+        let base_out = pool.amount_out(quote_in, pool.mint_b()).unwrap();
+        let expected = x - x * y / (y + quote_in as f64);
+        assert!((base_out as f64 - expected).abs() <= 2.0, "{} vs {}", base_out, expected);
+
+        let base_in = 1_000_000_000u64;
+        let quote_out = pool.amount_out(base_in, pool.mint_a()).unwrap();
+        let expected = y - x * y / (x + base_in as f64);
+        assert!((quote_out as f64 - expected).abs() <= 2.0, "{} vs {}", quote_out, expected);
+    }
+
+    #[test]
+    fn test_dbc_swap_beyond_curve_fails() {
+        let pool = test_pool(0, 0);
+
+        // Подъём цены с 1.5 до 2.0 требует L * 0.5 quote
+        assert!(pool.amount_out(600_000_000_000, pool.mint_b()).is_err());
+        // Спуск цены с 1.5 до 1.0 возвращает не более L * (1/1 - 1/1.5) base
+        assert!(pool.amount_out(400_000_000_000, pool.mint_a()).is_err());
+    }
+
+    #[test]
+    fn test_dbc_rejects_swaps_before_activation() {
+        let pool = test_pool(0, 0);
+        pool.state.write().unwrap().pool.activation_point = 1_000;
+        pool.state.write().unwrap().current_point = 999;
+        assert!(matches!(pool.amount_out(1_000_000, pool.mint_b()), Err(ArbError::PoolDisabled(_))));
+
+        pool.state.write().unwrap().current_point = 1_000;
+        assert!(pool.amount_out(1_000_000, pool.mint_b()).unwrap() > 0);
+    }
+
+    #[test]
+    fn test_dbc_fee_modes() {
+        let no_fee = test_pool(0, 0);
+        // 1% комиссии
+        let quote_fee = test_pool(10_000_000, 0);
+        let output_fee = test_pool(10_000_000, 1);
+
+        let amount_in = 1_000_000u64;
+        let gross = no_fee.amount_out(amount_in, no_fee.mint_a()).unwrap();
+        // Продажа base: в обоих режимах комиссия снимается с выходного quote
+        let net = quote_fee.amount_out(amount_in, quote_fee.mint_a()).unwrap();
+        assert_eq!(net, gross - fee_on_amount(gross, 10_000_000));
+        let net = output_fee.amount_out(amount_in, output_fee.mint_a()).unwrap();
+        assert_eq!(net, gross - fee_on_amount(gross, 10_000_000));
+
+        // Покупка base в режиме QuoteToken: комиссия снимается со входа
+        let net = quote_fee.amount_out(amount_in, quote_fee.mint_b()).unwrap();
+        let expected = no_fee
+            .amount_out(amount_in - fee_on_amount(amount_in, 10_000_000), no_fee.mint_b())
+            .unwrap();
+        assert_eq!(net, expected);
+    }
+
+    #[test]
+    fn test_dbc_fee_scheduler() {
+        let mut pool = test_pool(500_000_000, 0);
+        pool.config.base_fee.first_factor = 10; // number_of_period
+        pool.config.base_fee.second_factor = 100; // period_frequency
+        pool.config.base_fee.third_factor = 40_000_000; // reduction_factor
+        let mut state = pool.state();
+        state.pool.activation_point = 1_000;
+
+        state.current_point = 500;
+        assert_eq!(pool.trade_fee_numerator(&state).unwrap(), 500_000_000);
+        state.current_point = 1_250;
+        assert_eq!(pool.trade_fee_numerator(&state).unwrap(), 420_000_000);
+        state.current_point = 100_000;
+        assert_eq!(pool.trade_fee_numerator(&state).unwrap(), 100_000_000);
+
+        pool.config.base_fee.base_fee_mode = BaseFeeMode::FeeSchedulerExponential;
+        pool.config.base_fee.third_factor = 5_000; // -50% каждый период
+        state.current_point = 1_250;
+        let fee = pool.trade_fee_numerator(&state).unwrap();
+        assert!((124_999_990..=125_000_000).contains(&fee), "{}", fee);
+    }
+}
@@ -1,10 +1,13 @@
-use crate::dex::PoolMints;
+use crate::dex::{load_pool, PoolLoader, PoolMints, StateSlot};
 use crate::dex::meteora::constants::{dlmm_program_id, BIN_ARRAY};
-use solana_client::rpc_client::RpcClient;
+use crate::provider::{snapshot_account, AccountProvider, AccountSnapshot};
 use solana_sdk::pubkey::Pubkey;
 use std::mem::size_of;
-use crate::common::{read_mint_decimals};
+use std::sync::atomic::{AtomicI32, Ordering};
+use crate::common::{read_mint_decimals, read_pubkey};
 use log::debug;
+use crate::error::ArbError;
+use crate::events::PoolEvent;
 
 #[repr(C)]
 #[derive(Debug, Clone, Copy)]
@@ -99,6 +102,39 @@ pub struct DlmmInfo {
     pub lb_pair: LbPair,
 }
 
+// Offsets полей события Swap после 8 байт discriminator:
+// lb_pair, from (по 32), start_bin_id, end_bin_id (i32), amount_in, amount_out (u64), swap_for_y (bool), ...
+const SWAP_EVENT_LB_PAIR_OFFSET: usize = 0;
+const SWAP_EVENT_START_BIN_OFFSET: usize = 64;
+const SWAP_EVENT_END_BIN_OFFSET: usize = 68;
+const SWAP_EVENT_SWAP_FOR_Y_OFFSET: usize = 88;
+const SWAP_EVENT_LEN: usize = 129;
+
+/// Событие `Swap` программы DLMM: бин, в котором своп закончился, становится активным
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DlmmSwapEvent {
+    pub lb_pair: Pubkey,
+    pub start_bin_id: i32,
+    pub end_bin_id: i32,
+    pub swap_for_y: bool,
+}
+
+impl DlmmSwapEvent {
+    /// Разобрать тело события (без discriminator)
+    pub fn decode(data: &[u8]) -> Result<Self, ArbError> {
+        if data.len() < SWAP_EVENT_LEN {
+            return Err(ArbError::Decode("Invalid data length for DLMM Swap event".into()));
+        }
+
+        Ok(Self {
+            lb_pair: read_pubkey(data, SWAP_EVENT_LB_PAIR_OFFSET),
+            start_bin_id: i32::from_le_bytes(data[SWAP_EVENT_START_BIN_OFFSET..SWAP_EVENT_START_BIN_OFFSET + 4].try_into()?),
+            end_bin_id: i32::from_le_bytes(data[SWAP_EVENT_END_BIN_OFFSET..SWAP_EVENT_END_BIN_OFFSET + 4].try_into()?),
+            swap_for_y: data[SWAP_EVENT_SWAP_FOR_Y_OFFSET] != 0,
+        })
+    }
+}
+
 /// Минимальная структура DLMM-пула, достаточная для off-chain расчётов арбитража.
 #[derive(Debug)]
 pub struct MeteoraDlmmPoolInfo {
@@ -109,16 +145,19 @@ pub struct MeteoraDlmmPoolInfo {
     pub vault_b: Pubkey,
     pub decimals_a: u8,
     pub decimals_b: u8,
-    pub active_id: i32,
+    /// Активный бин из последнего снапшота LbPair
+    active_id: AtomicI32,
     pub bin_step: u16,
     /// Комиссия пула (из base_factor) в basis points
     pub fee_rate_bps: u16,
+    /// Слот снапшота, к которому относится состояние
+    slot: StateSlot,
 }
 
 impl DlmmInfo {
-    pub fn load_checked(data: &[u8]) -> Result<Self, Box<dyn std::error::Error>> {
+    pub fn load_checked(data: &[u8]) -> Result<Self, ArbError> {
         if data.len() < 8 + size_of::<LbPair>() {
-            return Err("Invalid data length for DlmmInfo".into());
+            return Err(ArbError::Decode("Invalid data length for DlmmInfo".into()));
         }
 
         let raw_lb_pair = &data[8..8 + size_of::<LbPair>()];
@@ -162,7 +201,7 @@ impl DlmmInfo {
         (token_vault, sol_vault)
     }
 
-    pub fn calculate_bin_arrays(&self, pair_pubkey: &Pubkey) -> Result<Vec<Pubkey>, Box<dyn std::error::Error>> {
+    pub fn calculate_bin_arrays(&self, pair_pubkey: &Pubkey) -> Result<Vec<Pubkey>, ArbError> {
         let bin_array_index = self.bin_id_to_bin_array_index(self.active_id)?;
 
         let mut bin_arrays = Vec::new();
@@ -177,13 +216,13 @@ impl DlmmInfo {
         Ok(bin_arrays)
     }
 
-    fn bin_id_to_bin_array_index(&self, bin_id: i32) -> Result<i32, Box<dyn std::error::Error>> {
+    fn bin_id_to_bin_array_index(&self, bin_id: i32) -> Result<i32, ArbError> {
         // Use a constant bin per array size of 100 as used in the meteora protocol
         let bin_per_array = 100;
         Ok(bin_id.div_euclid(bin_per_array))
     }
 
-    fn derive_bin_array_pda(&self, lb_pair: &Pubkey, index: i64) -> Result<Pubkey, Box<dyn std::error::Error>> {
+    fn derive_bin_array_pda(&self, lb_pair: &Pubkey, index: i64) -> Result<Pubkey, ArbError> {
         let seeds = [BIN_ARRAY, lb_pair.as_ref(), &index.to_le_bytes()[0..8]];
 
         let (pda, _) = Pubkey::find_program_address(&seeds, &dlmm_program_id());
@@ -193,9 +232,9 @@ impl DlmmInfo {
 }
 
 impl LbPair {
-    pub fn from_bytes(data: &[u8]) -> Result<Self, Box<dyn std::error::Error>> {
+    pub fn from_bytes(data: &[u8]) -> Result<Self, ArbError> {
         if data.len() < size_of::<Self>() {
-            return Err("Data is too small for LbPair".into());
+            return Err(ArbError::Decode("Data is too small for LbPair".into()));
         }
 
         let lb_pair = unsafe { std::ptr::read_unaligned(data.as_ptr() as *const LbPair) };
@@ -218,16 +257,48 @@ impl PoolMints for MeteoraDlmmPoolInfo {
         &self.mint_b
     }
 
+    fn required_accounts(&self) -> Vec<Pubkey> {
+        vec![self.pubkey]
+    }
+
+    fn ingest(&self, accounts: &AccountSnapshot) -> Result<(), ArbError> {
+        if let Some(acc) = accounts.get(&self.pubkey) {
+            let dlmm_info = DlmmInfo::load_checked(&acc.data)?;
+            self.active_id.store(dlmm_info.active_id, Ordering::Relaxed);
+        }
+        self.slot.update(accounts, &self.required_accounts());
+        Ok(())
+    }
+
+    fn state_slot(&self) -> u64 {
+        self.slot.get()
+    }
+
+    /// Расчёт зависит только от активного бина — берём его из end_bin_id события
+    fn apply_event(&self, event: &PoolEvent, slot: u64) -> Result<bool, ArbError> {
+        // Единственный вариант, если собран только один DEX с событиями
+        #[allow(irrefutable_let_patterns)]
+        let PoolEvent::DlmmSwap(swap) = event else {
+            return Ok(false);
+        };
+        if swap.lb_pair != self.pubkey || slot < self.slot.get() {
+            return Ok(false);
+        }
+
+        self.active_id.store(swap.end_bin_id, Ordering::Relaxed);
+        self.slot.advance(slot);
+        Ok(true)
+    }
+
     /// Расчёт amount_out для свопа в DLMM на основе active_id и bin_step.
     ///
     /// В DLMM цена рассчитывается по формуле: price = (1 + bin_step/10000)^(active_id)
     /// Для упрощения используем линейную аппроксимацию на основе текущей цены бина.
     fn amount_out(
         &self,
-        client: &RpcClient,
         amount_in: u64,
         token_in: &Pubkey,
-    ) -> Result<u64, Box<dyn std::error::Error>> {
+    ) -> Result<u64, ArbError> {
         if amount_in == 0 {
             return Ok(0);
         }
@@ -240,10 +311,10 @@ impl PoolMints for MeteoraDlmmPoolInfo {
         // Рассчитываем цену из bin_id: price = (1 + bin_step/10000)^(active_id)
         // Это цена token_y / token_x (или token_b / token_a) без учета decimals
         let bin_step_f = self.bin_step as f64 / 10_000.0;
-        let price_ratio = (1.0 + bin_step_f).powi(self.active_id);
+        let price_ratio = (1.0 + bin_step_f).powi(self.active_id());
 
         if price_ratio == 0.0 {
-            return Err("Price ratio is 0".into());
+            return Err(ArbError::MathOverflow("Price ratio is 0".into()));
         }
 
         // Упрощённый расчёт: для малых свопов используем текущую цену
@@ -253,30 +324,39 @@ impl PoolMints for MeteoraDlmmPoolInfo {
         // Цена уже в правильном соотношении, применяем с учетом decimals для конвертации между минимальными единицами
         let amount_out_f = if *token_in == *self.mint_a() {
             // token_a -> token_b: amount_out = amount_in * price_ratio * (10^decimals_b / 10^decimals_a)
-            amount_in_f * price_ratio * 10f64.powi((self.decimals_b as i32 - self.decimals_a as i32) as i32)
+            amount_in_f * price_ratio * 10f64.powi(self.decimals_b as i32 - self.decimals_a as i32)
         } else {
             // token_b -> token_a: amount_out = amount_in / price_ratio * (10^decimals_a / 10^decimals_b)
-            amount_in_f / price_ratio * 10f64.powi((self.decimals_a as i32 - self.decimals_b as i32) as i32)
+            amount_in_f / price_ratio * 10f64.powi(self.decimals_a as i32 - self.decimals_b as i32)
         };
 
         // Ограничиваем максимальный вывод доступными резервами
         if amount_out_f <= 0.0 {
-            return Err("Amount out is less than 0".into());
+            return Err(ArbError::MathOverflow("Amount out is less than 0".into()));
         }
 
         Ok(amount_out_f as u64)
     }
 }
 
-impl MeteoraDlmmPoolInfo {
-    /// Создать структуру пула из DlmmInfo.
-    pub fn from_dlmm_info(pool_pubkey: Pubkey, dlmm_info: &DlmmInfo, client: &RpcClient) -> Result<Self, Box<dyn std::error::Error>> {
-        // Читаем decimals из mint-аккаунтов
-        let mint_a_acc = client.get_account(&dlmm_info.token_x_mint)?;
-        let mint_b_acc = client.get_account(&dlmm_info.token_y_mint)?;
-        let decimals_a = read_mint_decimals(&mint_a_acc) as u8;
-        let decimals_b = read_mint_decimals(&mint_b_acc) as u8;
+impl PoolLoader for MeteoraDlmmPoolInfo {
+    /// Mint-аккаунты токенов пары (decimals)
+    fn dependent_accounts(pool_data: &[u8]) -> Result<Vec<Pubkey>, ArbError> {
+        let dlmm_info = DlmmInfo::load_checked(pool_data)?;
+        Ok(vec![dlmm_info.token_x_mint, dlmm_info.token_y_mint])
+    }
+
+    fn from_snapshot(pool_pubkey: Pubkey, accounts: &AccountSnapshot) -> Result<Self, ArbError> {
+        let dlmm_info = DlmmInfo::load_checked(&snapshot_account(accounts, &pool_pubkey)?.data)?;
+        let decimals_a = read_mint_decimals(snapshot_account(accounts, &dlmm_info.token_x_mint)?);
+        let decimals_b = read_mint_decimals(snapshot_account(accounts, &dlmm_info.token_y_mint)?);
+        Self::from_dlmm_info(pool_pubkey, &dlmm_info, decimals_a, decimals_b)
+    }
+}
 
+impl MeteoraDlmmPoolInfo {
+    /// Создать структуру пула из DlmmInfo и decimals mint-аккаунтов.
+    pub fn from_dlmm_info(pool_pubkey: Pubkey, dlmm_info: &DlmmInfo, decimals_a: u8, decimals_b: u8) -> Result<Self, ArbError> {
         // Комиссия в DLMM вычисляется из base_factor
         // base_factor хранится как u16, и указывает комиссию в basis points (bps)
         let base_factor = dlmm_info.lb_pair.parameters.base_factor; // base_factor реальное значение комиссии в bps
@@ -309,25 +389,29 @@ impl MeteoraDlmmPoolInfo {
             vault_b: dlmm_info.token_y_vault,
             decimals_a,
             decimals_b,
-            active_id: dlmm_info.active_id,
+            active_id: AtomicI32::new(dlmm_info.active_id),
             bin_step: dlmm_info.lb_pair.bin_step,
             fee_rate_bps: base_factor,
+            slot: StateSlot::default(),
         })
     }
 
     /// Создать структуру пула напрямую из аккаунта пула.
-    pub fn create(pool_pubkey: Pubkey, client: &RpcClient) -> Result<Self, Box<dyn std::error::Error>> {
+    pub fn create(pool_pubkey: Pubkey, provider: &dyn AccountProvider) -> Result<Self, ArbError> {
         println!("Creating DLMM pool: {}", pool_pubkey);
-        let account = client.get_account(&pool_pubkey)?;
-        let dlmm_info = DlmmInfo::load_checked(&account.data)?;
-        Self::from_dlmm_info(pool_pubkey, &dlmm_info, client)
+        load_pool(pool_pubkey, provider)
+    }
+
+    /// Активный бин из последнего снапшота
+    pub fn active_id(&self) -> i32 {
+        self.active_id.load(Ordering::Relaxed)
     }
 
     /// Рассчитать текущую цену на основе active_id и bin_step.
     /// Возвращает цену token_b / token_a с учетом decimals.
     pub fn price(&self) -> f64 {
         let bin_step_f = self.bin_step as f64 / 10_000.0;
-        let price_ratio = (1.0 + bin_step_f).powi(self.active_id);
+        let price_ratio = (1.0 + bin_step_f).powi(self.active_id());
         // Применяем decimals для получения цены в правильных единицах
         price_ratio// * 10f64.powi((self.decimals_b as i32 - self.decimals_a as i32) as i32)
     }
@@ -370,7 +454,7 @@ mod tests {
                 println!("  Vault B: {}", pool.vault_b);
                 println!("  Decimals A: {}", pool.decimals_a);
                 println!("  Decimals B: {}", pool.decimals_b);
-                println!("  Active ID: {}", pool.active_id);
+                println!("  Active ID: {}", pool.active_id());
                 println!("  Bin Step: {}", pool.bin_step);
                 println!("  Fee Rate (bps): {}", pool.fee_rate_bps);
                 
@@ -381,8 +465,8 @@ mod tests {
                 // Получаем резервы для дополнительной информации
                 match (client.get_account(&pool.vault_a), client.get_account(&pool.vault_b)) {
                     (Ok(vault_a_acc), Ok(vault_b_acc)) => {
-                        let reserve_a = crate::common::read_spl_amount(&vault_a_acc);
-                        let reserve_b = crate::common::read_spl_amount(&vault_b_acc);
+                        let reserve_a = crate::common::read_spl_amount(&vault_a_acc).unwrap();
+                        let reserve_b = crate::common::read_spl_amount(&vault_b_acc).unwrap();
                         println!("\nРезервы пула:");
                         println!("  Reserve A: {}", reserve_a);
                         println!("  Reserve B: {}", reserve_b);
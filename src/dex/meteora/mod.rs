@@ -1,2 +1,3 @@
 pub mod constants;
+pub mod dbc;
 pub mod dlmm;
\ No newline at end of file
@@ -1,34 +1,163 @@
+#[cfg(feature = "raydium")]
 pub mod raydium;
+#[cfg(feature = "meteora")]
 pub mod meteora;
+#[cfg(feature = "phoenix")]
+pub mod phoenix;
+#[cfg(feature = "openbook")]
+pub mod openbook;
+#[cfg(feature = "saber")]
+pub mod saber;
+#[cfg(feature = "stake-pool")]
+pub mod stake_pool;
+#[cfg(feature = "saber")]
+pub mod stable_swap;
+pub mod registry;
 
-use solana_client::rpc_client::RpcClient;
 use solana_sdk::pubkey::Pubkey;
+use std::collections::HashSet;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use crate::error::ArbError;
+use crate::events::PoolEvent;
+use crate::cache::AccountCache;
+use crate::provider::{snapshot_account, AccountProvider, AccountSnapshot};
+use solana_sdk::sysvar::clock;
 
 /// Общий trait для всех структур пулов, предоставляющий доступ к mint-адресам токенов
 /// и расчету выходного количества токенов при свопе
-pub trait PoolMints {
+///
+/// Расчёт разделён на три шага: `required_accounts` (какие аккаунты нужны),
+/// `ingest` (обновление состояния из снапшота) и `amount_out` (чистый расчёт без I/O).
+///
+/// Пулы разделяются между потоками (`Arc<dyn PoolMints>`), поэтому состояние хранится
+/// в `RwLock` или атомиках, а trait требует `Send + Sync`.
+pub trait PoolMints: Send + Sync {
     /// Возвращает адрес пула (pubkey)
     fn pool_pubkey(&self) -> &Pubkey;
-    
+
     /// Возвращает адрес первого токена в паре (mint_a)
     fn mint_a(&self) -> &Pubkey;
-    
+
     /// Возвращает адрес второго токена в паре (mint_b)
     fn mint_b(&self) -> &Pubkey;
-    
-    /// Рассчитывает количество выходных токенов при свопе
-    /// 
+
+    /// Аккаунты, от данных которых зависит расчёт amount_out
+    fn required_accounts(&self) -> Vec<Pubkey>;
+
+    /// Обновляет состояние пула из снапшота аккаунтов.
+    ///
+    /// Аккаунты, которых нет в снапшоте, состояние не меняют.
+    fn ingest(&self, accounts: &AccountSnapshot) -> Result<(), ArbError>;
+
+    /// Слот снапшота, из которого получено текущее состояние пула (0 — состояние ещё не загружено)
+    fn state_slot(&self) -> u64;
+
+    /// Применяет событие из логов транзакции, попавшей в слот `slot`, без перечитывания аккаунтов.
+    ///
+    /// Возвращает `true`, если состояние обновлено; событие другого пула, неподдерживаемое
+    /// событие или событие из слота старше текущего состояния игнорируются.
+    fn apply_event(&self, _event: &PoolEvent, _slot: u64) -> Result<bool, ArbError> {
+        Ok(false)
+    }
+
+    /// Рассчитывает количество выходных токенов при свопе по текущему состоянию пула
+    ///
     /// # Arguments
-    /// * `client` - RPC клиент для получения актуальных данных пула
     /// * `amount_in` - количество входящих токенов (в минимальных единицах)
     /// * `token_in` - адрес mint токена, который входит в своп
-    /// 
+    ///
     /// # Returns
     /// Количество выходных токенов (в минимальных единицах) или ошибка
     fn amount_out(
         &self,
-        client: &RpcClient,
         amount_in: u64,
         token_in: &Pubkey,
-    ) -> Result<u64, Box<dyn std::error::Error>>;
-}
\ No newline at end of file
+    ) -> Result<u64, ArbError>;
+
+    /// Маржинальный курс свопа с учётом комиссии: выход на единицу входа при малом объёме.
+    ///
+    /// По умолчанию оценивается котировкой объёма `probe` (в минимальных единицах `token_in`);
+    /// `None` — курс неизвестен (состояние не загружено, нет ликвидности, объём меньше шага цены).
+    fn marginal_rate(&self, token_in: &Pubkey, probe: u64) -> Option<f64> {
+        match self.amount_out(probe, token_in) {
+            Ok(amount_out) if probe > 0 && amount_out > 0 => Some(amount_out as f64 / probe as f64),
+            _ => None,
+        }
+    }
+}
+
+/// Слот состояния пула.
+///
+/// Обновляется в `ingest` наибольшим слотом среди аккаунтов состояния, попавших в снапшот,
+/// и никогда не уменьшается.
+#[derive(Debug, Default)]
+pub struct StateSlot(AtomicU64);
+
+impl StateSlot {
+    pub fn update(&self, accounts: &AccountSnapshot, pubkeys: &[Pubkey]) {
+        if let Some(slot) = accounts.max_slot_of(pubkeys) {
+            self.0.fetch_max(slot, Ordering::Relaxed);
+        }
+    }
+
+    /// Поднять слот состояния до `slot` (для обновлений не из снапшота)
+    pub fn advance(&self, slot: u64) {
+        self.0.fetch_max(slot, Ordering::Relaxed);
+    }
+
+    pub fn get(&self) -> u64 {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// Построение адаптера из снапшота аккаунтов.
+///
+/// Пакетный загрузчик сначала получает аккаунты пулов и sysvar Clock, затем их зависимости,
+/// и только после этого строит адаптеры — без последовательных запросов на каждый пул.
+pub trait PoolLoader: PoolMints + Sized {
+    /// Аккаунты, которые нужны помимо аккаунта пула и Clock (mints, конфиги, стороны стакана)
+    fn dependent_accounts(pool_data: &[u8]) -> Result<Vec<Pubkey>, ArbError>;
+
+    /// Построить адаптер из снапшота с аккаунтом пула, Clock и зависимостями
+    fn from_snapshot(pool_pubkey: Pubkey, accounts: &AccountSnapshot) -> Result<Self, ArbError>;
+}
+
+/// Загрузить один пул: аккаунт пула и Clock, зависимости, затем аккаунты состояния
+pub fn load_pool<P: PoolLoader>(pool_pubkey: Pubkey, provider: &dyn AccountProvider) -> Result<P, ArbError> {
+    let cache = AccountCache::new();
+    cache.load_missing(provider, &[pool_pubkey, clock::id()])?;
+    let dependencies = P::dependent_accounts(&snapshot_account(&cache.read(), &pool_pubkey)?.data)?;
+    cache.load_missing(provider, &dependencies)?;
+
+    let pool = P::from_snapshot(pool_pubkey, &cache.read())?;
+    cache.load_missing(provider, &pool.required_accounts())?;
+    pool.ingest(&cache.read())?;
+
+    Ok(pool)
+}
+
+/// Пакетно обновляет аккаунты состояния всех пулов в общем кэше и обновляет пулы из него.
+///
+/// После вызова поиск арбитража работает по согласованному снапшоту без обращений к сети.
+/// Запрос идёт с `min_context_slot` не ниже слота уже закэшированных версий этих аккаунтов.
+pub fn refresh_pools<'a, I>(pools: I, provider: &dyn AccountProvider, cache: &AccountCache) -> Result<(), ArbError>
+where
+    I: IntoIterator<Item = &'a Arc<dyn PoolMints>>,
+{
+    let mut seen: HashSet<Pubkey> = HashSet::new();
+    let pools: Vec<&Arc<dyn PoolMints>> = pools
+        .into_iter()
+        .filter(|pool| seen.insert(*pool.pool_pubkey()))
+        .collect();
+
+    let keys: Vec<Pubkey> = pools.iter().flat_map(|pool| pool.required_accounts()).collect();
+    cache.refresh(provider, &keys)?;
+
+    let accounts = cache.read();
+    for pool in pools {
+        pool.ingest(&accounts)?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,12 @@
+use solana_sdk::pubkey::Pubkey;
+use std::str::FromStr;
+
+pub fn openbook_v2_program_id() -> Pubkey {
+    Pubkey::from_str("opnb2LAfJYbRMAHHvqjCwQxanZn7ReEHp1k81EohpZb").unwrap()
+}
+
+/// Знаменатель maker/taker fee (fee / 10^6)
+pub const FEES_SCALE_FACTOR: i128 = 1_000_000;
+
+/// Максимальное число узлов в BookSide
+pub const MAX_ORDERTREE_NODES: usize = 1024;
@@ -0,0 +1,502 @@
+use crate::common::{snapshot_clock, read_pubkey, read_u128, read_u16, read_u32, read_u64, ClockInfo};
+use crate::dex::{load_pool, PoolLoader, PoolMints, StateSlot};
+use crate::dex::openbook::constants::{FEES_SCALE_FACTOR, MAX_ORDERTREE_NODES};
+use crate::provider::{snapshot_account, AccountProvider, AccountSnapshot};
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::sysvar::clock;
+use std::sync::RwLock;
+use log::debug;
+use crate::error::ArbError;
+
+/*
+OpenBook v2 — CLOB, стакан хранится в двух отдельных аккаунтах BookSide (bids и asks).
+BookSide: roots [OrderTreeRoot; 2] (0 — fixed-ордера, 1 — oracle pegged), reserved_roots, reserved,
+OrderTreeNodes { order_tree_type, bump_index, free_list_len, free_list_head, reserved, nodes [AnyNode; 1024] }.
+Дерево — crit-bit: внутренние узлы (tag 1) содержат children[2], листья (tag 2) — ордера.
+Ключ листа u128: старшие 64 бита — price_data (для fixed-ордеров это price_lots), младшие — seq_num.
+Цена в quote lots за один base lot.
+Oracle pegged ордера не учитываются — для них нужна цена оракула.
+*/
+
+// Offsets внутри аккаунта Market (с учётом 8 байт discriminator)
+const MARKET_BIDS_OFFSET: usize = 200;
+const MARKET_ASKS_OFFSET: usize = 232;
+const MARKET_TIME_EXPIRY_OFFSET: usize = 48;
+const MARKET_BASE_DECIMALS_OFFSET: usize = 9;
+const MARKET_QUOTE_DECIMALS_OFFSET: usize = 10;
+const MARKET_QUOTE_LOT_SIZE_OFFSET: usize = 448;
+const MARKET_BASE_LOT_SIZE_OFFSET: usize = 456;
+const MARKET_TAKER_FEE_OFFSET: usize = 488;
+const MARKET_BASE_MINT_OFFSET: usize = 576;
+const MARKET_QUOTE_MINT_OFFSET: usize = 608;
+const MARKET_BASE_VAULT_OFFSET: usize = 640;
+const MARKET_QUOTE_VAULT_OFFSET: usize = 680;
+const MARKET_LEN: usize = 848;
+
+// Offsets внутри аккаунта BookSide (с учётом 8 байт discriminator)
+const BOOKSIDE_FIXED_ROOT_OFFSET: usize = 8;
+const BOOKSIDE_NODES_OFFSET: usize = 840;
+const NODE_SIZE: usize = 88;
+const BOOKSIDE_LEN: usize = BOOKSIDE_NODES_OFFSET + MAX_ORDERTREE_NODES * NODE_SIZE;
+
+// Разметка AnyNode
+const NODE_TAG_INNER: u8 = 1;
+const NODE_TAG_LEAF: u8 = 2;
+const INNER_CHILDREN_OFFSET: usize = 24;
+const LEAF_TIME_IN_FORCE_OFFSET: usize = 2;
+const LEAF_KEY_OFFSET: usize = 8;
+const LEAF_QUANTITY_OFFSET: usize = 56;
+const LEAF_TIMESTAMP_OFFSET: usize = 64;
+
+/// Декодированный аккаунт Market
+#[derive(Clone, Debug)]
+pub struct OpenbookMarketState {
+    pub bids: Pubkey,
+    pub asks: Pubkey,
+    pub time_expiry: i64,
+    pub base_decimals: u8,
+    pub quote_decimals: u8,
+    pub quote_lot_size: i64,
+    pub base_lot_size: i64,
+    /// Taker fee в долях 10^-6
+    pub taker_fee: i64,
+    pub base_mint: Pubkey,
+    pub quote_mint: Pubkey,
+    pub market_base_vault: Pubkey,
+    pub market_quote_vault: Pubkey,
+}
+
+/// Ценовой уровень стакана: суммарный объём всех ордеров по одной цене
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PriceLevel {
+    /// Цена в quote lots за base lot
+    pub price_lots: i64,
+    pub base_lots: i64,
+}
+
+impl OpenbookMarketState {
+    pub fn load_checked(data: &[u8]) -> Result<Self, ArbError> {
+        if data.len() < MARKET_LEN {
+            return Err(ArbError::Decode("Invalid data length for OpenBook Market".into()));
+        }
+
+        Ok(Self {
+            bids: read_pubkey(data, MARKET_BIDS_OFFSET),
+            asks: read_pubkey(data, MARKET_ASKS_OFFSET),
+            time_expiry: read_u64(data, MARKET_TIME_EXPIRY_OFFSET) as i64,
+            base_decimals: data[MARKET_BASE_DECIMALS_OFFSET],
+            quote_decimals: data[MARKET_QUOTE_DECIMALS_OFFSET],
+            quote_lot_size: read_u64(data, MARKET_QUOTE_LOT_SIZE_OFFSET) as i64,
+            base_lot_size: read_u64(data, MARKET_BASE_LOT_SIZE_OFFSET) as i64,
+            taker_fee: read_u64(data, MARKET_TAKER_FEE_OFFSET) as i64,
+            base_mint: read_pubkey(data, MARKET_BASE_MINT_OFFSET),
+            quote_mint: read_pubkey(data, MARKET_QUOTE_MINT_OFFSET),
+            market_base_vault: read_pubkey(data, MARKET_BASE_VAULT_OFFSET),
+            market_quote_vault: read_pubkey(data, MARKET_QUOTE_VAULT_OFFSET),
+        })
+    }
+}
+
+/// Разбор BookSide: обход fixed-дерева и сбор живых ордеров (price_lots, quantity).
+/// Ордера с истёкшим time_in_force пропускаются.
+pub fn read_book_side(data: &[u8], clock: &ClockInfo) -> Result<Vec<(i64, i64)>, ArbError> {
+    if data.len() < BOOKSIDE_LEN {
+        return Err(ArbError::Decode("Invalid data length for OpenBook BookSide".into()));
+    }
+
+    let root = read_u32(data, BOOKSIDE_FIXED_ROOT_OFFSET);
+    let leaf_count = read_u32(data, BOOKSIDE_FIXED_ROOT_OFFSET + 4) as usize;
+    let mut orders = Vec::with_capacity(leaf_count);
+    if leaf_count == 0 {
+        return Ok(orders);
+    }
+
+    let node_at = |handle: u32| -> Result<usize, ArbError> {
+        if handle as usize >= MAX_ORDERTREE_NODES {
+            return Err(ArbError::Decode("OpenBook node handle out of bounds".into()));
+        }
+        Ok(BOOKSIDE_NODES_OFFSET + handle as usize * NODE_SIZE)
+    };
+
+    // Обход в глубину: сначала children[0] (меньший ключ), затем children[1]
+    let mut stack = vec![root];
+    let mut visited = 0usize;
+    while let Some(handle) = stack.pop() {
+        visited += 1;
+        if visited > MAX_ORDERTREE_NODES {
+            return Err(ArbError::Decode("OpenBook order tree is corrupted".into()));
+        }
+
+        let node = node_at(handle)?;
+        match data[node] {
+            NODE_TAG_INNER => {
+                stack.push(read_u32(data, node + INNER_CHILDREN_OFFSET + 4));
+                stack.push(read_u32(data, node + INNER_CHILDREN_OFFSET));
+            }
+            NODE_TAG_LEAF => {
+                let key = read_u128(data, node + LEAF_KEY_OFFSET);
+                let price_lots = (key >> 64) as u64 as i64;
+                let quantity = read_u64(data, node + LEAF_QUANTITY_OFFSET) as i64;
+                let time_in_force = read_u16(data, node + LEAF_TIME_IN_FORCE_OFFSET) as i64;
+                let timestamp = read_u64(data, node + LEAF_TIMESTAMP_OFFSET) as i64;
+
+                // Программа считает ордер истёкшим с момента timestamp + time_in_force включительно
+                let expired = time_in_force != 0
+                    && timestamp.checked_add(time_in_force).is_some_and(|expiry| clock.unix_timestamp >= expiry);
+                if !expired && quantity > 0 {
+                    orders.push((price_lots, quantity));
+                }
+            }
+            _ => return Err(ArbError::Decode("Unexpected OpenBook node tag".into())),
+        }
+    }
+
+    Ok(orders)
+}
+
+/// Схлопнуть ордера в ценовые уровни. `descending` — для бидов (лучшая цена — максимальная).
+fn aggregate_levels(mut orders: Vec<(i64, i64)>, descending: bool) -> Vec<PriceLevel> {
+    if descending {
+        orders.sort_by_key(|order| std::cmp::Reverse(order.0));
+    } else {
+        orders.sort_by_key(|order| order.0);
+    }
+
+    let mut levels: Vec<PriceLevel> = Vec::new();
+    for (price_lots, base_lots) in orders {
+        match levels.last_mut() {
+            Some(level) if level.price_lots == price_lots => level.base_lots += base_lots,
+            _ => levels.push(PriceLevel { price_lots, base_lots }),
+        }
+    }
+    levels
+}
+
+/// Изменяемая часть рынка OpenBook: стакан из последнего снапшота
+#[derive(Clone, Debug, Default)]
+pub struct OpenbookBook {
+    /// Биды, от лучшей (максимальной) цены к худшей
+    pub bids: Vec<PriceLevel>,
+    /// Аски, от лучшей (минимальной) цены к худшей
+    pub asks: Vec<PriceLevel>,
+    /// Clock, по которому отфильтрованы истёкшие ордера и проверен time_expiry
+    pub clock: ClockInfo,
+}
+
+/// Снимок рынка OpenBook v2, достаточный для off-chain расчёта тейкерского свопа.
+#[derive(Debug)]
+pub struct OpenbookMarketInfo {
+    pub pubkey: Pubkey,
+    mint_a: Pubkey,
+    mint_b: Pubkey,
+    pub market: OpenbookMarketState,
+    book: RwLock<OpenbookBook>,
+    /// Слот снапшота, к которому относится состояние
+    slot: StateSlot,
+}
+
+impl PoolMints for OpenbookMarketInfo {
+    fn pool_pubkey(&self) -> &Pubkey {
+        &self.pubkey
+    }
+
+    fn mint_a(&self) -> &Pubkey {
+        &self.mint_a
+    }
+
+    fn mint_b(&self) -> &Pubkey {
+        &self.mint_b
+    }
+
+    fn required_accounts(&self) -> Vec<Pubkey> {
+        vec![self.market.bids, self.market.asks, clock::id()]
+    }
+
+    fn ingest(&self, accounts: &AccountSnapshot) -> Result<(), ArbError> {
+        let mut book = self.book.write().unwrap();
+        if let Some(acc) = accounts.get(&clock::id()) {
+            book.clock = ClockInfo::from_account_data(&acc.data)?;
+        }
+        if let Some(acc) = accounts.get(&self.market.bids) {
+            book.bids = aggregate_levels(read_book_side(&acc.data, &book.clock)?, true);
+        }
+        if let Some(acc) = accounts.get(&self.market.asks) {
+            book.asks = aggregate_levels(read_book_side(&acc.data, &book.clock)?, false);
+        }
+        self.slot.update(accounts, &self.required_accounts());
+        Ok(())
+    }
+
+    fn state_slot(&self) -> u64 {
+        self.slot.get()
+    }
+
+    /// Расчёт amount_out тейкерского ордера с проходом по ценовым уровням стакана за вычетом taker fee.
+    ///
+    /// `token_in == mint_a()` (base) — продажа в биды, `token_in == mint_b()` (quote) — покупка из асков.
+    fn amount_out(
+        &self,
+        amount_in: u64,
+        token_in: &Pubkey,
+    ) -> Result<u64, ArbError> {
+        if amount_in == 0 {
+            return Ok(0);
+        }
+
+        let book = self.book.read().unwrap();
+        if self.market.time_expiry != 0 && book.clock.unix_timestamp >= self.market.time_expiry {
+            return Err(ArbError::PoolDisabled("OpenBook market is expired".into()));
+        }
+
+        if *token_in == *self.mint_a() {
+            self.sell_base(&book, amount_in)
+        } else if *token_in == *self.mint_b() {
+            self.buy_base(&book, amount_in)
+        } else {
+            Err(ArbError::UnsupportedMint("Token in is not mint_a or mint_b".into()))
+        }
+    }
+}
+
+impl PoolLoader for OpenbookMarketInfo {
+    /// Аккаунты BookSide (bids, asks)
+    fn dependent_accounts(pool_data: &[u8]) -> Result<Vec<Pubkey>, ArbError> {
+        let market = OpenbookMarketState::load_checked(pool_data)?;
+        Ok(vec![market.bids, market.asks])
+    }
+
+    fn from_snapshot(pool_pubkey: Pubkey, accounts: &AccountSnapshot) -> Result<Self, ArbError> {
+        let market_data = &snapshot_account(accounts, &pool_pubkey)?.data;
+        let market = OpenbookMarketState::load_checked(market_data)?;
+        Self::from_accounts(
+            pool_pubkey,
+            market_data,
+            &snapshot_account(accounts, &market.bids)?.data,
+            &snapshot_account(accounts, &market.asks)?.data,
+            &snapshot_clock(accounts)?,
+        )
+    }
+}
+
+impl OpenbookMarketInfo {
+    /// Создать снимок рынка из данных аккаунтов Market и двух BookSide.
+    pub fn from_accounts(
+        market_pubkey: Pubkey,
+        market_data: &[u8],
+        bids_data: &[u8],
+        asks_data: &[u8],
+        clock: &ClockInfo,
+    ) -> Result<Self, ArbError> {
+        let market = OpenbookMarketState::load_checked(market_data)?;
+        if market.base_lot_size <= 0 || market.quote_lot_size <= 0 {
+            return Err(ArbError::Decode("OpenBook market has invalid lot size".into()));
+        }
+
+        let bids = aggregate_levels(read_book_side(bids_data, clock)?, true);
+        let asks = aggregate_levels(read_book_side(asks_data, clock)?, false);
+
+        debug!(
+            "Parsed OpenBook Market: \
+             \n\tmarket={}, \
+             \n\tbase_mint={}, \
+             \n\tquote_mint={}, \
+             \n\tbase_lot_size={}, \
+             \n\tquote_lot_size={}, \
+             \n\ttaker_fee={}, \
+             \n\tbid_levels={}, \
+             \n\task_levels={}",
+            market_pubkey,
+            market.base_mint,
+            market.quote_mint,
+            market.base_lot_size,
+            market.quote_lot_size,
+            market.taker_fee,
+            bids.len(),
+            asks.len()
+        );
+
+        Ok(Self {
+            pubkey: market_pubkey,
+            mint_a: market.base_mint,
+            mint_b: market.quote_mint,
+            market,
+            book: RwLock::new(OpenbookBook { bids, asks, clock: *clock }),
+            slot: StateSlot::default(),
+        })
+    }
+
+    /// Текущий стакан из последнего снапшота
+    pub fn book(&self) -> OpenbookBook {
+        self.book.read().unwrap().clone()
+    }
+
+    /// Создать снимок рынка напрямую из аккаунта Market (дочитывает bids/asks).
+    pub fn create(market_pubkey: Pubkey, provider: &dyn AccountProvider) -> Result<Self, ArbError> {
+        load_pool(market_pubkey, provider)
+    }
+
+    /// Taker fee с округлением вверх, в нативных единицах quote
+    fn taker_fee_ceil(&self, quote_native: i128) -> i128 {
+        let numerator = quote_native * self.market.taker_fee as i128;
+        (numerator + FEES_SCALE_FACTOR - 1) / FEES_SCALE_FACTOR
+    }
+
+    /// Продажа base: проходим биды сверху вниз, комиссия снимается с полученного quote
+    fn sell_base(&self, book: &OpenbookBook, amount_in: u64) -> Result<u64, ArbError> {
+        let mut remaining_base_lots = amount_in as i128 / self.market.base_lot_size as i128;
+        let mut quote_lots: i128 = 0;
+
+        for level in &book.bids {
+            if remaining_base_lots == 0 {
+                break;
+            }
+            let filled = remaining_base_lots.min(level.base_lots as i128);
+            quote_lots += filled * level.price_lots as i128;
+            remaining_base_lots -= filled;
+        }
+
+        if remaining_base_lots != 0 {
+            return Err(ArbError::InsufficientLiquidity("Not enough liquidity in OpenBook bids".into()));
+        }
+
+        let quote_native = quote_lots * self.market.quote_lot_size as i128;
+        let amount_out = quote_native - self.taker_fee_ceil(quote_native);
+        u64::try_from(amount_out).map_err(|_| ArbError::MathOverflow("OpenBook amount out overflow".into()))
+    }
+
+    /// Покупка base: бюджет в quote lots заранее уменьшается на taker fee, затем проходим аски снизу вверх
+    fn buy_base(&self, book: &OpenbookBook, amount_in: u64) -> Result<u64, ArbError> {
+        let quote_lots_including_fees = amount_in as i128 / self.market.quote_lot_size as i128;
+        let mut budget = quote_lots_including_fees * FEES_SCALE_FACTOR
+            / (FEES_SCALE_FACTOR + self.market.taker_fee as i128);
+        let mut base_lots: i128 = 0;
+
+        for level in &book.asks {
+            if budget == 0 {
+                break;
+            }
+            let price_lots = level.price_lots as i128;
+            if price_lots <= 0 {
+                continue;
+            }
+            let filled = (budget / price_lots).min(level.base_lots as i128);
+            if filled == 0 {
+                // Бюджета не хватает даже на один лот по этой и более высоким ценам
+                budget = 0;
+                break;
+            }
+            base_lots += filled;
+            budget -= filled * price_lots;
+            if filled < level.base_lots as i128 {
+                budget = 0;
+            }
+        }
+
+        if budget != 0 {
+            return Err(ArbError::InsufficientLiquidity("Not enough liquidity in OpenBook asks".into()));
+        }
+
+        let amount_out = base_lots * self.market.base_lot_size as i128;
+        u64::try_from(amount_out).map_err(|_| ArbError::MathOverflow("OpenBook amount out overflow".into()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{write_u32, write_u64};
+
+    /// BookSide с деревом: inner(0) -> [leaf(1), inner(2) -> [leaf(3), leaf(4)]]
+    /// Ордера: (price_lots, quantity, time_in_force, timestamp)
+    fn bookside(orders: [(u64, u64, u16, u64); 3]) -> Vec<u8> {
+        let mut data = vec![0u8; BOOKSIDE_LEN];
+        write_u32(&mut data, BOOKSIDE_FIXED_ROOT_OFFSET, 0);
+        write_u32(&mut data, BOOKSIDE_FIXED_ROOT_OFFSET + 4, orders.len() as u32);
+
+        let node = |handle: usize| BOOKSIDE_NODES_OFFSET + handle * NODE_SIZE;
+        for (inner, children) in [(0usize, [1u32, 2u32]), (2, [3, 4])] {
+            data[node(inner)] = NODE_TAG_INNER;
+            write_u32(&mut data, node(inner) + INNER_CHILDREN_OFFSET, children[0]);
+            write_u32(&mut data, node(inner) + INNER_CHILDREN_OFFSET + 4, children[1]);
+        }
+        for (handle, (price_lots, quantity, time_in_force, timestamp)) in [1usize, 3, 4].into_iter().zip(orders) {
+            let leaf = node(handle);
+            data[leaf] = NODE_TAG_LEAF;
+            data[leaf + LEAF_TIME_IN_FORCE_OFFSET..leaf + LEAF_TIME_IN_FORCE_OFFSET + 2]
+                .copy_from_slice(&time_in_force.to_le_bytes());
+            write_u64(&mut data, leaf + LEAF_KEY_OFFSET + 8, price_lots);
+            write_u64(&mut data, leaf + LEAF_QUANTITY_OFFSET, quantity);
+            write_u64(&mut data, leaf + LEAF_TIMESTAMP_OFFSET, timestamp);
+        }
+        data
+    }
+
+    /// Рынок SOL/USDC: base lot 0.01 SOL, quote lot 1 micro-USDC, taker fee — taker_fee * 10^-6
+    fn test_market(taker_fee: u64) -> OpenbookMarketInfo {
+        let mut market = vec![0u8; MARKET_LEN];
+        write_u64(&mut market, MARKET_QUOTE_LOT_SIZE_OFFSET, 1);
+        write_u64(&mut market, MARKET_BASE_LOT_SIZE_OFFSET, 10_000_000);
+        write_u64(&mut market, MARKET_TAKER_FEE_OFFSET, taker_fee);
+        market[MARKET_BASE_MINT_OFFSET..MARKET_BASE_MINT_OFFSET + 32].copy_from_slice(Pubkey::new_unique().as_ref());
+        market[MARKET_QUOTE_MINT_OFFSET..MARKET_QUOTE_MINT_OFFSET + 32].copy_from_slice(Pubkey::new_unique().as_ref());
+
+        // Биды: 1.50 USDC за лот (150 USDC/SOL) x 200 лотов, 1.49 x 300, 1.51 x 100 (ордер истёк)
+        let bids = bookside([(1_500_000, 200, 0, 0), (1_490_000, 300, 0, 0), (1_510_000, 100, 10, 50)]);
+        // Аски: 1.52 x 100, 1.515 x 50, 1.53 x 1000
+        let asks = bookside([(1_520_000, 100, 0, 0), (1_515_000, 50, 0, 0), (1_530_000, 1_000, 0, 0)]);
+
+        let clock = ClockInfo { unix_timestamp: 100, ..ClockInfo::default() };
+        OpenbookMarketInfo::from_accounts(Pubkey::new_unique(), &market, &bids, &asks, &clock)
+            .expect("Failed to decode OpenBook market")
+    }
+
+    #[test]
+    fn test_openbook_bookside_decoding() {
+        let book = test_market(0).book();
+        assert_eq!(
+            book.bids,
+            vec![
+                PriceLevel { price_lots: 1_500_000, base_lots: 200 },
+                PriceLevel { price_lots: 1_490_000, base_lots: 300 },
+            ]
+        );
+        assert_eq!(
+            book.asks,
+            vec![
+                PriceLevel { price_lots: 1_515_000, base_lots: 50 },
+                PriceLevel { price_lots: 1_520_000, base_lots: 100 },
+                PriceLevel { price_lots: 1_530_000, base_lots: 1_000 },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_openbook_order_expires_at_time_in_force_boundary() {
+        // Истекает ровно в 100, истекает в 101, без time_in_force
+        let data = bookside([(1_000, 1, 10, 90), (2_000, 1, 11, 90), (3_000, 1, 0, 0)]);
+        let clock = ClockInfo { unix_timestamp: 100, ..ClockInfo::default() };
+        let mut prices: Vec<i64> = read_book_side(&data, &clock).unwrap().into_iter().map(|(price, _)| price).collect();
+        prices.sort();
+        assert_eq!(prices, vec![2_000, 3_000]);
+    }
+
+    #[test]
+    fn test_openbook_sell_and_buy() {
+        // 0.04% taker fee
+        let market = test_market(400);
+
+        // 3 SOL = 300 лотов: 200 по 1.50 + 100 по 1.49 = 449 USDC, минус 0.04%
+        let out = market.amount_out(3_000_000_000, market.mint_a()).unwrap();
+        assert_eq!(out, 449_000_000 - 179_600);
+        assert!(market.amount_out(5_010_000_000, market.mint_a()).is_err());
+
+        // Бюджет 75.78 USDC после комиссии = 75.75: 50 лотов по 1.515 ровно
+        let out = market.amount_out(75_780_300, market.mint_b()).unwrap();
+        assert_eq!(out, 500_000_000);
+
+        // Частичное исполнение следующего уровня с округлением вниз до лота
+        let market = test_market(0);
+        let out = market.amount_out(75_750_000 + 2_000_000, market.mint_b()).unwrap();
+        assert_eq!(out, 500_000_000 + 10_000_000);
+    }
+}
@@ -0,0 +1,9 @@
+use solana_sdk::pubkey::Pubkey;
+use std::str::FromStr;
+
+pub fn phoenix_program_id() -> Pubkey {
+    Pubkey::from_str("PhoeNiXZ8ByJGLkxNfZRnkUfjvmuYqLR89jjFHGqdXY").unwrap()
+}
+
+/// Знаменатель taker fee (basis points)
+pub const FEE_BPS_DENOMINATOR: u64 = 10_000;
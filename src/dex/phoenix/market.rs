@@ -0,0 +1,554 @@
+use crate::common::{snapshot_clock, read_pubkey, read_u32, read_u64, ClockInfo};
+use crate::dex::{load_pool, PoolLoader, PoolMints, StateSlot};
+use crate::dex::phoenix::constants::FEE_BPS_DENOMINATOR;
+use crate::provider::{snapshot_account, AccountProvider, AccountSnapshot};
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::sysvar::clock;
+use log::debug;
+use std::sync::RwLock;
+use crate::error::ArbError;
+
+/*
+Phoenix — on-chain CLOB. Аккаунт рынка состоит из MarketHeader (576 байт) и FIFOMarket:
+  _padding [u64; 32], base_lots_per_base_unit, tick_size_in_quote_lots_per_base_unit,
+  order_sequence_number, taker_fee_bps, collected/unclaimed quote lot fees,
+  bids: RedBlackTree, asks: RedBlackTree, traders: RedBlackTree.
+Дерево (sokoban RedBlackTree): root u32 + padding [u32; 3], NodeAllocator { size u64, bump_index u32,
+free_list_head u32, nodes }. Узел: registers [u32; 4] (left, right, parent, color) + key + value.
+Индексы узлов начинаются с 1, 0 — SENTINEL.
+Цена уровня в quote lots за base unit: price_in_ticks * tick_size_in_quote_lots_per_base_unit.
+*/
+
+// Offsets внутри MarketHeader
+const STATUS_OFFSET: usize = 8;
+const BIDS_SIZE_OFFSET: usize = 16;
+const ASKS_SIZE_OFFSET: usize = 24;
+const BASE_DECIMALS_OFFSET: usize = 40;
+const BASE_MINT_OFFSET: usize = 48;
+const BASE_VAULT_OFFSET: usize = 80;
+const BASE_LOT_SIZE_OFFSET: usize = 112;
+const QUOTE_DECIMALS_OFFSET: usize = 120;
+const QUOTE_MINT_OFFSET: usize = 128;
+const QUOTE_VAULT_OFFSET: usize = 160;
+const QUOTE_LOT_SIZE_OFFSET: usize = 192;
+const TICK_SIZE_IN_QUOTE_ATOMS_OFFSET: usize = 200;
+const MARKET_HEADER_SIZE: usize = 576;
+
+// Offsets внутри FIFOMarket (абсолютные, от начала аккаунта)
+const BASE_LOTS_PER_BASE_UNIT_OFFSET: usize = MARKET_HEADER_SIZE + 256;
+const TICK_SIZE_IN_QUOTE_LOTS_OFFSET: usize = BASE_LOTS_PER_BASE_UNIT_OFFSET + 8;
+const TAKER_FEE_BPS_OFFSET: usize = BASE_LOTS_PER_BASE_UNIT_OFFSET + 24;
+const BIDS_TREE_OFFSET: usize = BASE_LOTS_PER_BASE_UNIT_OFFSET + 48;
+
+// Размеры дерева: заголовок дерева (16) + заголовок аллокатора (16), узел — 4 регистра + ключ (16) + значение (32)
+const TREE_HEADER_SIZE: usize = 32;
+const NODE_SIZE: usize = 64;
+const REGISTER_LEFT: usize = 0;
+const REGISTER_RIGHT: usize = 1;
+const NODE_KEY_OFFSET: usize = 16;
+const NODE_VALUE_OFFSET: usize = 32;
+const SENTINEL: u32 = 0;
+
+/// Статус рынка Phoenix (MarketStatus). Тейкерские сделки разрешены только в Active.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MarketStatus {
+    Uninitialized,
+    Active,
+    PostOnly,
+    Paused,
+    Closed,
+    Tombstoned,
+}
+
+impl MarketStatus {
+    fn from_u64(value: u64) -> Result<Self, ArbError> {
+        Ok(match value {
+            0 => MarketStatus::Uninitialized,
+            1 => MarketStatus::Active,
+            2 => MarketStatus::PostOnly,
+            3 => MarketStatus::Paused,
+            4 => MarketStatus::Closed,
+            5 => MarketStatus::Tombstoned,
+            _ => return Err(ArbError::Decode("Unknown Phoenix market status".into())),
+        })
+    }
+}
+
+/// Декодированный MarketHeader
+#[derive(Clone, Debug)]
+pub struct PhoenixMarketHeader {
+    pub status: MarketStatus,
+    pub bids_size: usize,
+    pub asks_size: usize,
+    pub base_mint: Pubkey,
+    pub base_vault: Pubkey,
+    pub base_decimals: u32,
+    pub base_lot_size: u64,
+    pub quote_mint: Pubkey,
+    pub quote_vault: Pubkey,
+    pub quote_decimals: u32,
+    pub quote_lot_size: u64,
+    pub tick_size_in_quote_atoms_per_base_unit: u64,
+}
+
+/// Ценовой уровень стакана: суммарный объём всех ордеров по одной цене
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BookLevel {
+    pub price_in_ticks: u64,
+    pub base_lots: u64,
+}
+
+impl PhoenixMarketHeader {
+    pub fn load_checked(data: &[u8]) -> Result<Self, ArbError> {
+        if data.len() < MARKET_HEADER_SIZE {
+            return Err(ArbError::Decode("Invalid data length for Phoenix MarketHeader".into()));
+        }
+
+        Ok(Self {
+            status: MarketStatus::from_u64(read_u64(data, STATUS_OFFSET))?,
+            bids_size: read_u64(data, BIDS_SIZE_OFFSET) as usize,
+            asks_size: read_u64(data, ASKS_SIZE_OFFSET) as usize,
+            base_mint: read_pubkey(data, BASE_MINT_OFFSET),
+            base_vault: read_pubkey(data, BASE_VAULT_OFFSET),
+            base_decimals: read_u32(data, BASE_DECIMALS_OFFSET),
+            base_lot_size: read_u64(data, BASE_LOT_SIZE_OFFSET),
+            quote_mint: read_pubkey(data, QUOTE_MINT_OFFSET),
+            quote_vault: read_pubkey(data, QUOTE_VAULT_OFFSET),
+            quote_decimals: read_u32(data, QUOTE_DECIMALS_OFFSET),
+            quote_lot_size: read_u64(data, QUOTE_LOT_SIZE_OFFSET),
+            tick_size_in_quote_atoms_per_base_unit: read_u64(data, TICK_SIZE_IN_QUOTE_ATOMS_OFFSET),
+        })
+    }
+}
+
+/// Обход RedBlackTree стакана в порядке ключей. Возвращает (price_in_ticks, num_base_lots)
+/// для всех живых ордеров; просроченные по слоту или времени ордера пропускаются.
+fn read_book_side(
+    data: &[u8],
+    tree_offset: usize,
+    capacity: usize,
+    clock: &ClockInfo,
+) -> Result<Vec<(u64, u64)>, ArbError> {
+    let tree_end = tree_offset + TREE_HEADER_SIZE + capacity * NODE_SIZE;
+    if data.len() < tree_end {
+        return Err(ArbError::Decode("Invalid data length for Phoenix order book".into()));
+    }
+
+    let nodes_offset = tree_offset + TREE_HEADER_SIZE;
+    let node_at = |index: u32| -> Result<usize, ArbError> {
+        if index == SENTINEL || index as usize > capacity {
+            return Err(ArbError::Decode("Phoenix order book node index out of bounds".into()));
+        }
+        Ok(nodes_offset + (index as usize - 1) * NODE_SIZE)
+    };
+    let register = |node: usize, register: usize| read_u32(data, node + register * 4);
+
+    let mut orders = Vec::new();
+    let mut stack: Vec<u32> = Vec::new();
+    let mut current = read_u32(data, tree_offset);
+
+    // Итеративный in-order обход; число посещённых узлов ограничено ёмкостью дерева
+    while current != SENTINEL || !stack.is_empty() {
+        while current != SENTINEL {
+            if stack.len() > capacity {
+                return Err(ArbError::Decode("Phoenix order book tree is corrupted".into()));
+            }
+            stack.push(current);
+            current = register(node_at(current)?, REGISTER_LEFT);
+        }
+
+        let index = stack.pop().unwrap();
+        let node = node_at(index)?;
+        if orders.len() >= capacity {
+            return Err(ArbError::Decode("Phoenix order book tree is corrupted".into()));
+        }
+
+        let price_in_ticks = read_u64(data, node + NODE_KEY_OFFSET);
+        let num_base_lots = read_u64(data, node + NODE_VALUE_OFFSET + 8);
+        let last_valid_slot = read_u64(data, node + NODE_VALUE_OFFSET + 16);
+        let last_valid_unix_timestamp = read_u64(data, node + NODE_VALUE_OFFSET + 24);
+
+        let expired = (last_valid_slot != 0 && last_valid_slot < clock.slot)
+            || (last_valid_unix_timestamp != 0 && (last_valid_unix_timestamp as i64) < clock.unix_timestamp);
+        if !expired && num_base_lots > 0 {
+            orders.push((price_in_ticks, num_base_lots));
+        }
+
+        current = register(node, REGISTER_RIGHT);
+    }
+
+    Ok(orders)
+}
+
+/// Схлопнуть ордера в ценовые уровни. `descending` — для бидов (лучшая цена — максимальная).
+fn aggregate_levels(mut orders: Vec<(u64, u64)>, descending: bool) -> Vec<BookLevel> {
+    if descending {
+        orders.sort_by_key(|order| std::cmp::Reverse(order.0));
+    } else {
+        orders.sort_by_key(|order| order.0);
+    }
+
+    let mut levels: Vec<BookLevel> = Vec::new();
+    for (price_in_ticks, base_lots) in orders {
+        match levels.last_mut() {
+            Some(level) if level.price_in_ticks == price_in_ticks => level.base_lots += base_lots,
+            _ => levels.push(BookLevel { price_in_ticks, base_lots }),
+        }
+    }
+    levels
+}
+
+/// Изменяемая часть рынка Phoenix: заголовок и стакан из последнего снапшота
+#[derive(Clone, Debug)]
+pub struct PhoenixBook {
+    pub header: PhoenixMarketHeader,
+    /// Биды, от лучшей (максимальной) цены к худшей
+    pub bids: Vec<BookLevel>,
+    /// Аски, от лучшей (минимальной) цены к худшей
+    pub asks: Vec<BookLevel>,
+    /// Clock, по которому отфильтрованы истёкшие ордера
+    pub clock: ClockInfo,
+}
+
+impl PhoenixBook {
+    /// Декодировать заголовок и обе стороны стакана из данных аккаунта рынка
+    pub fn from_account_data(data: &[u8], clock: &ClockInfo) -> Result<Self, ArbError> {
+        let header = PhoenixMarketHeader::load_checked(data)?;
+        if data.len() < BIDS_TREE_OFFSET {
+            return Err(ArbError::Decode("Invalid data length for Phoenix FIFOMarket".into()));
+        }
+        if header.base_lot_size == 0 || header.quote_lot_size == 0 {
+            return Err(ArbError::Decode("Phoenix market has zero lot size".into()));
+        }
+
+        let asks_tree_offset = BIDS_TREE_OFFSET + TREE_HEADER_SIZE + header.bids_size * NODE_SIZE;
+        let bids = aggregate_levels(read_book_side(data, BIDS_TREE_OFFSET, header.bids_size, clock)?, true);
+        let asks = aggregate_levels(read_book_side(data, asks_tree_offset, header.asks_size, clock)?, false);
+
+        Ok(Self { header, bids, asks, clock: *clock })
+    }
+}
+
+/// Снимок рынка Phoenix, достаточный для off-chain расчёта тейкерского свопа.
+#[derive(Debug)]
+pub struct PhoenixMarketInfo {
+    pub pubkey: Pubkey,
+    mint_a: Pubkey,
+    mint_b: Pubkey,
+    pub base_lots_per_base_unit: u64,
+    pub tick_size_in_quote_lots_per_base_unit: u64,
+    pub taker_fee_bps: u64,
+    book: RwLock<PhoenixBook>,
+    /// Слот снапшота, к которому относится состояние
+    slot: StateSlot,
+}
+
+impl PoolMints for PhoenixMarketInfo {
+    fn pool_pubkey(&self) -> &Pubkey {
+        &self.pubkey
+    }
+
+    fn mint_a(&self) -> &Pubkey {
+        &self.mint_a
+    }
+
+    fn mint_b(&self) -> &Pubkey {
+        &self.mint_b
+    }
+
+    fn required_accounts(&self) -> Vec<Pubkey> {
+        vec![self.pubkey, clock::id()]
+    }
+
+    fn ingest(&self, accounts: &AccountSnapshot) -> Result<(), ArbError> {
+        let mut book = self.book.write().unwrap();
+        let clock = match accounts.get(&clock::id()) {
+            Some(acc) => ClockInfo::from_account_data(&acc.data)?,
+            None => book.clock,
+        };
+        if let Some(acc) = accounts.get(&self.pubkey) {
+            *book = PhoenixBook::from_account_data(&acc.data, &clock)?;
+        }
+        self.slot.update(accounts, &self.required_accounts());
+        Ok(())
+    }
+
+    fn state_slot(&self) -> u64 {
+        self.slot.get()
+    }
+
+    /// Расчёт amount_out тейкерского IOC-ордера с проходом по уровням стакана.
+    ///
+    /// `token_in == mint_a()` (base) — продажа в биды, `token_in == mint_b()` (quote) — покупка из асков.
+    /// Объёмы округляются вниз до лотов, taker fee берётся в quote lots.
+    fn amount_out(
+        &self,
+        amount_in: u64,
+        token_in: &Pubkey,
+    ) -> Result<u64, ArbError> {
+        if amount_in == 0 {
+            return Ok(0);
+        }
+
+        let book = self.book.read().unwrap();
+        if book.header.status != MarketStatus::Active {
+            return Err(ArbError::PoolDisabled("Phoenix market is not active".into()));
+        }
+
+        if *token_in == *self.mint_a() {
+            self.sell_base(&book, amount_in)
+        } else if *token_in == *self.mint_b() {
+            self.buy_base(&book, amount_in)
+        } else {
+            Err(ArbError::UnsupportedMint("Token in is not mint_a or mint_b".into()))
+        }
+    }
+}
+
+impl PoolLoader for PhoenixMarketInfo {
+    fn dependent_accounts(_pool_data: &[u8]) -> Result<Vec<Pubkey>, ArbError> {
+        Ok(Vec::new())
+    }
+
+    fn from_snapshot(pool_pubkey: Pubkey, accounts: &AccountSnapshot) -> Result<Self, ArbError> {
+        let account = snapshot_account(accounts, &pool_pubkey)?;
+        Self::from_account_data(pool_pubkey, &account.data, &snapshot_clock(accounts)?)
+    }
+}
+
+impl PhoenixMarketInfo {
+    /// Создать снимок рынка из бинарных данных аккаунта.
+    pub fn from_account_data(
+        market_pubkey: Pubkey,
+        data: &[u8],
+        clock: &ClockInfo,
+    ) -> Result<Self, ArbError> {
+        let book = PhoenixBook::from_account_data(data, clock)?;
+        let header = &book.header;
+
+        let base_lots_per_base_unit = read_u64(data, BASE_LOTS_PER_BASE_UNIT_OFFSET);
+        let tick_size_in_quote_lots_per_base_unit = read_u64(data, TICK_SIZE_IN_QUOTE_LOTS_OFFSET);
+        let taker_fee_bps = read_u64(data, TAKER_FEE_BPS_OFFSET);
+
+        if base_lots_per_base_unit == 0 {
+            return Err(ArbError::Decode("Phoenix market has zero lot size".into()));
+        }
+
+        debug!(
+            "Parsed Phoenix Market: \
+             \n\tmarket={}, \
+             \n\tbase_mint={}, \
+             \n\tquote_mint={}, \
+             \n\tbase_lot_size={}, \
+             \n\tquote_lot_size={}, \
+             \n\ttaker_fee_bps={}, \
+             \n\tbid_levels={}, \
+             \n\task_levels={}",
+            market_pubkey,
+            header.base_mint,
+            header.quote_mint,
+            header.base_lot_size,
+            header.quote_lot_size,
+            taker_fee_bps,
+            book.bids.len(),
+            book.asks.len()
+        );
+
+        Ok(Self {
+            pubkey: market_pubkey,
+            mint_a: header.base_mint,
+            mint_b: header.quote_mint,
+            base_lots_per_base_unit,
+            tick_size_in_quote_lots_per_base_unit,
+            taker_fee_bps,
+            book: RwLock::new(book),
+            slot: StateSlot::default(),
+        })
+    }
+
+    /// Текущий стакан из последнего снапшота
+    pub fn book(&self) -> PhoenixBook {
+        self.book.read().unwrap().clone()
+    }
+
+    /// Создать снимок рынка напрямую из аккаунта.
+    pub fn create(market_pubkey: Pubkey, provider: &dyn AccountProvider) -> Result<Self, ArbError> {
+        load_pool(market_pubkey, provider)
+    }
+
+    /// Стоимость base_lots по цене price_in_ticks в quote lots
+    fn quote_lots_for(&self, price_in_ticks: u64, base_lots: u64) -> u128 {
+        price_in_ticks as u128 * self.tick_size_in_quote_lots_per_base_unit as u128 * base_lots as u128
+            / self.base_lots_per_base_unit as u128
+    }
+
+    /// Продажа base: проходим биды сверху вниз, комиссия снимается с полученных quote lots
+    fn sell_base(&self, book: &PhoenixBook, amount_in: u64) -> Result<u64, ArbError> {
+        let mut remaining_base_lots = amount_in / book.header.base_lot_size;
+        let mut quote_lots: u128 = 0;
+
+        for level in &book.bids {
+            if remaining_base_lots == 0 {
+                break;
+            }
+            let filled = remaining_base_lots.min(level.base_lots);
+            quote_lots += self.quote_lots_for(level.price_in_ticks, filled);
+            remaining_base_lots -= filled;
+        }
+
+        if remaining_base_lots != 0 {
+            return Err(ArbError::InsufficientLiquidity("Not enough liquidity in Phoenix bids".into()));
+        }
+
+        let fee = (quote_lots * self.taker_fee_bps as u128).div_ceil(FEE_BPS_DENOMINATOR as u128);
+        let amount_out = (quote_lots - fee) * book.header.quote_lot_size as u128;
+        u64::try_from(amount_out).map_err(|_| ArbError::MathOverflow("Phoenix amount out overflow".into()))
+    }
+
+    /// Покупка base: бюджет в quote lots заранее уменьшается на taker fee, затем проходим аски снизу вверх
+    fn buy_base(&self, book: &PhoenixBook, amount_in: u64) -> Result<u64, ArbError> {
+        let quote_lots_in = (amount_in / book.header.quote_lot_size) as u128;
+        let mut budget = quote_lots_in * FEE_BPS_DENOMINATOR as u128
+            / (FEE_BPS_DENOMINATOR + self.taker_fee_bps) as u128;
+        let mut base_lots: u128 = 0;
+
+        for level in &book.asks {
+            if budget == 0 {
+                break;
+            }
+            // Уровень с нулевой ценой программа не исполняет, а деление на цену ниже невозможно
+            let price_per_base_unit = level.price_in_ticks as u128 * self.tick_size_in_quote_lots_per_base_unit as u128;
+            if price_per_base_unit == 0 {
+                continue;
+            }
+            let level_cost = self.quote_lots_for(level.price_in_ticks, level.base_lots);
+            if level_cost <= budget {
+                base_lots += level.base_lots as u128;
+                budget -= level_cost;
+                continue;
+            }
+
+            // Частичное исполнение уровня с округлением вниз до целого лота
+            let filled = budget * self.base_lots_per_base_unit as u128 / price_per_base_unit;
+            base_lots += filled;
+            budget = 0;
+        }
+
+        if budget != 0 {
+            return Err(ArbError::InsufficientLiquidity("Not enough liquidity in Phoenix asks".into()));
+        }
+
+        let amount_out = base_lots * book.header.base_lot_size as u128;
+        u64::try_from(amount_out).map_err(|_| ArbError::MathOverflow("Phoenix amount out overflow".into()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{write_u32, write_u64};
+
+    const BOOK_SIZE: usize = 4;
+
+    /// Записать сбалансированное дерево из трёх узлов (корень — 1, слева 2, справа 3).
+    /// Ордера: (price_in_ticks, num_base_lots, last_valid_slot)
+    fn write_tree(data: &mut [u8], tree_offset: usize, orders: [(u64, u64, u64); 3]) {
+        write_u32(data, tree_offset, 1);
+        let nodes = tree_offset + TREE_HEADER_SIZE;
+        for (i, (price, lots, last_valid_slot)) in orders.iter().enumerate() {
+            let node = nodes + i * NODE_SIZE;
+            write_u64(data, node + NODE_KEY_OFFSET, *price);
+            write_u64(data, node + NODE_KEY_OFFSET + 8, i as u64);
+            write_u64(data, node + NODE_VALUE_OFFSET + 8, *lots);
+            write_u64(data, node + NODE_VALUE_OFFSET + 16, *last_valid_slot);
+        }
+        write_u32(data, nodes + REGISTER_LEFT * 4, 2);
+        write_u32(data, nodes + REGISTER_RIGHT * 4, 3);
+    }
+
+    /// Рынок SOL/USDC: base lot 0.001 SOL, quote lot 1 micro-USDC, 1000 base lots в SOL, тик 0.001 USDC.
+    fn test_market(taker_fee_bps: u64) -> PhoenixMarketInfo {
+        let asks_offset = BIDS_TREE_OFFSET + TREE_HEADER_SIZE + BOOK_SIZE * NODE_SIZE;
+        let len = asks_offset + TREE_HEADER_SIZE + BOOK_SIZE * NODE_SIZE;
+        let mut data = vec![0u8; len];
+
+        write_u64(&mut data, STATUS_OFFSET, 1);
+        write_u64(&mut data, BIDS_SIZE_OFFSET, BOOK_SIZE as u64);
+        write_u64(&mut data, ASKS_SIZE_OFFSET, BOOK_SIZE as u64);
+        data[BASE_MINT_OFFSET..BASE_MINT_OFFSET + 32].copy_from_slice(Pubkey::new_unique().as_ref());
+        data[QUOTE_MINT_OFFSET..QUOTE_MINT_OFFSET + 32].copy_from_slice(Pubkey::new_unique().as_ref());
+        write_u64(&mut data, BASE_LOT_SIZE_OFFSET, 1_000_000);
+        write_u64(&mut data, QUOTE_LOT_SIZE_OFFSET, 1);
+        write_u64(&mut data, BASE_LOTS_PER_BASE_UNIT_OFFSET, 1_000);
+        write_u64(&mut data, TICK_SIZE_IN_QUOTE_LOTS_OFFSET, 1_000);
+        write_u64(&mut data, TAKER_FEE_BPS_OFFSET, taker_fee_bps);
+
+        // Биды: 150.000 (2 SOL), 149.000 (3 SOL), 151.000 (1 SOL, просрочен на слоте 5)
+        write_tree(&mut data, BIDS_TREE_OFFSET, [(150_000, 2_000, 0), (149_000, 3_000, 0), (151_000, 1_000, 5)]);
+        // Аски: 152.000 (1 SOL), 151.500 (0.5 SOL), 153.000 (10 SOL)
+        write_tree(&mut data, asks_offset, [(152_000, 1_000, 0), (151_500, 500, 0), (153_000, 10_000, 0)]);
+
+        let clock = ClockInfo { slot: 10, ..ClockInfo::default() };
+        PhoenixMarketInfo::from_account_data(Pubkey::new_unique(), &data, &clock)
+            .expect("Failed to decode Phoenix market")
+    }
+
+    #[test]
+    fn test_phoenix_book_decoding() {
+        let book = test_market(0).book();
+        assert_eq!(
+            book.bids,
+            vec![
+                BookLevel { price_in_ticks: 150_000, base_lots: 2_000 },
+                BookLevel { price_in_ticks: 149_000, base_lots: 3_000 },
+            ]
+        );
+        assert_eq!(
+            book.asks,
+            vec![
+                BookLevel { price_in_ticks: 151_500, base_lots: 500 },
+                BookLevel { price_in_ticks: 152_000, base_lots: 1_000 },
+                BookLevel { price_in_ticks: 153_000, base_lots: 10_000 },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_phoenix_sell_walks_bids() {
+        let market = test_market(10);
+
+        // 3 SOL: 2 SOL по 150 + 1 SOL по 149 = 449 USDC, комиссия 0.1%
+        let out = market.amount_out(3_000_000_000, market.mint_a()).unwrap();
+        assert_eq!(out, 449_000_000 - 449_000);
+
+        // Остаток меньше лота отбрасывается
+        let out = market.amount_out(1_000_999, market.mint_a()).unwrap();
+        assert_eq!(out, 150_000 - 150);
+
+        assert!(market.amount_out(6_000_000_000, market.mint_a()).is_err());
+    }
+
+    #[test]
+    fn test_phoenix_buy_walks_asks() {
+        let market = test_market(0);
+
+        // 0.5 SOL по 151.5 = 75.75 USDC, затем 1 SOL по 152, остаток 10 USDC по 153
+        let out = market.amount_out(237_750_000, market.mint_b()).unwrap();
+        assert_eq!(out, 1_500_000_000 + 65_000_000);
+
+        // С комиссией бюджет уменьшается до amount_in / (1 + fee)
+        let market = test_market(10);
+        let out = market.amount_out(75_825_750, market.mint_b()).unwrap();
+        assert_eq!(out, 500_000_000);
+    }
+
+    #[test]
+    fn test_phoenix_buy_skips_zero_price_asks() {
+        let market = test_market(0);
+        market.book.write().unwrap().asks.insert(0, BookLevel { price_in_ticks: 0, base_lots: 1_000 });
+
+        // Аск с нулевой ценой пропускается: 75.75 USDC покупают 0.5 SOL по 151.5
+        let out = market.amount_out(75_750_000, market.mint_b()).unwrap();
+        assert_eq!(out, 500_000_000);
+    }
+}
@@ -1,9 +1,11 @@
-use solana_client::rpc_client::RpcClient;
+use crate::provider::{snapshot_account, AccountProvider, AccountSnapshot};
 use solana_sdk::pubkey::Pubkey;
 use log::debug;
+use std::sync::RwLock;
 
 use crate::common::{read_spl_amount, read_u64};
-use crate::dex::PoolMints;
+use crate::dex::{load_pool, PoolLoader, PoolMints, StateSlot};
+use crate::error::ArbError;
 
 const BASE_VAULT_OFFSET: usize = 336; // coinVault/tokenVaultA
 const QUOTE_VAULT_OFFSET: usize = 368; // pcVault/tokenVaultB
@@ -156,7 +158,7 @@ pub struct AmmInfo {
     pub amm_owner: Pubkey,
     /// pool lp amount
     pub lp_amount: u64,
-    /// client order id
+    /// provider order id
     pub client_order_id: u64,
     /// recent epoch
     pub recent_epoch: u64,
@@ -187,6 +189,10 @@ pub struct RaydiumAmmPoolInfo {
     base_mint: Pubkey,
     quote_mint: Pubkey,
     pub fees: Fees,
+    /// Балансы vault-аккаунтов (base, quote) из последнего снапшота
+    reserves: RwLock<(u64, u64)>,
+    /// Слот снапшота, к которому относится состояние
+    slot: StateSlot,
 }
 
 impl PoolMints for RaydiumAmmPoolInfo {
@@ -202,6 +208,29 @@ impl PoolMints for RaydiumAmmPoolInfo {
         &self.quote_mint
     }
 
+    fn required_accounts(&self) -> Vec<Pubkey> {
+        vec![self.base_vault, self.quote_vault]
+    }
+
+    fn ingest(&self, accounts: &AccountSnapshot) -> Result<(), ArbError> {
+        // Оба vault читаются до записи: при ошибке разбора резервы не меняются
+        let base = accounts.get(&self.base_vault).map(read_spl_amount).transpose()?;
+        let quote = accounts.get(&self.quote_vault).map(read_spl_amount).transpose()?;
+        let mut reserves = self.reserves.write().unwrap();
+        if let Some(base) = base {
+            reserves.0 = base;
+        }
+        if let Some(quote) = quote {
+            reserves.1 = quote;
+        }
+        self.slot.update(accounts, &self.required_accounts());
+        Ok(())
+    }
+
+    fn state_slot(&self) -> u64 {
+        self.slot.get()
+    }
+
     /// Расчёт amount_out для свопа в AMM v4 (формула x*y=k) с учётом комиссии.
     ///
     /// `amount_in` задаётся в натуральных единицах токена (u64 в минимальных долях).
@@ -209,27 +238,20 @@ impl PoolMints for RaydiumAmmPoolInfo {
     /// если `mint_b()` — наоборот.
     fn amount_out(
         &self,
-        client: &RpcClient,
         amount_in: u64,
         token_in: &Pubkey,
-    ) -> Result<u64, Box<dyn std::error::Error>> {
+    ) -> Result<u64, ArbError> {
         if amount_in == 0 {
             return Ok(0);
         }
 
-        let base_vault_acc = client.get_account(&self.base_vault)?;
-        let quote_vault_acc = client.get_account(&self.quote_vault)?;
-
-        let base_raw = read_spl_amount(&base_vault_acc) as u128;
-        let quote_raw = read_spl_amount(&quote_vault_acc) as u128;
+        let (base_reserve, quote_reserve) = *self.reserves.read().unwrap();
+        let base_raw = base_reserve as u128;
+        let quote_raw = quote_reserve as u128;
 
         let amount_in_u128 = amount_in as u128;
 
-        let swap_fee = amount_in_u128
-            .checked_mul(self.fees.swap_fee_numerator.into())
-            .unwrap()
-            .checked_ceil_div(self.fees.swap_fee_denominator.into())
-            .unwrap();
+        let swap_fee = self.swap_fee(amount_in)?;
 
         // Комиссия снимается из amount_in
         let amount_in_after_fee = amount_in_u128.saturating_sub(swap_fee);
@@ -239,7 +261,7 @@ impl PoolMints for RaydiumAmmPoolInfo {
         } else if *token_in == *self.mint_b() {
             (quote_raw, base_raw)
         } else {
-            return Err("token_in is neither mint_a nor mint_b".into());
+            return Err(ArbError::UnsupportedMint("token_in is neither mint_a nor mint_b".into()));
         };
 
         if reserve_in == 0 || reserve_out == 0 {
@@ -250,13 +272,37 @@ impl PoolMints for RaydiumAmmPoolInfo {
 
         Ok(amount_out)
     }
+
+    fn marginal_rate(&self, token_in: &Pubkey, _probe: u64) -> Option<f64> {
+        // Постоянное произведение: спот-цена reserve_out / reserve_in за вычетом комиссии
+        let (base_reserve, quote_reserve) = *self.reserves.read().unwrap();
+        let (reserve_in, reserve_out) = if *token_in == *self.mint_a() {
+            (base_reserve, quote_reserve)
+        } else if *token_in == *self.mint_b() {
+            (quote_reserve, base_reserve)
+        } else {
+            return None;
+        };
+        if reserve_in == 0 || reserve_out == 0 || self.fees.swap_fee_denominator == 0 {
+            return None;
+        }
+
+        let fee = self.fees.swap_fee_numerator as f64 / self.fees.swap_fee_denominator as f64;
+        Some(reserve_out as f64 / reserve_in as f64 * (1.0 - fee))
+    }
 }
 
-// Комментарий
-impl RaydiumAmmPoolInfo {
-    /// Создать из бинарных данных аккаунта
-    pub fn create(pool_pubkey: Pubkey, client: &RpcClient) -> Result<Self, Box<dyn std::error::Error>> {
-        let account = client.get_account(&pool_pubkey)?;
+impl PoolLoader for RaydiumAmmPoolInfo {
+    fn dependent_accounts(_pool_data: &[u8]) -> Result<Vec<Pubkey>, ArbError> {
+        Ok(Vec::new())
+    }
+
+    /// Создать из бинарных данных аккаунта; резервы приходят через `ingest`
+    fn from_snapshot(pool_pubkey: Pubkey, accounts: &AccountSnapshot) -> Result<Self, ArbError> {
+        let account = snapshot_account(accounts, &pool_pubkey)?;
+        if account.data.len() < QUOTE_MINT_OFFSET + 32 {
+            return Err(ArbError::Decode("Invalid data length for AMM pool".into()));
+        }
 
         let base_vault = Pubkey::new_from_array(
             account.data[BASE_VAULT_OFFSET..BASE_VAULT_OFFSET + 32].try_into().unwrap()); // offset vaultA
@@ -282,23 +328,36 @@ impl RaydiumAmmPoolInfo {
             base_mint,
             quote_mint,
             fees,
+            reserves: RwLock::new((0, 0)),
+            slot: StateSlot::default(),
         })
     }
+}
 
-    pub fn calc_swap_fee(&self, amount_in: u64) -> Result<u64, Box<dyn std::error::Error>> {
-        let swap_fee = (amount_in as u128)
-            .checked_mul(self.fees.swap_fee_numerator.into())
-            .unwrap()
-            .checked_ceil_div(self.fees.swap_fee_denominator.into())
-            .unwrap() as u64;
+// Комментарий
+impl RaydiumAmmPoolInfo {
+    /// Создать структуру пула, дочитав резервы из vault-аккаунтов
+    pub fn create(pool_pubkey: Pubkey, provider: &dyn AccountProvider) -> Result<Self, ArbError> {
+        load_pool(pool_pubkey, provider)
+    }
 
-        Ok(swap_fee)
+    pub fn calc_swap_fee(&self, amount_in: u64) -> Result<u64, ArbError> {
+        u64::try_from(self.swap_fee(amount_in)?).map_err(|_| ArbError::MathOverflow("AMM swap fee overflow".into()))
+    }
+
+    // Комиссия с amount_in с округлением вверх; нулевой знаменатель — повреждённые данные пула
+    fn swap_fee(&self, amount_in: u64) -> Result<u128, ArbError> {
+        (amount_in as u128)
+            .checked_mul(self.fees.swap_fee_numerator.into())
+            .and_then(|fee| fee.checked_ceil_div(self.fees.swap_fee_denominator.into()))
+            .ok_or_else(|| ArbError::MathOverflow("AMM swap fee with zero denominator".into()))
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::test_utils::spl_token_account;
     use solana_client::rpc_client::RpcClient;
     use solana_sdk::pubkey::Pubkey;
     use std::str::FromStr;
@@ -336,7 +395,7 @@ mod tests {
 
         // Вычисляем amount_out для свопа base_mint -> quote_mint
         // Выходное количество без учета запятой. Она не нужна при расчете арбитража
-        let amount_out = pool_info.amount_out(&client, amount_in, pool_info.mint_a())
+        let amount_out = pool_info.amount_out(amount_in, pool_info.mint_a())
             .expect("Failed to calculate amount_out");
 
         let swap_fee = pool_info.calc_swap_fee(amount_in).expect("Can't calc swap fee");
@@ -346,6 +405,81 @@ mod tests {
         println!("  amount_out: {} (quote token)", amount_out);
         println!("  swap_fee: {}", swap_fee);
     }
+
+    #[test]
+    fn test_amm_quotes_from_ingested_snapshot() {
+        use crate::provider::InMemoryAccountProvider;
+
+        let pool_pubkey = Pubkey::new_unique();
+        let (base_vault, quote_vault) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let (base_mint, quote_mint) = (Pubkey::new_unique(), Pubkey::new_unique());
+
+        let mut data = vec![0u8; 752];
+        data[BASE_VAULT_OFFSET..BASE_VAULT_OFFSET + 32].copy_from_slice(base_vault.as_ref());
+        data[QUOTE_VAULT_OFFSET..QUOTE_VAULT_OFFSET + 32].copy_from_slice(quote_vault.as_ref());
+        data[BASE_MINT_OFFSET..BASE_MINT_OFFSET + 32].copy_from_slice(base_mint.as_ref());
+        data[QUOTE_MINT_OFFSET..QUOTE_MINT_OFFSET + 32].copy_from_slice(quote_mint.as_ref());
+        data[FEES_OFFSET + 48..FEES_OFFSET + 56].copy_from_slice(&25u64.to_le_bytes());
+        data[FEES_OFFSET + 56..FEES_OFFSET + 64].copy_from_slice(&10_000u64.to_le_bytes());
+
+        let mut provider = InMemoryAccountProvider::new(100);
+        provider.insert(pool_pubkey, solana_sdk::account::Account { data, ..Default::default() });
+        provider.insert(base_vault, spl_token_account(1_000_000_000_000));
+        provider.insert(quote_vault, spl_token_account(150_000_000_000));
+
+        let pool = RaydiumAmmPoolInfo::create(pool_pubkey, &provider).unwrap();
+        assert_eq!(pool.required_accounts(), vec![base_vault, quote_vault]);
+        assert_eq!(pool.state_slot(), 100);
+
+        // 1 base с комиссией 0.25%: 150_000 * 0.9975 / 1001 * 1000 (округление вниз)
+        let out = pool.amount_out(1_000_000_000, &base_mint).unwrap();
+        assert_eq!(out, 149_475_898);
+
+        // Маржинальный курс — спот-цена за вычетом комиссии, выше курса полного объёма
+        let rate = pool.marginal_rate(&base_mint, 0).unwrap();
+        assert!((rate - 0.15 * 0.9975).abs() < 1e-12);
+        assert!(rate > out as f64 / 1_000_000_000.0);
+        assert!((pool.marginal_rate(&quote_mint, 0).unwrap() - 1.0 / 0.15 * 0.9975).abs() < 1e-9);
+        assert!(pool.marginal_rate(&Pubkey::new_unique(), 0).is_none());
+
+        // Новый снапшот меняет резервы без пересоздания пула
+        let mut snapshot = crate::provider::AccountSnapshot::new();
+        snapshot.insert(quote_vault, spl_token_account(300_000_000_000), 105);
+        pool.ingest(&snapshot).unwrap();
+        assert_eq!(pool.amount_out(1_000_000_000, &base_mint).unwrap(), 298_951_796);
+        assert_eq!(pool.state_slot(), 105);
+    }
+
+    #[test]
+    fn test_amm_rejects_malformed_accounts_without_panicking() {
+        let pool_pubkey = Pubkey::new_unique();
+        let (base_vault, quote_vault) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let base_mint = Pubkey::new_unique();
+
+        // Короткий аккаунт программы AMM v4
+        let mut snapshot = crate::provider::AccountSnapshot::new();
+        snapshot.insert(pool_pubkey, solana_sdk::account::Account { data: vec![0u8; 100], ..Default::default() }, 100);
+        assert!(matches!(RaydiumAmmPoolInfo::from_snapshot(pool_pubkey, &snapshot), Err(ArbError::Decode(_))));
+
+        // Нулевой знаменатель комиссии
+        let mut data = vec![0u8; 752];
+        data[BASE_VAULT_OFFSET..BASE_VAULT_OFFSET + 32].copy_from_slice(base_vault.as_ref());
+        data[QUOTE_VAULT_OFFSET..QUOTE_VAULT_OFFSET + 32].copy_from_slice(quote_vault.as_ref());
+        data[BASE_MINT_OFFSET..BASE_MINT_OFFSET + 32].copy_from_slice(base_mint.as_ref());
+        snapshot.insert(pool_pubkey, solana_sdk::account::Account { data, ..Default::default() }, 100);
+        snapshot.insert(base_vault, spl_token_account(1_000), 100);
+        snapshot.insert(quote_vault, spl_token_account(1_000), 100);
+        let pool = RaydiumAmmPoolInfo::from_snapshot(pool_pubkey, &snapshot).unwrap();
+        pool.ingest(&snapshot).unwrap();
+        assert!(matches!(pool.amount_out(10, &base_mint), Err(ArbError::MathOverflow(_))));
+        assert!(matches!(pool.calc_swap_fee(10), Err(ArbError::MathOverflow(_))));
+
+        // Закрытый vault приходит с пустыми данными: ошибка, резервы прежние
+        let mut closed = crate::provider::AccountSnapshot::new();
+        closed.insert(quote_vault, solana_sdk::account::Account::default(), 101);
+        assert!(matches!(pool.ingest(&closed), Err(ArbError::Decode(_))));
+        assert_eq!(*pool.reserves.read().unwrap(), (1_000, 1_000));
+    }
 }
 
 
@@ -1,10 +1,13 @@
-use solana_client::rpc_client::RpcClient;
+use crate::provider::{snapshot_account, AccountProvider, AccountSnapshot};
 use solana_sdk::pubkey::Pubkey;
 use std::convert::TryInto;
-use log::{info, debug};
+use std::sync::RwLock;
+use log::debug;
 
-use crate::common::read_mint_decimals;
-use crate::dex::PoolMints;
+use crate::common::{read_mint_decimals, read_pubkey};
+use crate::dex::{load_pool, PoolLoader, PoolMints, StateSlot};
+use crate::error::ArbError;
+use crate::events::PoolEvent;
 
 /* Trade Fee Rate: 500 Расчет: 
 trade_fee_rate (например 500) / 1,000,000=0.0005 (или 0.05%). 
@@ -148,21 +151,77 @@ pub struct PoolState {
 }
 
 // Offsets внутри аккаунта пула CLMM (PoolState), уже с учётом первых 8 байт discriminator.
-const BUMP_OFFSET: usize = 8;
+// Не используются: bump (8), owner (41), observation_key (201), mint_decimals_0/1 (233/234) —
+// decimals читаются из mint-аккаунтов.
 const AMM_CONFIG_OFFSET: usize = 9;
-const OWNER_OFFSET: usize = 41;
 const MINT_A_OFFSET: usize = 73;       // token_mint_0
 const MINT_B_OFFSET: usize = 105;      // token_mint_1
 const VAULT_A_OFFSET: usize = 137;     // token_vault_0
 const VAULT_B_OFFSET: usize = 169;     // token_vault_1
-const OBSERVATION_KEY_OFFSET: usize = 201;
-const DECIMALS_A_OFFSET: usize = 233;  // mint_decimals_0
-const DECIMALS_B_OFFSET: usize = 234;  // mint_decimals_1
 const TICK_SPACING_OFFSET: usize = 235; // u16
 const LIQUIDITY_OFFSET: usize = 237;   // u128, 237..253
 const SQRT_PRICE_X64_OFFSET: usize = 253; // u128, 253..269
 const TICK_CURRENT_OFFSET: usize = 269;   // i32, 269..273
 
+/// Изменяемая часть PoolState, которая обновляется из снапшота
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ClmmState {
+    pub liquidity: u128,
+    pub sqrt_price_x64: u128,
+    pub tick_current: i32,
+}
+
+impl ClmmState {
+    pub fn from_pool_data(data: &[u8]) -> Result<Self, ArbError> {
+        let liquidity_bytes: [u8; 16] = data[LIQUIDITY_OFFSET..LIQUIDITY_OFFSET + 16].try_into()?;
+        let sqrt_price_bytes: [u8; 16] = data[SQRT_PRICE_X64_OFFSET..SQRT_PRICE_X64_OFFSET + 16].try_into()?;
+        let tick_current_bytes: [u8; 4] = data[TICK_CURRENT_OFFSET..TICK_CURRENT_OFFSET + 4].try_into()?;
+
+        Ok(Self {
+            liquidity: u128::from_le_bytes(liquidity_bytes),
+            sqrt_price_x64: u128::from_le_bytes(sqrt_price_bytes),
+            tick_current: i32::from_le_bytes(tick_current_bytes),
+        })
+    }
+}
+
+// Offsets полей SwapEvent после 8 байт discriminator:
+// pool_state, sender, token_account_0, token_account_1 (по 32), amount_0, transfer_fee_0,
+// amount_1, transfer_fee_1 (по u64), zero_for_one (bool), sqrt_price_x64, liquidity (u128), tick (i32)
+const SWAP_EVENT_POOL_OFFSET: usize = 0;
+const SWAP_EVENT_ZERO_FOR_ONE_OFFSET: usize = 160;
+const SWAP_EVENT_SQRT_PRICE_OFFSET: usize = 161;
+const SWAP_EVENT_LIQUIDITY_OFFSET: usize = 177;
+const SWAP_EVENT_TICK_OFFSET: usize = 193;
+const SWAP_EVENT_LEN: usize = 197;
+
+/// Событие `SwapEvent` программы CLMM: состояние пула после свопа
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ClmmSwapEvent {
+    pub pool_state: Pubkey,
+    pub zero_for_one: bool,
+    pub sqrt_price_x64: u128,
+    pub liquidity: u128,
+    pub tick: i32,
+}
+
+impl ClmmSwapEvent {
+    /// Разобрать тело события (без discriminator)
+    pub fn decode(data: &[u8]) -> Result<Self, ArbError> {
+        if data.len() < SWAP_EVENT_LEN {
+            return Err(ArbError::Decode("Invalid data length for CLMM SwapEvent".into()));
+        }
+
+        Ok(Self {
+            pool_state: read_pubkey(data, SWAP_EVENT_POOL_OFFSET),
+            zero_for_one: data[SWAP_EVENT_ZERO_FOR_ONE_OFFSET] != 0,
+            sqrt_price_x64: u128::from_le_bytes(data[SWAP_EVENT_SQRT_PRICE_OFFSET..SWAP_EVENT_SQRT_PRICE_OFFSET + 16].try_into()?),
+            liquidity: u128::from_le_bytes(data[SWAP_EVENT_LIQUIDITY_OFFSET..SWAP_EVENT_LIQUIDITY_OFFSET + 16].try_into()?),
+            tick: i32::from_le_bytes(data[SWAP_EVENT_TICK_OFFSET..SWAP_EVENT_TICK_OFFSET + 4].try_into()?),
+        })
+    }
+}
+
 /// Минимальная структура CLMM-пула, достаточная для off-chain расчётов арбитража.
 pub struct RaydiumClmmPoolInfo {
     pub pubkey: Pubkey,
@@ -174,11 +233,11 @@ pub struct RaydiumClmmPoolInfo {
     pub decimals_a: u8,
     pub decimals_b: u8,
     pub tick_spacing: u16,
-    pub liquidity: u128,
-    pub sqrt_price_x64: u128,
-    pub tick_current: i32,
     /// Комиссия пула (trade fee) в basis points, например 25 = 0.25%
     pub fee_rate_bps: u16,
+    state: RwLock<ClmmState>,
+    /// Слот снапшота, к которому относится состояние
+    slot: StateSlot,
 }
 
 impl PoolMints for RaydiumClmmPoolInfo {
@@ -194,6 +253,42 @@ impl PoolMints for RaydiumClmmPoolInfo {
         &self.mint_b
     }
 
+    fn required_accounts(&self) -> Vec<Pubkey> {
+        vec![self.pubkey]
+    }
+
+    fn ingest(&self, accounts: &AccountSnapshot) -> Result<(), ArbError> {
+        if let Some(acc) = accounts.get(&self.pubkey) {
+            *self.state.write().unwrap() = ClmmState::from_pool_data(&acc.data)?;
+        }
+        self.slot.update(accounts, &self.required_accounts());
+        Ok(())
+    }
+
+    fn state_slot(&self) -> u64 {
+        self.slot.get()
+    }
+
+    /// SwapEvent несёт sqrt_price, liquidity и tick после свопа — этого достаточно для состояния
+    fn apply_event(&self, event: &PoolEvent, slot: u64) -> Result<bool, ArbError> {
+        // Единственный вариант, если собран только один DEX с событиями
+        #[allow(irrefutable_let_patterns)]
+        let PoolEvent::ClmmSwap(swap) = event else {
+            return Ok(false);
+        };
+        if swap.pool_state != self.pubkey || slot < self.slot.get() {
+            return Ok(false);
+        }
+
+        *self.state.write().unwrap() = ClmmState {
+            liquidity: swap.liquidity,
+            sqrt_price_x64: swap.sqrt_price_x64,
+            tick_current: swap.tick,
+        };
+        self.slot.advance(slot);
+        Ok(true)
+    }
+
     /// Упрощённый расчёт amount_out для небольших свопов на текущем тике.
     ///
     /// Для полноценной реализации нужен перебор tick array и распределения ликвидности,
@@ -201,12 +296,12 @@ impl PoolMints for RaydiumClmmPoolInfo {
     /// на основе текущего sqrt_price_x64 и liquidity.
     fn amount_out(
         &self,
-        _client: &RpcClient,
         amount_in: u64,
         token_in: &Pubkey,
-    ) -> Result<u64, Box<dyn std::error::Error>> {
-        if amount_in == 0 || self.liquidity == 0 {
-            return Err("Amount in is 0 or liquidity is 0".into());
+    ) -> Result<u64, ArbError> {
+        let state = self.state();
+        if amount_in == 0 || state.liquidity == 0 {
+            return Err(ArbError::InsufficientLiquidity("Amount in is 0 or liquidity is 0".into()));
         }
 
         // Применяем комиссию пула к входящему количеству.
@@ -224,16 +319,16 @@ impl PoolMints for RaydiumClmmPoolInfo {
         // Для малых amount_in можно аппроксимировать локальным производным,
         // что эквивалентно использованию текущей цены без сильного сдвига sqrtP.
 
-        let sqrt_p = self.sqrt_price_x64 as f64 / (2u128.pow(64) as f64);
+        let sqrt_p = state.sqrt_price_x64 as f64 / (2u128.pow(64) as f64);
         if sqrt_p == 0.0 {
-            return Err("Sqrt price is 0".into());
+            return Err(ArbError::MathOverflow("Sqrt price is 0".into()));
         }
 
         // Текущая цена token_b / token_a.
         let price = (sqrt_p * sqrt_p)
-            * 10f64.powi((self.decimals_a as i32 - self.decimals_b as i32) as i32);
+            * 10f64.powi(self.decimals_a as i32 - self.decimals_b as i32);
         if price == 0.0 {
-            return Err("Price is 0".into());
+            return Err(ArbError::MathOverflow("Price is 0".into()));
         }
 
         // В локальной линейной аппроксимации:
@@ -241,25 +336,40 @@ impl PoolMints for RaydiumClmmPoolInfo {
         let amount_in_f = amount_in_after_fee as f64;
        
         let amount_out_f = if *token_in == *self.mint_a() {  // a -> b
-            amount_in_f * price * 10f64.powi((self.decimals_b as i32 - self.decimals_a as i32) as i32)
+            amount_in_f * price * 10f64.powi(self.decimals_b as i32 - self.decimals_a as i32)
         } else if *token_in == *self.mint_b() { // b -> a
-            amount_in_f / price * 10f64.powi((self.decimals_a as i32 - self.decimals_b as i32) as i32)
+            amount_in_f / price * 10f64.powi(self.decimals_a as i32 - self.decimals_b as i32)
         } else {
-            return Err("Token in is not mint_a or mint_b".into());
+            return Err(ArbError::UnsupportedMint("Token in is not mint_a or mint_b".into()));
         };
 
         if amount_out_f <= 0.0 {
-            return Err("Amount out is less than 0".into());
+            Err(ArbError::MathOverflow("Amount out is less than 0".into()))
         } else {
             Ok(amount_out_f as u64)
         }
     }
 }
 
-impl RaydiumClmmPoolInfo {
+impl PoolLoader for RaydiumClmmPoolInfo {
+    /// Mint-аккаунты (decimals) и AmmConfig (fee rate)
+    fn dependent_accounts(pool_data: &[u8]) -> Result<Vec<Pubkey>, ArbError> {
+        if pool_data.len() < TICK_CURRENT_OFFSET + 4 {
+            return Err(ArbError::Decode("Invalid data length for CLMM PoolState".into()));
+        }
+        Ok(vec![
+            read_pubkey(pool_data, MINT_A_OFFSET),
+            read_pubkey(pool_data, MINT_B_OFFSET),
+            read_pubkey(pool_data, AMM_CONFIG_OFFSET),
+        ])
+    }
+
     /// Создать структуру пула из бинарных данных аккаунта PoolState.
-    pub fn create(pool_pubkey: Pubkey, client: &RpcClient) -> Result<Self, Box<dyn std::error::Error>> {
-        let account = client.get_account(&pool_pubkey)?;
+    fn from_snapshot(pool_pubkey: Pubkey, accounts: &AccountSnapshot) -> Result<Self, ArbError> {
+        let account = snapshot_account(accounts, &pool_pubkey)?;
+        if account.data.len() < TICK_CURRENT_OFFSET + 4 {
+            return Err(ArbError::Decode("Invalid data length for CLMM PoolState".into()));
+        }
 
         let amm_config = Pubkey::new_from_array(
             account.data[AMM_CONFIG_OFFSET..AMM_CONFIG_OFFSET + 32].try_into().unwrap(),
@@ -281,24 +391,15 @@ impl RaydiumClmmPoolInfo {
         let tick_spacing_bytes: [u8; 2] = account.data[TICK_SPACING_OFFSET..TICK_SPACING_OFFSET + 2].try_into()?;
         let tick_spacing = u16::from_le_bytes(tick_spacing_bytes);
 
-        let liquidity_bytes: [u8; 16] = account.data[LIQUIDITY_OFFSET..LIQUIDITY_OFFSET + 16].try_into()?;
-        let liquidity = u128::from_le_bytes(liquidity_bytes);
-
-        let sqrt_price_bytes: [u8; 16] = account.data[SQRT_PRICE_X64_OFFSET..SQRT_PRICE_X64_OFFSET + 16].try_into()?;
-        let sqrt_price_x64 = u128::from_le_bytes(sqrt_price_bytes);
-
-        let tick_current_bytes: [u8; 4] = account.data[TICK_CURRENT_OFFSET..TICK_CURRENT_OFFSET + 4].try_into()?;
-        let tick_current = i32::from_le_bytes(tick_current_bytes);
+        let state = ClmmState::from_pool_data(&account.data)?;
 
         // Десятичные разряды читаем из mint-аккаунтов, а не из PoolState,
         // чтобы быть совместимыми с AMM-частью и унифицировать логику.
-        let mint_a_acc = client.get_account(&mint_a)?;
-        let mint_b_acc = client.get_account(&mint_b)?;
-        let decimals_a = read_mint_decimals(&mint_a_acc) as u8;
-        let decimals_b = read_mint_decimals(&mint_b_acc) as u8;
+        let decimals_a = read_mint_decimals(snapshot_account(accounts, &mint_a)?);
+        let decimals_b = read_mint_decimals(snapshot_account(accounts, &mint_b)?);
 
         // Читаем fee_rate из AmmConfig аккаунта.
-        let fee_rate_bps = read_clmm_fee_rate_bps(client, &amm_config)?;
+        let fee_rate_bps = read_clmm_fee_rate_bps(&snapshot_account(accounts, &amm_config)?.data)?;
 
         debug!(
             "Parsed CLMM Pool: \
@@ -317,9 +418,9 @@ impl RaydiumClmmPoolInfo {
             vault_a,
             vault_b,
             amm_config,
-            liquidity,
-            sqrt_price_x64,
-            tick_current,
+            state.liquidity,
+            state.sqrt_price_x64,
+            state.tick_current,
             tick_spacing,
             fee_rate_bps
         );
@@ -334,25 +435,30 @@ impl RaydiumClmmPoolInfo {
             decimals_a,
             decimals_b,
             tick_spacing,
-            liquidity,
-            sqrt_price_x64,
-            tick_current,
             fee_rate_bps,
+            state: RwLock::new(state),
+            slot: StateSlot::default(),
         })
     }
+}
+
+impl RaydiumClmmPoolInfo {
+    /// Создать структуру пула, дочитав mint-аккаунты и AmmConfig.
+    pub fn create(pool_pubkey: Pubkey, provider: &dyn AccountProvider) -> Result<Self, ArbError> {
+        load_pool(pool_pubkey, provider)
+    }
+
+    /// Текущее состояние пула из последнего снапшота
+    pub fn state(&self) -> ClmmState {
+        *self.state.read().unwrap()
+    }
+
+    /// Посчитать текущую цену quote/base на основе sqrt_price_x64 из снапшота.
+    pub fn price(&self) -> Result<f64, ArbError> {
+        let sqrt_price_x64 = self.state().sqrt_price_x64;
 
-    /// Посчитать текущую цену quote/base на основе sqrt_price_x64.
-    /// Получает свежие данные пула перед расчётом цены.
-    pub fn price(&self, client: &RpcClient) -> Result<f64, Box<dyn std::error::Error>> {
-        // Получаем свежие данные пула для актуального sqrt_price_x64
-        let account = client.get_account(&self.pubkey)?;
-        
-        let sqrt_price_bytes: [u8; 16] =
-            account.data[SQRT_PRICE_X64_OFFSET..SQRT_PRICE_X64_OFFSET + 16].try_into()?;
-        let sqrt_price_x64 = u128::from_le_bytes(sqrt_price_bytes);
-        
         let sqrt_price = (sqrt_price_x64 as f64) / (2u128.pow(64) as f64);
-        let decimals_diff = (self.decimals_a as i32 - self.decimals_b as i32) as i32;
+        let decimals_diff = self.decimals_a as i32 - self.decimals_b as i32;
         let price = (sqrt_price * sqrt_price) * 10f64.powi(decimals_diff);
 
         debug!(
@@ -377,13 +483,7 @@ impl RaydiumClmmPoolInfo {
 /// 
 /// trade_fee_rate хранится как u32 в формате "hundredths of a bip" (10^-6),
 /// конвертируем в basis points: value / 100.
-fn read_clmm_fee_rate_bps(
-    client: &RpcClient,
-    amm_config: &Pubkey,
-) -> Result<u16, Box<dyn std::error::Error>> {
-    let acc = client.get_account(amm_config)?;
-    let data = acc.data;
-
+fn read_clmm_fee_rate_bps(data: &[u8]) -> Result<u16, ArbError> {
     // Правильный offset для trade_fee_rate (u32) в структуре AmmConfig
     // discriminator (8) + bump (1) + index (2) + owner (32) + protocol_fee_rate (4) = 47
     const TRADE_FEE_RATE_OFFSET: usize = 39;
@@ -474,9 +574,9 @@ mod tests {
 
         // Выводим полученную структуру AmmConfig
         println!("AmmConfig+ structure:");
-        println!("  amm_config: {}", pool_info.amm_config.to_string());
+        println!("  amm_config: {}", pool_info.amm_config);
         let mut offset = 11;
-        println!("  owner: {}", Pubkey::new_from_array(data[offset .. offset + 32].try_into().unwrap()).to_string());
+        println!("  owner: {}", Pubkey::new_from_array(data[offset .. offset + 32].try_into().unwrap()));
         offset += 32;
         println!("  protocol_fee_rate: {}", u32::from_le_bytes(data[offset .. offset + 4].try_into().unwrap()));
         offset += 4;
@@ -488,6 +588,6 @@ mod tests {
         offset += 4;
         println!("  padding_u32: {}", u32::from_le_bytes(data[offset .. offset + 4].try_into().unwrap()));
         offset += 4;
-        println!("  owner: {}", Pubkey::new_from_array(data[offset .. offset + 32].try_into().unwrap()).to_string());
+        println!("  owner: {}", Pubkey::new_from_array(data[offset .. offset + 32].try_into().unwrap()));
     }
 }
\ No newline at end of file
@@ -0,0 +1,13 @@
+use solana_sdk::pubkey::Pubkey;
+use std::str::FromStr;
+
+pub fn amm_v4_program_id() -> Pubkey {
+    Pubkey::from_str("675kPX9MHTjS2zt1qfr1NYHuzeLXfQM9H24wFSUt1Mp8").unwrap()
+}
+
+pub fn clmm_program_id() -> Pubkey {
+    Pubkey::from_str("CAMMCzo5YL8w4VFF8KVHrK22GGUsp5VXuhuQzZ8GYPAJ").unwrap()
+}
+
+/// Discriminator события `SwapEvent`: sha256("event:SwapEvent")[..8]
+pub const CLMM_SWAP_EVENT_DISCRIMINATOR: [u8; 8] = [64, 198, 205, 232, 38, 8, 113, 226];
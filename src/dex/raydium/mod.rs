@@ -1,2 +1,3 @@
 pub mod amm;
 pub mod clmm;
+pub mod constants;
@@ -0,0 +1,107 @@
+use solana_sdk::account::Account;
+use solana_sdk::pubkey::Pubkey;
+use std::collections::HashMap;
+
+#[cfg(feature = "meteora")]
+use crate::dex::meteora::constants::{dbc_program_id, dlmm_program_id};
+#[cfg(feature = "openbook")]
+use crate::dex::openbook::constants::openbook_v2_program_id;
+#[cfg(feature = "phoenix")]
+use crate::dex::phoenix::constants::phoenix_program_id;
+#[cfg(feature = "raydium")]
+use crate::dex::raydium::constants::{amm_v4_program_id, clmm_program_id};
+#[cfg(feature = "saber")]
+use crate::dex::saber::constants::saber_program_id;
+#[cfg(feature = "stake-pool")]
+use crate::dex::stake_pool::constants::stake_pool_program_id;
+use crate::error::ArbError;
+use crate::loader::PoolKind;
+
+/// Реестр DEX: программа-владелец аккаунта пула -> тип адаптера.
+///
+/// Тип пула определяется по `account.owner`, поэтому в конфиге достаточно адреса пула,
+/// а аккаунт неизвестной программы отклоняется вместо разбора по чужим смещениям.
+#[derive(Clone, Debug)]
+pub struct DexRegistry {
+    programs: HashMap<Pubkey, PoolKind>,
+}
+
+/// Программы DEX, адаптеры которых включены cargo features
+impl Default for DexRegistry {
+    #[allow(unused_mut)]
+    fn default() -> Self {
+        let mut registry = Self { programs: HashMap::new() };
+        #[cfg(feature = "raydium")]
+        {
+            registry.register(amm_v4_program_id(), PoolKind::RaydiumAmm);
+            registry.register(clmm_program_id(), PoolKind::RaydiumClmm);
+        }
+        #[cfg(feature = "meteora")]
+        {
+            registry.register(dlmm_program_id(), PoolKind::MeteoraDlmm);
+            registry.register(dbc_program_id(), PoolKind::MeteoraDbc);
+        }
+        #[cfg(feature = "phoenix")]
+        registry.register(phoenix_program_id(), PoolKind::Phoenix);
+        #[cfg(feature = "openbook")]
+        registry.register(openbook_v2_program_id(), PoolKind::Openbook);
+        #[cfg(feature = "saber")]
+        registry.register(saber_program_id(), PoolKind::Saber);
+        #[cfg(feature = "stake-pool")]
+        registry.register(stake_pool_program_id(), PoolKind::StakePool);
+        registry
+    }
+}
+
+impl DexRegistry {
+    /// Добавить программу (например, форк с той же разметкой аккаунтов)
+    pub fn register(&mut self, program_id: Pubkey, kind: PoolKind) {
+        self.programs.insert(program_id, kind);
+    }
+
+    /// Тип адаптера для программы-владельца
+    pub fn kind_for_owner(&self, owner: &Pubkey) -> Option<PoolKind> {
+        self.programs.get(owner).copied()
+    }
+
+    /// Тип адаптера для аккаунта пула; неизвестный владелец — ошибка
+    pub fn resolve(&self, pool_pubkey: &Pubkey, account: &Account) -> Result<PoolKind, ArbError> {
+        self.kind_for_owner(&account.owner).ok_or_else(|| {
+            ArbError::Config(format!(
+                "pool {} is owned by unknown program {}",
+                pool_pubkey, account.owner
+            ))
+        })
+    }
+
+    /// Проверить, что аккаунт принадлежит программе указанного типа
+    pub fn check(&self, pool_pubkey: &Pubkey, account: &Account, expected: PoolKind) -> Result<(), ArbError> {
+        let kind = self.resolve(pool_pubkey, account)?;
+        if kind != expected {
+            return Err(ArbError::Config(format!(
+                "pool {} is listed as {:?} but owned by {:?} program {}",
+                pool_pubkey, expected, kind, account.owner
+            )));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(all(test, feature = "meteora"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_registry_resolves_owner_program() {
+        let registry = DexRegistry::default();
+        let pool = Pubkey::new_unique();
+        let account = Account { owner: dlmm_program_id(), ..Account::default() };
+
+        assert_eq!(registry.resolve(&pool, &account).unwrap(), PoolKind::MeteoraDlmm);
+        assert!(registry.check(&pool, &account, PoolKind::MeteoraDlmm).is_ok());
+        assert!(matches!(registry.check(&pool, &account, PoolKind::RaydiumClmm), Err(ArbError::Config(_))));
+
+        let unknown = Account { owner: Pubkey::new_unique(), ..Account::default() };
+        assert!(matches!(registry.resolve(&pool, &unknown), Err(ArbError::Config(_))));
+    }
+}
@@ -0,0 +1,6 @@
+use solana_sdk::pubkey::Pubkey;
+use std::str::FromStr;
+
+pub fn saber_program_id() -> Pubkey {
+    Pubkey::from_str("SSwpkEEcbUqx4vtoEByFjSkhKdCT862DNVb52nZg1UZ").unwrap()
+}
@@ -0,0 +1,367 @@
+use crate::common::{read_pubkey, read_spl_amount, read_u64, snapshot_clock, ClockInfo};
+use crate::dex::{load_pool, PoolLoader, PoolMints, StateSlot};
+use crate::dex::stable_swap::{StableSwap, StableSwapFees};
+use crate::provider::{snapshot_account, AccountProvider, AccountSnapshot};
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::sysvar::clock;
+use log::debug;
+use std::sync::RwLock;
+use crate::error::ArbError;
+
+/*
+Saber SwapInfo (Pack, без discriminator, 395 байт):
+  is_initialized u8, is_paused u8, nonce u8,
+  initial_amp_factor u64, target_amp_factor u64, start_ramp_ts i64, stop_ramp_ts i64,
+  future_admin_deadline i64, future_admin_key, admin_key,
+  token_a reserves, token_b reserves, pool_mint, token_a mint, token_b mint,
+  admin_fee_account_a, admin_fee_account_b, fees (8 x u64).
+*/
+
+const IS_INITIALIZED_OFFSET: usize = 0;
+const IS_PAUSED_OFFSET: usize = 1;
+const INITIAL_AMP_FACTOR_OFFSET: usize = 3;
+const TARGET_AMP_FACTOR_OFFSET: usize = 11;
+const START_RAMP_TS_OFFSET: usize = 19;
+const STOP_RAMP_TS_OFFSET: usize = 27;
+const TOKEN_A_RESERVES_OFFSET: usize = 107;
+const TOKEN_B_RESERVES_OFFSET: usize = 139;
+const POOL_MINT_OFFSET: usize = 171;
+const TOKEN_A_MINT_OFFSET: usize = 203;
+const TOKEN_B_MINT_OFFSET: usize = 235;
+const FEES_OFFSET: usize = 331;
+const SWAP_INFO_LEN: usize = 395;
+
+/// Декодированный SwapInfo пула Saber
+#[derive(Clone, Debug)]
+pub struct SaberSwapInfo {
+    pub is_initialized: bool,
+    pub is_paused: bool,
+    pub initial_amp_factor: u64,
+    pub target_amp_factor: u64,
+    pub start_ramp_ts: i64,
+    pub stop_ramp_ts: i64,
+    pub token_a_reserves: Pubkey,
+    pub token_b_reserves: Pubkey,
+    pub pool_mint: Pubkey,
+    pub token_a_mint: Pubkey,
+    pub token_b_mint: Pubkey,
+    pub fees: StableSwapFees,
+}
+
+impl SaberSwapInfo {
+    pub fn load_checked(data: &[u8]) -> Result<Self, ArbError> {
+        if data.len() < SWAP_INFO_LEN {
+            return Err(ArbError::Decode("Invalid data length for Saber SwapInfo".into()));
+        }
+
+        // fees: admin_trade_fee (num, den), admin_withdraw_fee (num, den), trade_fee (num, den), withdraw_fee (num, den)
+        let fees = StableSwapFees {
+            admin_trade_fee_numerator: read_u64(data, FEES_OFFSET),
+            admin_trade_fee_denominator: read_u64(data, FEES_OFFSET + 8),
+            trade_fee_numerator: read_u64(data, FEES_OFFSET + 32),
+            trade_fee_denominator: read_u64(data, FEES_OFFSET + 40),
+        };
+
+        Ok(Self {
+            is_initialized: data[IS_INITIALIZED_OFFSET] != 0,
+            is_paused: data[IS_PAUSED_OFFSET] != 0,
+            initial_amp_factor: read_u64(data, INITIAL_AMP_FACTOR_OFFSET),
+            target_amp_factor: read_u64(data, TARGET_AMP_FACTOR_OFFSET),
+            start_ramp_ts: read_u64(data, START_RAMP_TS_OFFSET) as i64,
+            stop_ramp_ts: read_u64(data, STOP_RAMP_TS_OFFSET) as i64,
+            token_a_reserves: read_pubkey(data, TOKEN_A_RESERVES_OFFSET),
+            token_b_reserves: read_pubkey(data, TOKEN_B_RESERVES_OFFSET),
+            pool_mint: read_pubkey(data, POOL_MINT_OFFSET),
+            token_a_mint: read_pubkey(data, TOKEN_A_MINT_OFFSET),
+            token_b_mint: read_pubkey(data, TOKEN_B_MINT_OFFSET),
+            fees,
+        })
+    }
+}
+
+/// Изменяемое состояние пула Saber из последнего снапшота
+#[derive(Clone, Debug)]
+pub struct SaberState {
+    /// Пауза, комиссии и параметры рампы A могут меняться администратором
+    pub swap_info: SaberSwapInfo,
+    /// Параметры кривой на момент последнего clock (timestamp нужен для рампы A)
+    pub curve: StableSwap,
+    /// Балансы vault-аккаунтов (a, b)
+    pub reserves: (u64, u64),
+}
+
+/// Stable swap пул Saber, достаточный для off-chain расчётов арбитража.
+#[derive(Debug)]
+pub struct SaberStableSwapPoolInfo {
+    pub pubkey: Pubkey,
+    mint_a: Pubkey,
+    mint_b: Pubkey,
+    pub vault_a: Pubkey,
+    pub vault_b: Pubkey,
+    state: RwLock<SaberState>,
+    /// Слот снапшота, к которому относится состояние
+    slot: StateSlot,
+}
+
+impl PoolMints for SaberStableSwapPoolInfo {
+    fn pool_pubkey(&self) -> &Pubkey {
+        &self.pubkey
+    }
+
+    fn mint_a(&self) -> &Pubkey {
+        &self.mint_a
+    }
+
+    fn mint_b(&self) -> &Pubkey {
+        &self.mint_b
+    }
+
+    fn required_accounts(&self) -> Vec<Pubkey> {
+        vec![self.pubkey, self.vault_a, self.vault_b, clock::id()]
+    }
+
+    fn ingest(&self, accounts: &AccountSnapshot) -> Result<(), ArbError> {
+        let mut state = self.state.write().unwrap();
+        if let Some(acc) = accounts.get(&self.pubkey) {
+            let swap_info = SaberSwapInfo::load_checked(&acc.data)?;
+            state.curve = curve_at(&swap_info, state.curve.current_ts);
+            state.swap_info = swap_info;
+        }
+        if let Some(acc) = accounts.get(&clock::id()) {
+            state.curve.current_ts = ClockInfo::from_account_data(&acc.data)?.unix_timestamp;
+        }
+        if let Some(acc) = accounts.get(&self.vault_a) {
+            state.reserves.0 = read_spl_amount(acc)?;
+        }
+        if let Some(acc) = accounts.get(&self.vault_b) {
+            state.reserves.1 = read_spl_amount(acc)?;
+        }
+        self.slot.update(accounts, &self.required_accounts());
+        Ok(())
+    }
+
+    fn state_slot(&self) -> u64 {
+        self.slot.get()
+    }
+
+    /// Расчёт amount_out по инварианту StableSwap на резервах из снапшота.
+    ///
+    /// Резервы, SwapInfo и clock обновляются через `ingest`, поэтому рампа A идёт по времени снапшота.
+    fn amount_out(
+        &self,
+        amount_in: u64,
+        token_in: &Pubkey,
+    ) -> Result<u64, ArbError> {
+        if amount_in == 0 {
+            return Ok(0);
+        }
+
+        let state = self.state.read().unwrap();
+        let (reserve_a, reserve_b) = state.reserves;
+        self.quote_state(&state, amount_in, token_in, reserve_a, reserve_b)
+    }
+}
+
+impl PoolLoader for SaberStableSwapPoolInfo {
+    fn dependent_accounts(_pool_data: &[u8]) -> Result<Vec<Pubkey>, ArbError> {
+        Ok(Vec::new())
+    }
+
+    fn from_snapshot(pool_pubkey: Pubkey, accounts: &AccountSnapshot) -> Result<Self, ArbError> {
+        let account = snapshot_account(accounts, &pool_pubkey)?;
+        Self::from_account_data(pool_pubkey, &account.data, &snapshot_clock(accounts)?)
+    }
+}
+
+impl SaberStableSwapPoolInfo {
+    /// Создать структуру пула из данных аккаунта SwapInfo.
+    pub fn from_account_data(
+        pool_pubkey: Pubkey,
+        data: &[u8],
+        clock: &ClockInfo,
+    ) -> Result<Self, ArbError> {
+        let swap_info = SaberSwapInfo::load_checked(data)?;
+        if !swap_info.is_initialized {
+            return Err(ArbError::Decode("Saber swap is not initialized".into()));
+        }
+
+        let curve = curve_at(&swap_info, clock.unix_timestamp);
+
+        debug!(
+            "Parsed Saber Pool: \
+             \n\tpool={}, \
+             \n\tmintA={}, \
+             \n\tmintB={}, \
+             \n\tvaultA={}, \
+             \n\tvaultB={}, \
+             \n\tamp={:?}, \
+             \n\tfees={:?}",
+            pool_pubkey,
+            swap_info.token_a_mint,
+            swap_info.token_b_mint,
+            swap_info.token_a_reserves,
+            swap_info.token_b_reserves,
+            curve.compute_amp_factor(),
+            swap_info.fees
+        );
+
+        Ok(Self {
+            pubkey: pool_pubkey,
+            mint_a: swap_info.token_a_mint,
+            mint_b: swap_info.token_b_mint,
+            vault_a: swap_info.token_a_reserves,
+            vault_b: swap_info.token_b_reserves,
+            state: RwLock::new(SaberState { swap_info, curve, reserves: (0, 0) }),
+            slot: StateSlot::default(),
+        })
+    }
+
+    /// Создать структуру пула напрямую из аккаунта пула.
+    pub fn create(pool_pubkey: Pubkey, provider: &dyn AccountProvider) -> Result<Self, ArbError> {
+        load_pool(pool_pubkey, provider)
+    }
+
+    /// Текущее состояние пула из последнего снапшота
+    pub fn state(&self) -> SaberState {
+        self.state.read().unwrap().clone()
+    }
+
+    /// Расчёт выхода при заданных резервах (без обращения к RPC)
+    pub fn quote(
+        &self,
+        amount_in: u64,
+        token_in: &Pubkey,
+        reserve_a: u64,
+        reserve_b: u64,
+    ) -> Result<u64, ArbError> {
+        self.quote_state(&self.state.read().unwrap(), amount_in, token_in, reserve_a, reserve_b)
+    }
+
+    fn quote_state(
+        &self,
+        state: &SaberState,
+        amount_in: u64,
+        token_in: &Pubkey,
+        reserve_a: u64,
+        reserve_b: u64,
+    ) -> Result<u64, ArbError> {
+        if state.swap_info.is_paused {
+            return Err(ArbError::PoolDisabled("Saber swap is paused".into()));
+        }
+
+        let (reserve_in, reserve_out) = if *token_in == *self.mint_a() {
+            (reserve_a, reserve_b)
+        } else if *token_in == *self.mint_b() {
+            (reserve_b, reserve_a)
+        } else {
+            return Err(ArbError::UnsupportedMint("Token in is not mint_a or mint_b".into()));
+        };
+
+        let result = state
+            .curve
+            .swap_to(amount_in, reserve_in, reserve_out, &state.swap_info.fees)
+            .ok_or_else(|| ArbError::MathOverflow("Stable swap math overflow".into()))?;
+
+        Ok(result.amount_swapped)
+    }
+}
+
+// Кривая с параметрами рампы A из SwapInfo на момент `current_ts`
+fn curve_at(swap_info: &SaberSwapInfo, current_ts: i64) -> StableSwap {
+    StableSwap::new(
+        swap_info.initial_amp_factor,
+        swap_info.target_amp_factor,
+        current_ts,
+        swap_info.start_ramp_ts,
+        swap_info.stop_ramp_ts,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::write_u64;
+
+    fn test_swap_data(is_paused: bool) -> Vec<u8> {
+        let mut data = vec![0u8; SWAP_INFO_LEN];
+        data[IS_INITIALIZED_OFFSET] = 1;
+        data[IS_PAUSED_OFFSET] = is_paused as u8;
+        write_u64(&mut data, INITIAL_AMP_FACTOR_OFFSET, 100);
+        write_u64(&mut data, TARGET_AMP_FACTOR_OFFSET, 100);
+        for offset in [TOKEN_A_RESERVES_OFFSET, TOKEN_B_RESERVES_OFFSET, POOL_MINT_OFFSET, TOKEN_A_MINT_OFFSET, TOKEN_B_MINT_OFFSET] {
+            data[offset..offset + 32].copy_from_slice(Pubkey::new_unique().as_ref());
+        }
+        // trade fee 0.04%, admin fee 50% от trade fee
+        write_u64(&mut data, FEES_OFFSET, 1);
+        write_u64(&mut data, FEES_OFFSET + 8, 2);
+        write_u64(&mut data, FEES_OFFSET + 32, 4);
+        write_u64(&mut data, FEES_OFFSET + 40, 10_000);
+        data
+    }
+
+    #[test]
+    fn test_saber_swap_info_decoding_and_quote() {
+        let pool = SaberStableSwapPoolInfo::from_account_data(
+            Pubkey::new_unique(),
+            &test_swap_data(false),
+            &ClockInfo::default(),
+        )
+        .expect("Failed to decode Saber swap");
+
+        let state = pool.state();
+        assert_eq!(state.swap_info.fees.trade_fee_numerator, 4);
+        assert_eq!(state.swap_info.fees.admin_trade_fee_denominator, 2);
+        assert_eq!(state.curve.compute_amp_factor(), Some(100));
+
+        let reserve = 50_000_000_000_000u64;
+        let a_to_b = pool.quote(1_000_000, pool.mint_a(), reserve, reserve).unwrap();
+        let b_to_a = pool.quote(1_000_000, pool.mint_b(), reserve, reserve).unwrap();
+        assert_eq!(a_to_b, b_to_a);
+        assert!((999_500..=999_600).contains(&a_to_b), "{}", a_to_b);
+
+        assert!(pool.quote(1_000_000, &Pubkey::new_unique(), reserve, reserve).is_err());
+    }
+
+    #[test]
+    fn test_saber_paused_pool_rejects_swaps() {
+        let pool = SaberStableSwapPoolInfo::from_account_data(
+            Pubkey::new_unique(),
+            &test_swap_data(true),
+            &ClockInfo::default(),
+        )
+        .unwrap();
+        assert!(pool.quote(1_000, pool.mint_a(), 1_000_000, 1_000_000).is_err());
+    }
+
+    #[test]
+    fn test_saber_ingest_advances_amp_ramp_and_rereads_swap_info() {
+        use solana_sdk::account::Account;
+
+        // Рампа A со 100 до 200 за время 0..1000
+        let mut data = test_swap_data(false);
+        write_u64(&mut data, TARGET_AMP_FACTOR_OFFSET, 200);
+        write_u64(&mut data, STOP_RAMP_TS_OFFSET, 1_000);
+        let pool_pubkey = Pubkey::new_unique();
+        let pool = SaberStableSwapPoolInfo::from_account_data(pool_pubkey, &data, &ClockInfo::default()).unwrap();
+        assert!(pool.required_accounts().contains(&pool_pubkey));
+        assert!(pool.required_accounts().contains(&clock::id()));
+        assert_eq!(pool.state().curve.compute_amp_factor(), Some(100));
+
+        // Новый clock двигает рампу без перезагрузки пула
+        let mut clock_data = vec![0u8; 40];
+        write_u64(&mut clock_data, 32, 500);
+        let mut snapshot = AccountSnapshot::new();
+        snapshot.insert(clock::id(), Account { data: clock_data, ..Account::default() }, 10);
+        pool.ingest(&snapshot).unwrap();
+        assert_eq!(pool.state().curve.compute_amp_factor(), Some(150));
+
+        // Пауза администратором видна после следующего снапшота SwapInfo
+        data[IS_PAUSED_OFFSET] = 1;
+        let mut snapshot = AccountSnapshot::new();
+        snapshot.insert(pool_pubkey, Account { data, ..Account::default() }, 11);
+        pool.ingest(&snapshot).unwrap();
+        assert!(pool.state().swap_info.is_paused);
+        assert_eq!(pool.state().curve.compute_amp_factor(), Some(150));
+        assert!(pool.quote(1_000, pool.mint_a(), 1_000_000, 1_000_000).is_err());
+    }
+}
@@ -0,0 +1,292 @@
+use spl_math::uint::U256;
+
+/*
+Инвариант StableSwap (Curve) для двух токенов в целочисленной арифметике:
+  A * n^n * sum(x_i) + D = A * D * n^n + D^(n+1) / (n^n * prod(x_i))
+D и y находятся методом Ньютона, все промежуточные значения считаются в U256.
+Реализация совпадает с on-chain математикой Saber (stable-swap-math): leverage = A * n,
+коэффициент усиления A линейно меняется между start_ramp_ts и stop_ramp_ts.
+*/
+
+/// Количество токенов в пуле
+pub const N_COINS: u8 = 2;
+/// Максимальный коэффициент усиления
+pub const MAX_AMP: u64 = 1_000_000;
+/// Максимальное число итераций метода Ньютона
+const MAX_ITERATIONS: usize = 256;
+
+/// Комиссии stable swap пула: trade fee снимается с выхода, admin fee — доля от trade fee
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct StableSwapFees {
+    pub admin_trade_fee_numerator: u64,
+    pub admin_trade_fee_denominator: u64,
+    pub trade_fee_numerator: u64,
+    pub trade_fee_denominator: u64,
+}
+
+impl StableSwapFees {
+    /// Комиссия за своп (округление вниз)
+    pub fn trade_fee(&self, amount: u64) -> Option<u64> {
+        mul_div(amount, self.trade_fee_numerator, self.trade_fee_denominator)
+    }
+
+    /// Доля комиссии, уходящая администратору (округление вниз)
+    pub fn admin_trade_fee(&self, fee_amount: u64) -> Option<u64> {
+        mul_div(fee_amount, self.admin_trade_fee_numerator, self.admin_trade_fee_denominator)
+    }
+}
+
+fn mul_div(amount: u64, numerator: u64, denominator: u64) -> Option<u64> {
+    if denominator == 0 || numerator == 0 {
+        return Some(0);
+    }
+    let result = amount as u128 * numerator as u128 / denominator as u128;
+    u64::try_from(result).ok()
+}
+
+/// Результат свопа по кривой
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SwapResult {
+    /// Новый резерв входящего токена
+    pub new_source_amount: u64,
+    /// Новый резерв выходящего токена (с учётом оставшейся в пуле комиссии)
+    pub new_destination_amount: u64,
+    /// Количество токенов, которое получит пользователь
+    pub amount_swapped: u64,
+    /// Доля комиссии администратора
+    pub admin_fee: u64,
+    /// Полная комиссия
+    pub fee: u64,
+}
+
+/// Параметры кривой StableSwap с рампой коэффициента усиления
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct StableSwap {
+    pub initial_amp_factor: u64,
+    pub target_amp_factor: u64,
+    pub current_ts: i64,
+    pub start_ramp_ts: i64,
+    pub stop_ramp_ts: i64,
+}
+
+impl StableSwap {
+    pub fn new(
+        initial_amp_factor: u64,
+        target_amp_factor: u64,
+        current_ts: i64,
+        start_ramp_ts: i64,
+        stop_ramp_ts: i64,
+    ) -> Self {
+        Self {
+            initial_amp_factor,
+            target_amp_factor,
+            current_ts,
+            start_ramp_ts,
+            stop_ramp_ts,
+        }
+    }
+
+    /// Текущий коэффициент усиления A с учётом линейной рампы
+    pub fn compute_amp_factor(&self) -> Option<u64> {
+        if self.current_ts < self.stop_ramp_ts {
+            let time_range = self.stop_ramp_ts.checked_sub(self.start_ramp_ts)? as u64;
+            let time_delta = self.current_ts.checked_sub(self.start_ramp_ts)?.max(0) as u64;
+            if time_range == 0 {
+                return Some(self.target_amp_factor);
+            }
+
+            if self.target_amp_factor >= self.initial_amp_factor {
+                let amp_range = self.target_amp_factor - self.initial_amp_factor;
+                let amp_delta = (amp_range as u128 * time_delta as u128 / time_range as u128) as u64;
+                self.initial_amp_factor.checked_add(amp_delta)
+            } else {
+                let amp_range = self.initial_amp_factor - self.target_amp_factor;
+                let amp_delta = (amp_range as u128 * time_delta as u128 / time_range as u128) as u64;
+                self.initial_amp_factor.checked_sub(amp_delta)
+            }
+        } else {
+            Some(self.target_amp_factor)
+        }
+    }
+
+    /// Одна итерация Ньютона для D
+    fn compute_next_d(amp_factor: u64, d_init: U256, d_prod: U256, sum_x: u64) -> Option<U256> {
+        let n_coins = U256::from(N_COINS);
+        let leverage = U256::from(amp_factor).checked_mul(n_coins)?;
+        // d = (leverage * sum_x + d_p * n) * d / ((leverage - 1) * d + (n + 1) * d_p)
+        let numerator = d_init.checked_mul(
+            d_prod
+                .checked_mul(n_coins)?
+                .checked_add(leverage.checked_mul(sum_x.into())?)?,
+        )?;
+        let denominator = d_init
+            .checked_mul(leverage.checked_sub(U256::one())?)?
+            .checked_add(d_prod.checked_mul(n_coins.checked_add(U256::one())?)?)?;
+        numerator.checked_div(denominator)
+    }
+
+    /// Инвариант D для резервов amount_a и amount_b
+    pub fn compute_d(&self, amount_a: u64, amount_b: u64) -> Option<U256> {
+        let sum_x = amount_a.checked_add(amount_b)?;
+        if sum_x == 0 {
+            return Some(U256::zero());
+        }
+        if amount_a == 0 || amount_b == 0 {
+            return None;
+        }
+
+        let amp_factor = self.compute_amp_factor()?;
+        let amount_a_times_coins = U256::from(amount_a).checked_mul(N_COINS.into())?;
+        let amount_b_times_coins = U256::from(amount_b).checked_mul(N_COINS.into())?;
+
+        let mut d = U256::from(sum_x);
+        for _ in 0..MAX_ITERATIONS {
+            let mut d_prod = d;
+            d_prod = d_prod.checked_mul(d)?.checked_div(amount_a_times_coins)?;
+            d_prod = d_prod.checked_mul(d)?.checked_div(amount_b_times_coins)?;
+            let d_prev = d;
+            d = Self::compute_next_d(amp_factor, d, d_prod, sum_x)?;
+            if abs_diff(d, d_prev) <= U256::one() {
+                break;
+            }
+        }
+
+        Some(d)
+    }
+
+    /// Новый резерв выходящего токена y при резерве входящего x и инварианте d
+    pub fn compute_y(&self, x: u64, d: U256) -> Option<u64> {
+        if x == 0 {
+            return None;
+        }
+
+        let amp_factor = self.compute_amp_factor()?;
+        let n_coins = U256::from(N_COINS);
+        let leverage = U256::from(amp_factor).checked_mul(n_coins)?;
+
+        // c = D^(n+1) / (n^n * x * leverage)
+        let mut c = d.checked_mul(d)?.checked_div(U256::from(x).checked_mul(n_coins)?)?;
+        c = c.checked_mul(d)?.checked_div(leverage.checked_mul(n_coins)?)?;
+
+        // b = x + D / leverage
+        let b = d.checked_div(leverage)?.checked_add(x.into())?;
+
+        // y^2 + (b - D) * y = c
+        let mut y = d;
+        for _ in 0..MAX_ITERATIONS {
+            let y_prev = y;
+            y = y
+                .checked_mul(y)?
+                .checked_add(c)?
+                .checked_div(y.checked_mul(n_coins)?.checked_add(b)?.checked_sub(d)?)?;
+            if abs_diff(y, y_prev) <= U256::one() {
+                break;
+            }
+        }
+
+        if y > U256::from(u64::MAX) {
+            return None;
+        }
+        Some(y.as_u64())
+    }
+
+    /// Своп source_amount входящего токена при текущих резервах
+    pub fn swap_to(
+        &self,
+        source_amount: u64,
+        swap_source_amount: u64,
+        swap_destination_amount: u64,
+        fees: &StableSwapFees,
+    ) -> Option<SwapResult> {
+        let d = self.compute_d(swap_source_amount, swap_destination_amount)?;
+        let new_source_amount = swap_source_amount.checked_add(source_amount)?;
+        let y = self.compute_y(new_source_amount, d)?;
+
+        let dy = swap_destination_amount.checked_sub(y)?;
+        let fee = fees.trade_fee(dy)?;
+        let admin_fee = fees.admin_trade_fee(fee)?;
+
+        let amount_swapped = dy.checked_sub(fee)?;
+        let new_destination_amount = swap_destination_amount
+            .checked_sub(amount_swapped)?
+            .checked_sub(admin_fee)?;
+
+        Some(SwapResult {
+            new_source_amount,
+            new_destination_amount,
+            amount_swapped,
+            admin_fee,
+            fee,
+        })
+    }
+}
+
+fn abs_diff(a: U256, b: U256) -> U256 {
+    if a > b { a - b } else { b - a }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FEES: StableSwapFees = StableSwapFees {
+        admin_trade_fee_numerator: 0,
+        admin_trade_fee_denominator: 1,
+        trade_fee_numerator: 4,
+        trade_fee_denominator: 10_000,
+    };
+
+    #[test]
+    fn test_amp_ramp() {
+        let mut swap = StableSwap::new(100, 200, 1_000, 1_000, 2_000);
+        assert_eq!(swap.compute_amp_factor(), Some(100));
+        swap.current_ts = 1_500;
+        assert_eq!(swap.compute_amp_factor(), Some(150));
+        swap.current_ts = 5_000;
+        assert_eq!(swap.compute_amp_factor(), Some(200));
+
+        let swap = StableSwap::new(200, 100, 1_250, 1_000, 2_000);
+        assert_eq!(swap.compute_amp_factor(), Some(175));
+    }
+
+    #[test]
+    fn test_invariant_balanced_pool() {
+        let swap = StableSwap::new(100, 100, 0, 0, 0);
+        // Для сбалансированного пула D = сумма резервов
+        let d = swap.compute_d(1_000_000_000, 1_000_000_000).unwrap();
+        assert!(abs_diff(d, U256::from(2_000_000_000u64)) <= U256::one());
+
+        // y(x, D) восстанавливает второй резерв
+        let y = swap.compute_y(1_000_000_000, d).unwrap();
+        assert!(y.abs_diff(1_000_000_000) <= 1);
+    }
+
+    #[test]
+    fn test_swap_close_to_peg_and_convex() {
+        let reserve = 10_000_000_000_000u64;
+        let high_amp = StableSwap::new(1_000, 1_000, 0, 0, 0);
+        let low_amp = StableSwap::new(1, 1, 0, 0, 0);
+
+        let amount_in = 1_000_000_000u64;
+        let high = high_amp.swap_to(amount_in, reserve, reserve, &FEES).unwrap();
+        let low = low_amp.swap_to(amount_in, reserve, reserve, &FEES).unwrap();
+
+        // Около пега курс ~1:1 минус 0.04% комиссии
+        let expected = amount_in - amount_in * 4 / 10_000;
+        assert!(high.amount_swapped <= expected && high.amount_swapped + 1_000 >= expected, "{:?}", high);
+        // Чем меньше A, тем ближе к x*y=k и тем больше проскальзывание
+        assert!(low.amount_swapped < high.amount_swapped);
+        assert_eq!(high.new_destination_amount, reserve - high.amount_swapped - high.admin_fee);
+    }
+
+    #[test]
+    fn test_imbalanced_pool_gives_premium() {
+        let swap = StableSwap::new(100, 100, 0, 0, 0);
+        let fees = StableSwapFees::default();
+        // Пул перекошен в сторону токена A: обмен A -> B хуже 1:1, обмен B -> A лучше 1:1
+        let a_to_b = swap.swap_to(1_000_000, 3_000_000_000, 1_000_000_000, &fees).unwrap();
+        let b_to_a = swap.swap_to(1_000_000, 1_000_000_000, 3_000_000_000, &fees).unwrap();
+        assert!(a_to_b.amount_swapped < 1_000_000);
+        assert!(b_to_a.amount_swapped > 1_000_000);
+    }
+}
@@ -0,0 +1,14 @@
+use solana_sdk::pubkey::Pubkey;
+use std::str::FromStr;
+
+pub fn stake_pool_program_id() -> Pubkey {
+    Pubkey::from_str("SPoo1Ku8WFXoNDMHPsrGSTSG1Y47rzgn41SLUNakuHy").unwrap()
+}
+
+/// WSOL — mint, которым в графе арбитража представлен SOL
+pub fn native_mint() -> Pubkey {
+    Pubkey::from_str("So11111111111111111111111111111111111111112").unwrap()
+}
+
+/// AccountType::StakePool
+pub const ACCOUNT_TYPE_STAKE_POOL: u8 = 1;
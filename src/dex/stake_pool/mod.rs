@@ -0,0 +1,2 @@
+pub mod constants;
+pub mod pool;
@@ -0,0 +1,460 @@
+use crate::common::{snapshot_clock, read_pubkey, read_u64, ClockInfo};
+use crate::dex::{load_pool, PoolLoader, PoolMints, StateSlot};
+use crate::dex::stake_pool::constants::{native_mint, ACCOUNT_TYPE_STAKE_POOL};
+use crate::provider::{snapshot_account, AccountProvider, AccountSnapshot};
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::sysvar::clock;
+use log::debug;
+use std::sync::RwLock;
+use crate::error::ArbError;
+
+/*
+SPL Stake Pool хранит состояние в borsh, поэтому часть полей (Option<Pubkey>, FutureEpoch<Fee>)
+имеет переменную длину и читается последовательно.
+Курс LST: total_lamports / pool_token_supply, обновляется раз в эпоху (UpdateStakePoolBalance).
+Депозит SOL (DepositSol) минтит pool_tokens = lamports * pool_token_supply / total_lamports,
+из которых удерживается sol_deposit_fee. Обратное направление (WithdrawSol) ограничено
+ликвидностью reserve stake и здесь не котируется.
+*/
+
+/// Комиссия stake pool: numerator / denominator
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Fee {
+    pub denominator: u64,
+    pub numerator: u64,
+}
+
+impl Fee {
+    /// Комиссия с округлением вверх (как в Fee::apply программы)
+    pub fn apply(&self, amount: u64) -> u64 {
+        if self.denominator == 0 {
+            return 0;
+        }
+        let numerator = amount as u128 * self.numerator as u128;
+        numerator.div_ceil(self.denominator as u128) as u64
+    }
+}
+
+/// Последовательное чтение borsh-полей с проверкой границ
+struct BorshReader<'a> {
+    data: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> BorshReader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self { data, offset: 0 }
+    }
+
+    fn ensure(&self, len: usize) -> Result<(), ArbError> {
+        if self.offset + len > self.data.len() {
+            return Err(ArbError::Decode("Invalid data length for StakePool".into()));
+        }
+        Ok(())
+    }
+
+    fn u8(&mut self) -> Result<u8, ArbError> {
+        self.ensure(1)?;
+        let value = self.data[self.offset];
+        self.offset += 1;
+        Ok(value)
+    }
+
+    fn u64(&mut self) -> Result<u64, ArbError> {
+        self.ensure(8)?;
+        let value = read_u64(self.data, self.offset);
+        self.offset += 8;
+        Ok(value)
+    }
+
+    fn pubkey(&mut self) -> Result<Pubkey, ArbError> {
+        self.ensure(32)?;
+        let value = read_pubkey(self.data, self.offset);
+        self.offset += 32;
+        Ok(value)
+    }
+
+    fn skip(&mut self, len: usize) -> Result<(), ArbError> {
+        self.ensure(len)?;
+        self.offset += len;
+        Ok(())
+    }
+
+    fn option_pubkey(&mut self) -> Result<Option<Pubkey>, ArbError> {
+        match self.u8()? {
+            0 => Ok(None),
+            1 => Ok(Some(self.pubkey()?)),
+            _ => Err(ArbError::Decode("Invalid Option tag in StakePool".into())),
+        }
+    }
+
+    fn fee(&mut self) -> Result<Fee, ArbError> {
+        Ok(Fee {
+            denominator: self.u64()?,
+            numerator: self.u64()?,
+        })
+    }
+
+    /// FutureEpoch<Fee>: 0 — None, 1 — One(Fee), 2 — Two(Fee)
+    fn future_epoch_fee(&mut self) -> Result<Option<Fee>, ArbError> {
+        match self.u8()? {
+            0 => Ok(None),
+            1 | 2 => Ok(Some(self.fee()?)),
+            _ => Err(ArbError::Decode("Invalid FutureEpoch tag in StakePool".into())),
+        }
+    }
+}
+
+/// Декодированный аккаунт StakePool (только поля, влияющие на курс и комиссии)
+#[derive(Clone, Debug)]
+pub struct StakePoolState {
+    pub reserve_stake: Pubkey,
+    pub pool_mint: Pubkey,
+    pub total_lamports: u64,
+    pub pool_token_supply: u64,
+    pub last_update_epoch: u64,
+    pub epoch_fee: Fee,
+    pub stake_deposit_fee: Fee,
+    pub stake_withdrawal_fee: Fee,
+    pub sol_deposit_authority: Option<Pubkey>,
+    pub sol_deposit_fee: Fee,
+    pub sol_referral_fee: u8,
+    pub sol_withdraw_authority: Option<Pubkey>,
+    pub sol_withdrawal_fee: Fee,
+}
+
+impl StakePoolState {
+    pub fn load_checked(data: &[u8]) -> Result<Self, ArbError> {
+        let mut reader = BorshReader::new(data);
+        if reader.u8()? != ACCOUNT_TYPE_STAKE_POOL {
+            return Err(ArbError::Decode("Account is not an SPL StakePool".into()));
+        }
+
+        // manager, staker, stake_deposit_authority
+        reader.skip(32 * 3)?;
+        // stake_withdraw_bump_seed
+        reader.skip(1)?;
+        // validator_list
+        reader.skip(32)?;
+        let reserve_stake = reader.pubkey()?;
+        let pool_mint = reader.pubkey()?;
+        // manager_fee_account, token_program_id
+        reader.skip(32 * 2)?;
+        let total_lamports = reader.u64()?;
+        let pool_token_supply = reader.u64()?;
+        let last_update_epoch = reader.u64()?;
+        // lockup: unix_timestamp, epoch, custodian
+        reader.skip(8 + 8 + 32)?;
+        let epoch_fee = reader.fee()?;
+        let _next_epoch_fee = reader.future_epoch_fee()?;
+        let _preferred_deposit_validator = reader.option_pubkey()?;
+        let _preferred_withdraw_validator = reader.option_pubkey()?;
+        let stake_deposit_fee = reader.fee()?;
+        let stake_withdrawal_fee = reader.fee()?;
+        let _next_stake_withdrawal_fee = reader.future_epoch_fee()?;
+        let _stake_referral_fee = reader.u8()?;
+        let sol_deposit_authority = reader.option_pubkey()?;
+        let sol_deposit_fee = reader.fee()?;
+        let sol_referral_fee = reader.u8()?;
+        let sol_withdraw_authority = reader.option_pubkey()?;
+        let sol_withdrawal_fee = reader.fee()?;
+
+        Ok(Self {
+            reserve_stake,
+            pool_mint,
+            total_lamports,
+            pool_token_supply,
+            last_update_epoch,
+            epoch_fee,
+            stake_deposit_fee,
+            stake_withdrawal_fee,
+            sol_deposit_authority,
+            sol_deposit_fee,
+            sol_referral_fee,
+            sol_withdraw_authority,
+            sol_withdrawal_fee,
+        })
+    }
+
+    /// Количество pool tokens, которое будет сминчено за lamports (до удержания комиссии)
+    pub fn calc_pool_tokens_for_deposit(&self, lamports: u64) -> Option<u64> {
+        if self.total_lamports == 0 || self.pool_token_supply == 0 {
+            return Some(lamports);
+        }
+        let pool_tokens = lamports as u128 * self.pool_token_supply as u128 / self.total_lamports as u128;
+        u64::try_from(pool_tokens).ok()
+    }
+
+    /// Количество lamports за сжигаемые pool tokens (до удержания комиссии)
+    pub fn calc_lamports_withdraw_amount(&self, pool_tokens: u64) -> Option<u64> {
+        if self.pool_token_supply == 0 {
+            return None;
+        }
+        let lamports = pool_tokens as u128 * self.total_lamports as u128 / self.pool_token_supply as u128;
+        u64::try_from(lamports).ok()
+    }
+}
+
+/// Состояние stake pool и эпоха из последнего снапшота
+#[derive(Clone, Debug)]
+pub struct StakePoolSnapshot {
+    pub state: StakePoolState,
+    pub current_epoch: u64,
+}
+
+/// LST stake pool как venue SOL -> LST для графа арбитража.
+#[derive(Debug)]
+pub struct StakePoolInfo {
+    pub pubkey: Pubkey,
+    mint_a: Pubkey,
+    mint_b: Pubkey,
+    snapshot: RwLock<StakePoolSnapshot>,
+    /// Слот снапшота, к которому относится состояние
+    slot: StateSlot,
+}
+
+impl PoolMints for StakePoolInfo {
+    fn pool_pubkey(&self) -> &Pubkey {
+        &self.pubkey
+    }
+
+    /// WSOL
+    fn mint_a(&self) -> &Pubkey {
+        &self.mint_a
+    }
+
+    /// LST (pool_mint)
+    fn mint_b(&self) -> &Pubkey {
+        &self.mint_b
+    }
+
+    fn required_accounts(&self) -> Vec<Pubkey> {
+        vec![self.pubkey, clock::id()]
+    }
+
+    fn ingest(&self, accounts: &AccountSnapshot) -> Result<(), ArbError> {
+        let mut snapshot = self.snapshot.write().unwrap();
+        if let Some(acc) = accounts.get(&self.pubkey) {
+            snapshot.state = StakePoolState::load_checked(&acc.data)?;
+        }
+        if let Some(acc) = accounts.get(&clock::id()) {
+            snapshot.current_epoch = ClockInfo::from_account_data(&acc.data)?.epoch;
+        }
+        self.slot.update(accounts, &self.required_accounts());
+        Ok(())
+    }
+
+    fn state_slot(&self) -> u64 {
+        self.slot.get()
+    }
+
+    /// Расчёт количества LST за депозит SOL (DepositSol) с учётом sol_deposit_fee.
+    ///
+    /// Направление LST -> SOL не поддерживается: мгновенный вывод ограничен reserve stake.
+    fn amount_out(
+        &self,
+        amount_in: u64,
+        token_in: &Pubkey,
+    ) -> Result<u64, ArbError> {
+        if *token_in == *self.mint_b() {
+            return Err(ArbError::UnsupportedMint("Stake pool LST -> SOL direction is unsupported".into()));
+        }
+        if *token_in != *self.mint_a() {
+            return Err(ArbError::UnsupportedMint("Token in is not mint_a or mint_b".into()));
+        }
+
+        if amount_in == 0 {
+            return Ok(0);
+        }
+
+        self.deposit_sol(amount_in)
+    }
+}
+
+impl PoolLoader for StakePoolInfo {
+    fn dependent_accounts(_pool_data: &[u8]) -> Result<Vec<Pubkey>, ArbError> {
+        Ok(Vec::new())
+    }
+
+    fn from_snapshot(pool_pubkey: Pubkey, accounts: &AccountSnapshot) -> Result<Self, ArbError> {
+        let account = snapshot_account(accounts, &pool_pubkey)?;
+        Self::from_account_data(pool_pubkey, &account.data, &snapshot_clock(accounts)?)
+    }
+}
+
+impl StakePoolInfo {
+    /// Создать структуру пула из данных аккаунта StakePool.
+    pub fn from_account_data(
+        pool_pubkey: Pubkey,
+        data: &[u8],
+        clock: &ClockInfo,
+    ) -> Result<Self, ArbError> {
+        let state = StakePoolState::load_checked(data)?;
+
+        debug!(
+            "Parsed Stake Pool: \
+             \n\tpool={}, \
+             \n\tpool_mint={}, \
+             \n\ttotal_lamports={}, \
+             \n\tpool_token_supply={}, \
+             \n\tlast_update_epoch={}, \
+             \n\tsol_deposit_fee={:?}, \
+             \n\tsol_withdrawal_fee={:?}",
+            pool_pubkey,
+            state.pool_mint,
+            state.total_lamports,
+            state.pool_token_supply,
+            state.last_update_epoch,
+            state.sol_deposit_fee,
+            state.sol_withdrawal_fee
+        );
+
+        Ok(Self {
+            pubkey: pool_pubkey,
+            mint_a: native_mint(),
+            mint_b: state.pool_mint,
+            snapshot: RwLock::new(StakePoolSnapshot { state, current_epoch: clock.epoch }),
+            slot: StateSlot::default(),
+        })
+    }
+
+    /// Текущее состояние пула из последнего снапшота
+    pub fn snapshot(&self) -> StakePoolSnapshot {
+        self.snapshot.read().unwrap().clone()
+    }
+
+    /// Создать структуру пула напрямую из аккаунта пула.
+    pub fn create(pool_pubkey: Pubkey, provider: &dyn AccountProvider) -> Result<Self, ArbError> {
+        load_pool(pool_pubkey, provider)
+    }
+
+    /// Программа отклоняет депозиты, пока пул не обновлён в текущей эпохе
+    fn check_updated(snapshot: &StakePoolSnapshot) -> Result<(), ArbError> {
+        if snapshot.state.last_update_epoch < snapshot.current_epoch {
+            return Err(ArbError::PoolDisabled("Stake pool is not updated for current epoch".into()));
+        }
+        Ok(())
+    }
+
+    /// DepositSol: LST за lamports за вычетом sol_deposit_fee
+    pub fn deposit_sol(&self, lamports: u64) -> Result<u64, ArbError> {
+        let snapshot = self.snapshot.read().unwrap();
+        if snapshot.state.sol_deposit_authority.is_some() {
+            return Err(ArbError::PoolDisabled("Stake pool SOL deposits are permissioned".into()));
+        }
+        Self::check_updated(&snapshot)?;
+
+        let new_pool_tokens = snapshot
+            .state
+            .calc_pool_tokens_for_deposit(lamports)
+            .ok_or_else(|| ArbError::MathOverflow("Stake pool math overflow".into()))?;
+        let fee = snapshot.state.sol_deposit_fee.apply(new_pool_tokens);
+
+        Ok(new_pool_tokens.saturating_sub(fee))
+    }
+
+    /// WithdrawSol: lamports за pool tokens за вычетом sol_withdrawal_fee.
+    /// Не учитывает ограничение ликвидностью reserve stake.
+    pub fn withdraw_sol(&self, pool_tokens: u64) -> Result<u64, ArbError> {
+        let snapshot = self.snapshot.read().unwrap();
+        if snapshot.state.sol_withdraw_authority.is_some() {
+            return Err(ArbError::PoolDisabled("Stake pool SOL withdrawals are permissioned".into()));
+        }
+        Self::check_updated(&snapshot)?;
+
+        let fee = snapshot.state.sol_withdrawal_fee.apply(pool_tokens);
+        let pool_tokens_burnt = pool_tokens.saturating_sub(fee);
+        snapshot.state
+            .calc_lamports_withdraw_amount(pool_tokens_burnt)
+            .ok_or_else(|| ArbError::MathOverflow("Stake pool math overflow".into()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Сериализация StakePool в borsh с заданными параметрами курса и комиссий
+    fn stake_pool_data(pool_mint: &Pubkey, sol_deposit_authority: Option<Pubkey>) -> Vec<u8> {
+        let mut data = vec![ACCOUNT_TYPE_STAKE_POOL];
+        data.extend_from_slice(&[0u8; 32 * 3 + 1 + 32 + 32]);
+        data.extend_from_slice(pool_mint.as_ref());
+        data.extend_from_slice(&[0u8; 32 * 2]);
+        data.extend_from_slice(&1_100_000_000_000u64.to_le_bytes()); // total_lamports
+        data.extend_from_slice(&1_000_000_000_000u64.to_le_bytes()); // pool_token_supply
+        data.extend_from_slice(&500u64.to_le_bytes()); // last_update_epoch
+        data.extend_from_slice(&[0u8; 8 + 8 + 32]); // lockup
+        let fee = |data: &mut Vec<u8>, numerator: u64, denominator: u64| {
+            data.extend_from_slice(&denominator.to_le_bytes());
+            data.extend_from_slice(&numerator.to_le_bytes());
+        };
+        fee(&mut data, 5, 100); // epoch_fee
+        data.push(1); // next_epoch_fee = One
+        fee(&mut data, 6, 100);
+        data.push(1); // preferred_deposit_validator = Some
+        data.extend_from_slice(Pubkey::new_unique().as_ref());
+        data.push(0); // preferred_withdraw_validator = None
+        fee(&mut data, 0, 0); // stake_deposit_fee
+        fee(&mut data, 1, 1_000); // stake_withdrawal_fee
+        data.push(0); // next_stake_withdrawal_fee = None
+        data.push(0); // stake_referral_fee
+        match sol_deposit_authority {
+            Some(authority) => {
+                data.push(1);
+                data.extend_from_slice(authority.as_ref());
+            }
+            None => data.push(0),
+        }
+        fee(&mut data, 1, 1_000); // sol_deposit_fee 0.1%
+        data.push(50); // sol_referral_fee
+        data.push(0); // sol_withdraw_authority
+        fee(&mut data, 3, 1_000); // sol_withdrawal_fee 0.3%
+        data.push(0); // next_sol_withdrawal_fee
+        data.extend_from_slice(&[0u8; 16]); // last_epoch_pool_token_supply, last_epoch_total_lamports
+        data
+    }
+
+    #[test]
+    fn test_stake_pool_decoding_and_deposit_quote() {
+        let pool_mint = Pubkey::new_unique();
+        let clock = ClockInfo { epoch: 500, ..ClockInfo::default() };
+        let pool = StakePoolInfo::from_account_data(Pubkey::new_unique(), &stake_pool_data(&pool_mint, None), &clock)
+            .expect("Failed to decode stake pool");
+
+        let state = pool.snapshot().state;
+        assert_eq!(pool.mint_b(), &pool_mint);
+        assert_eq!(state.sol_deposit_fee, Fee { denominator: 1_000, numerator: 1 });
+        assert_eq!(state.sol_withdrawal_fee, Fee { denominator: 1_000, numerator: 3 });
+        assert_eq!(state.sol_referral_fee, 50);
+
+        // 1.1 SOL -> 1.0 LST, минус 0.1%
+        let out = pool.amount_out(1_100_000_000, pool.mint_a()).unwrap();
+        assert_eq!(out, 1_000_000_000 - 1_000_000);
+
+        assert!(pool.amount_out(1_000_000_000, pool.mint_b()).is_err());
+
+        // 1.0 LST -> 1.1 SOL за вычетом 0.3% в pool tokens
+        assert_eq!(pool.withdraw_sol(1_000_000_000).unwrap(), 1_096_700_000);
+    }
+
+    #[test]
+    fn test_stake_pool_rejects_stale_or_permissioned_deposits() {
+        let pool_mint = Pubkey::new_unique();
+
+        let stale = StakePoolInfo::from_account_data(
+            Pubkey::new_unique(),
+            &stake_pool_data(&pool_mint, None),
+            &ClockInfo { epoch: 501, ..ClockInfo::default() },
+        )
+        .unwrap();
+        assert!(stale.amount_out(1_000_000_000, stale.mint_a()).is_err());
+
+        let permissioned = StakePoolInfo::from_account_data(
+            Pubkey::new_unique(),
+            &stake_pool_data(&pool_mint, Some(Pubkey::new_unique())),
+            &ClockInfo { epoch: 500, ..ClockInfo::default() },
+        )
+        .unwrap();
+        assert!(permissioned.amount_out(1_000_000_000, permissioned.mint_a()).is_err());
+    }
+}
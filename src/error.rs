@@ -0,0 +1,129 @@
+use std::fmt;
+
+use solana_client::client_error::ClientError;
+use solana_client::nonblocking::pubsub_client::PubsubClientError;
+use solana_sdk::pubkey::{ParsePubkeyError, Pubkey};
+
+/// Ошибки движка арбитража.
+///
+/// Варианты разделяют причины, по которым пул или цепочка могут быть пропущены:
+/// транспорт, разметка аккаунта, арифметика, ликвидность, состояние пула и неподходящий токен.
+#[derive(Debug)]
+pub enum ArbError {
+    /// Ошибка RPC-запроса
+    Rpc(Box<ClientError>),
+    /// Аккаунт отсутствует у источника данных
+    AccountNotFound(Pubkey),
+    /// Данные аккаунта не соответствуют ожидаемой разметке
+    Decode(String),
+    /// Переполнение или вырожденное значение в расчётах свопа
+    MathOverflow(String),
+    /// В пуле недостаточно ликвидности для свопа
+    InsufficientLiquidity(String),
+    /// Пул не принимает свопы (пауза, миграция, устаревшее состояние и т.п.)
+    PoolDisabled(String),
+    /// Токен не относится к пулу или направление свопа не поддерживается
+    UnsupportedMint(String),
+    /// Ошибка чтения или разбора конфига
+    Config(String),
+    /// Источник данных отстаёт от запрошенного слота или данные разнесены по слотам
+    StaleSnapshot(String),
+    /// Запрос к источнику данных не уложился в таймаут
+    Timeout(String),
+    /// Ошибка потоковой подписки на аккаунты
+    Stream(String),
+}
+
+impl ArbError {
+    /// Короткая метка причины — для подсчёта пропусков по типам ошибок
+    pub fn reason(&self) -> &'static str {
+        match self {
+            ArbError::Rpc(_) => "rpc",
+            ArbError::AccountNotFound(_) => "account_not_found",
+            ArbError::Decode(_) => "decode",
+            ArbError::MathOverflow(_) => "math_overflow",
+            ArbError::InsufficientLiquidity(_) => "insufficient_liquidity",
+            ArbError::PoolDisabled(_) => "pool_disabled",
+            ArbError::UnsupportedMint(_) => "unsupported_mint",
+            ArbError::Config(_) => "config",
+            ArbError::StaleSnapshot(_) => "stale_snapshot",
+            ArbError::Timeout(_) => "timeout",
+            ArbError::Stream(_) => "stream",
+        }
+    }
+}
+
+impl fmt::Display for ArbError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ArbError::Rpc(e) => write!(f, "RPC error: {}", e),
+            ArbError::AccountNotFound(pubkey) => write!(f, "Account not found: {}", pubkey),
+            ArbError::Decode(msg) => write!(f, "Decode error: {}", msg),
+            ArbError::MathOverflow(msg) => write!(f, "Math error: {}", msg),
+            ArbError::InsufficientLiquidity(msg) => write!(f, "Insufficient liquidity: {}", msg),
+            ArbError::PoolDisabled(msg) => write!(f, "Pool disabled: {}", msg),
+            ArbError::UnsupportedMint(msg) => write!(f, "Unsupported mint: {}", msg),
+            ArbError::Config(msg) => write!(f, "Config error: {}", msg),
+            ArbError::StaleSnapshot(msg) => write!(f, "Stale snapshot: {}", msg),
+            ArbError::Timeout(msg) => write!(f, "Timeout: {}", msg),
+            ArbError::Stream(msg) => write!(f, "Stream error: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for ArbError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ArbError::Rpc(e) => Some(e.as_ref()),
+            _ => None,
+        }
+    }
+}
+
+impl From<ClientError> for ArbError {
+    fn from(e: ClientError) -> Self {
+        ArbError::Rpc(Box::new(e))
+    }
+}
+
+impl From<PubsubClientError> for ArbError {
+    fn from(e: PubsubClientError) -> Self {
+        ArbError::Stream(e.to_string())
+    }
+}
+
+impl From<tonic::Status> for ArbError {
+    fn from(e: tonic::Status) -> Self {
+        ArbError::Stream(format!("gRPC status {:?}: {}", e.code(), e.message()))
+    }
+}
+
+impl From<tonic::transport::Error> for ArbError {
+    fn from(e: tonic::transport::Error) -> Self {
+        ArbError::Stream(e.to_string())
+    }
+}
+
+impl From<std::array::TryFromSliceError> for ArbError {
+    fn from(e: std::array::TryFromSliceError) -> Self {
+        ArbError::Decode(e.to_string())
+    }
+}
+
+impl From<ParsePubkeyError> for ArbError {
+    fn from(e: ParsePubkeyError) -> Self {
+        ArbError::Config(format!("invalid pubkey: {}", e))
+    }
+}
+
+impl From<std::io::Error> for ArbError {
+    fn from(e: std::io::Error) -> Self {
+        ArbError::Config(e.to_string())
+    }
+}
+
+impl From<toml::de::Error> for ArbError {
+    fn from(e: toml::de::Error) -> Self {
+        ArbError::Config(e.to_string())
+    }
+}
@@ -0,0 +1,417 @@
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use log::warn;
+use solana_sdk::pubkey::Pubkey;
+use solana_transaction_status_client_types::{
+    option_serializer::OptionSerializer, EncodedConfirmedTransactionWithStatusMeta, EncodedTransaction, UiInstruction,
+    UiMessage,
+};
+use std::sync::Arc;
+
+#[cfg(feature = "meteora")]
+use crate::dex::meteora::{
+    constants::{dlmm_program_id, DLMM_SWAP_EVENT_DISCRIMINATOR},
+    dlmm::DlmmSwapEvent,
+};
+#[cfg(feature = "raydium")]
+use crate::dex::raydium::{
+    clmm::ClmmSwapEvent,
+    constants::{clmm_program_id, CLMM_SWAP_EVENT_DISCRIMINATOR},
+};
+use crate::dex::PoolMints;
+use crate::error::ArbError;
+use crate::pool_registry::PoolRegistry;
+
+/// Префикс инструкции Anchor event CPI (`emit_cpi!`): EVENT_IX_TAG в little-endian
+pub const EVENT_IX_TAG_LE: [u8; 8] = [228, 69, 165, 46, 81, 203, 154, 29];
+
+const PROGRAM_DATA_PREFIX: &str = "Program data: ";
+const LOG_TRUNCATED: &str = "Log truncated";
+
+/// Событие пула из транзакции, достаточное для обновления состояния без чтения аккаунтов.
+///
+/// Варианты доступны при включённых features соответствующих DEX.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PoolEvent {
+    #[cfg(feature = "raydium")]
+    ClmmSwap(ClmmSwapEvent),
+    #[cfg(feature = "meteora")]
+    DlmmSwap(DlmmSwapEvent),
+}
+
+impl PoolEvent {
+    /// Пул, к которому относится событие
+    pub fn pool(&self) -> &Pubkey {
+        match *self {
+            #[cfg(feature = "raydium")]
+            PoolEvent::ClmmSwap(ref event) => &event.pool_state,
+            #[cfg(feature = "meteora")]
+            PoolEvent::DlmmSwap(ref event) => &event.lb_pair,
+        }
+    }
+}
+
+/// Программы, события которых разбираются
+pub fn event_programs() -> Vec<Pubkey> {
+    vec![
+        #[cfg(feature = "raydium")]
+        clmm_program_id(),
+        #[cfg(feature = "meteora")]
+        dlmm_program_id(),
+    ]
+}
+
+/// Разобрать payload события Anchor (discriminator + тело) программы `program_id`.
+///
+/// `Ok(None)` — событие не относится к отслеживаемым (другая программа или другой тип события).
+pub fn decode_event(program_id: &Pubkey, payload: &[u8]) -> Result<Option<PoolEvent>, ArbError> {
+    let Some((discriminator, body)) = payload.split_first_chunk::<8>() else {
+        return Ok(None);
+    };
+
+    #[cfg(feature = "raydium")]
+    if *program_id == clmm_program_id() && *discriminator == CLMM_SWAP_EVENT_DISCRIMINATOR {
+        return Ok(Some(PoolEvent::ClmmSwap(ClmmSwapEvent::decode(body)?)));
+    }
+    #[cfg(feature = "meteora")]
+    if *program_id == dlmm_program_id() && *discriminator == DLMM_SWAP_EVENT_DISCRIMINATOR {
+        return Ok(Some(PoolEvent::DlmmSwap(DlmmSwapEvent::decode(body)?)));
+    }
+    let _ = (program_id, discriminator, body);
+    Ok(None)
+}
+
+/// Разобрать данные внутренней инструкции event CPI (`emit_cpi!`) программы `program_id`
+pub fn parse_event_cpi(program_id: &Pubkey, ix_data: &[u8]) -> Result<Option<PoolEvent>, ArbError> {
+    match ix_data.strip_prefix(&EVENT_IX_TAG_LE) {
+        Some(payload) => decode_event(program_id, payload),
+        None => Ok(None),
+    }
+}
+
+/// Внутренняя инструкция транзакции
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct InnerInstruction {
+    pub program_id: Pubkey,
+    pub data: Vec<u8>,
+}
+
+/// Внутренние инструкции транзакции, полученной в кодировке JSON.
+///
+/// Индексы программ разрешаются по ключам сообщения и адресам, загруженным из lookup-таблиц.
+pub fn inner_instructions(tx: &EncodedConfirmedTransactionWithStatusMeta) -> Result<Vec<InnerInstruction>, ArbError> {
+    let EncodedTransaction::Json(ref transaction) = tx.transaction.transaction else {
+        return Err(ArbError::Decode("transaction is not JSON-encoded".to_string()));
+    };
+    let UiMessage::Raw(ref message) = transaction.message else {
+        return Err(ArbError::Decode("transaction message is not raw".to_string()));
+    };
+    let Some(ref meta) = tx.transaction.meta else {
+        return Err(ArbError::Decode("transaction has no status meta".to_string()));
+    };
+
+    let mut keys = message.account_keys.clone();
+    if let OptionSerializer::Some(ref loaded) = meta.loaded_addresses {
+        keys.extend(loaded.writable.iter().cloned());
+        keys.extend(loaded.readonly.iter().cloned());
+    }
+
+    let OptionSerializer::Some(ref inner) = meta.inner_instructions else {
+        return Ok(Vec::new());
+    };
+    let mut instructions = Vec::new();
+    for instruction in inner.iter().flat_map(|inner| &inner.instructions) {
+        let UiInstruction::Compiled(instruction) = instruction else {
+            continue;
+        };
+        let program_id = keys
+            .get(usize::from(instruction.program_id_index))
+            .ok_or_else(|| ArbError::Decode(format!("program index {} out of range", instruction.program_id_index)))?
+            .parse()
+            .map_err(|_| ArbError::Decode("invalid program id in transaction".to_string()))?;
+        let data = bs58::decode(&instruction.data).into_vec().map_err(|e| ArbError::Decode(e.to_string()))?;
+        instructions.push(InnerInstruction { program_id, data });
+    }
+    Ok(instructions)
+}
+
+/// События из логов одной транзакции
+#[derive(Debug, Default)]
+pub struct ParsedLogs {
+    pub events: Vec<PoolEvent>,
+    /// Логи неполные или событие не разобрано: состояние пулов нужно перечитать целиком
+    pub gap: bool,
+    /// Отслеживаемая программа вызвала саму себя (`emit_cpi!`): её события есть только
+    /// во внутренних инструкциях транзакции, см. [`parse_inner_instructions`]
+    pub event_cpi: bool,
+}
+
+/// Разобрать логи транзакции: строки `Program data:` (`emit!`) относятся к программе
+/// на вершине стека вызовов, который восстанавливается по строкам `invoke`/`success`/`failed`.
+pub fn parse_logs(logs: &[String]) -> ParsedLogs {
+    let programs = event_programs();
+    let mut parsed = ParsedLogs::default();
+    let mut stack: Vec<Pubkey> = Vec::new();
+
+    for line in logs {
+        if line.starts_with(LOG_TRUNCATED) {
+            parsed.gap = true;
+            break;
+        }
+
+        if let Some(data) = line.strip_prefix(PROGRAM_DATA_PREFIX) {
+            let Some(program_id) = stack.last().filter(|program_id| programs.contains(program_id)) else {
+                continue;
+            };
+            let event = BASE64
+                .decode(data)
+                .map_err(|e| ArbError::Decode(e.to_string()))
+                .and_then(|payload| decode_event(program_id, &payload));
+            match event {
+                Ok(Some(event)) => parsed.events.push(event),
+                Ok(None) => {}
+                Err(e) => {
+                    warn!("Failed to decode event of {}: {}", program_id, e);
+                    parsed.gap = true;
+                }
+            }
+            continue;
+        }
+
+        let Some(rest) = line.strip_prefix("Program ") else {
+            continue;
+        };
+        let mut words = rest.split_whitespace();
+        let (Some(program), Some(action)) = (words.next(), words.next()) else {
+            continue;
+        };
+        match action {
+            "invoke" => {
+                let program_id = program.parse().unwrap_or_default();
+                if stack.last() == Some(&program_id) && programs.contains(&program_id) {
+                    parsed.event_cpi = true;
+                }
+                stack.push(program_id);
+            }
+            "success" | "failed:" => {
+                stack.pop();
+            }
+            _ => {}
+        }
+    }
+
+    parsed
+}
+
+/// Разобрать события event CPI (`emit_cpi!`) из внутренних инструкций транзакции
+pub fn parse_inner_instructions(instructions: &[InnerInstruction]) -> ParsedLogs {
+    let programs = event_programs();
+    let mut parsed = ParsedLogs::default();
+    for instruction in instructions.iter().filter(|instruction| programs.contains(&instruction.program_id)) {
+        match parse_event_cpi(&instruction.program_id, &instruction.data) {
+            Ok(Some(event)) => parsed.events.push(event),
+            Ok(None) => {}
+            Err(e) => {
+                warn!("Failed to decode event CPI of {}: {}", instruction.program_id, e);
+                parsed.gap = true;
+            }
+        }
+    }
+    parsed
+}
+
+/// Применить события транзакции из слота `slot` к пулам реестра.
+///
+/// Возвращает пулы, состояние которых обновлено.
+pub fn apply_events(registry: &PoolRegistry, events: &[PoolEvent], slot: u64) -> Result<Vec<Arc<dyn PoolMints>>, ArbError> {
+    let mut updated: Vec<Arc<dyn PoolMints>> = Vec::new();
+    for event in events {
+        let Some(pool) = registry.get(event.pool()) else {
+            continue;
+        };
+        if pool.apply_event(event, slot)? && !updated.iter().any(|known| Arc::ptr_eq(known, pool)) {
+            updated.push(Arc::clone(pool));
+        }
+    }
+    Ok(updated)
+}
+
+#[cfg(all(test, feature = "raydium", feature = "meteora"))]
+mod tests {
+    use super::*;
+    use crate::dex::meteora::dlmm::{DlmmInfo, LbPair, MeteoraDlmmPoolInfo};
+    use crate::dex::raydium::clmm::RaydiumClmmPoolInfo;
+    use crate::dex::PoolLoader;
+    use crate::provider::AccountSnapshot;
+    use solana_sdk::account::Account;
+    use std::mem::size_of;
+
+    fn clmm_swap_payload(pool: &Pubkey, sqrt_price_x64: u128, liquidity: u128, tick: i32) -> Vec<u8> {
+        let mut payload = CLMM_SWAP_EVENT_DISCRIMINATOR.to_vec();
+        let mut body = vec![0u8; 197];
+        body[0..32].copy_from_slice(pool.as_ref());
+        body[160] = 1;
+        body[161..177].copy_from_slice(&sqrt_price_x64.to_le_bytes());
+        body[177..193].copy_from_slice(&liquidity.to_le_bytes());
+        body[193..197].copy_from_slice(&tick.to_le_bytes());
+        payload.extend(body);
+        payload
+    }
+
+    fn dlmm_swap_payload(lb_pair: &Pubkey, end_bin_id: i32) -> Vec<u8> {
+        let mut payload = DLMM_SWAP_EVENT_DISCRIMINATOR.to_vec();
+        let mut body = vec![0u8; 129];
+        body[0..32].copy_from_slice(lb_pair.as_ref());
+        body[68..72].copy_from_slice(&end_bin_id.to_le_bytes());
+        payload.extend(body);
+        payload
+    }
+
+    fn clmm_pool(pubkey: Pubkey) -> RaydiumClmmPoolInfo {
+        let (mint_a, mint_b, amm_config) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+        let mut data = vec![0u8; 1544];
+        data[9..41].copy_from_slice(amm_config.as_ref());
+        data[73..105].copy_from_slice(mint_a.as_ref());
+        data[105..137].copy_from_slice(mint_b.as_ref());
+        data[237..253].copy_from_slice(&1_000u128.to_le_bytes());
+        data[253..269].copy_from_slice(&(1u128 << 64).to_le_bytes());
+
+        let mut mint = vec![0u8; 82];
+        mint[44] = 6;
+        let mut accounts = AccountSnapshot::new();
+        accounts.insert(pubkey, Account { data, ..Account::default() }, 100);
+        accounts.insert(mint_a, Account { data: mint.clone(), ..Account::default() }, 100);
+        accounts.insert(mint_b, Account { data: mint, ..Account::default() }, 100);
+        accounts.insert(amm_config, Account::default(), 100);
+
+        let pool = RaydiumClmmPoolInfo::from_snapshot(pubkey, &accounts).unwrap();
+        pool.ingest(&accounts).unwrap();
+        pool
+    }
+
+    fn logs(lines: &[&str]) -> Vec<String> {
+        lines.iter().map(|line| line.to_string()).collect()
+    }
+
+    #[test]
+    fn test_parse_logs_attributes_program_data_to_invoking_program() {
+        let pool = Pubkey::new_unique();
+        let clmm = clmm_program_id().to_string();
+        let event = BASE64.encode(clmm_swap_payload(&pool, 7, 8, -9));
+        let token = "TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA";
+
+        let lines = vec![
+            format!("Program {} invoke [1]", clmm),
+            "Program log: Instruction: Swap".to_string(),
+            format!("Program {} invoke [2]", token),
+            // Данные чужой программы с тем же discriminator не принимаются за событие CLMM
+            format!("Program data: {}", event),
+            format!("Program {} success", token),
+            format!("Program data: {}", event),
+            format!("Program {} consumed 50000 of 200000 compute units", clmm),
+            format!("Program {} success", clmm),
+        ];
+        let parsed = parse_logs(&lines);
+        assert!(!parsed.gap);
+        assert_eq!(parsed.events.len(), 1);
+        let PoolEvent::ClmmSwap(swap) = parsed.events[0] else { panic!("expected CLMM swap") };
+        assert_eq!((swap.pool_state, swap.sqrt_price_x64, swap.liquidity, swap.tick), (pool, 7, 8, -9));
+        assert!(swap.zero_for_one);
+
+        // Обрезанные логи и неразбираемое событие — пропуск, требующий полного обновления
+        let truncated = parse_logs(&logs(&[&format!("Program {} invoke [1]", clmm), "Log truncated"]));
+        assert!(truncated.gap);
+        let short = BASE64.encode(&clmm_swap_payload(&pool, 7, 8, -9)[..100]);
+        let broken = parse_logs(&logs(&[&format!("Program {} invoke [1]", clmm), &format!("Program data: {}", short)]));
+        assert!(broken.gap);
+        assert!(broken.events.is_empty());
+    }
+
+    #[test]
+    fn test_event_cpi_is_read_from_inner_instructions() {
+        let lb_pair = Pubkey::new_unique();
+        let dlmm = dlmm_program_id().to_string();
+        let lines = [
+            format!("Program {} invoke [1]", dlmm),
+            format!("Program {} invoke [2]", dlmm),
+            format!("Program {} success", dlmm),
+            format!("Program {} success", dlmm),
+        ];
+        let parsed = parse_logs(&lines);
+        assert!(parsed.event_cpi && !parsed.gap && parsed.events.is_empty());
+
+        // Программа DLMM загружена из lookup-таблицы: индекс за ключами сообщения
+        let mut ix_data = EVENT_IX_TAG_LE.to_vec();
+        ix_data.extend(dlmm_swap_payload(&lb_pair, 7));
+        let tx = serde_json::json!({
+            "slot": 105,
+            "transaction": {
+                "signatures": ["swap"],
+                "message": {
+                    "header": { "numRequiredSignatures": 1, "numReadonlySignedAccounts": 0, "numReadonlyUnsignedAccounts": 0 },
+                    "accountKeys": [Pubkey::new_unique().to_string()],
+                    "recentBlockhash": Pubkey::default().to_string(),
+                    "instructions": []
+                }
+            },
+            "meta": {
+                "err": null,
+                "status": { "Ok": null },
+                "fee": 5000,
+                "preBalances": [],
+                "postBalances": [],
+                "innerInstructions": [{
+                    "index": 0,
+                    "instructions": [{ "programIdIndex": 2, "accounts": [], "data": bs58::encode(&ix_data).into_string() }]
+                }],
+                "loadedAddresses": { "writable": [lb_pair.to_string()], "readonly": [dlmm] }
+            }
+        });
+        let instructions = inner_instructions(&serde_json::from_value(tx).unwrap()).unwrap();
+        assert_eq!(instructions, vec![InnerInstruction { program_id: dlmm_program_id(), data: ix_data }]);
+
+        let parsed = parse_inner_instructions(&instructions);
+        assert!(!parsed.gap);
+        let PoolEvent::DlmmSwap(swap) = parsed.events[0] else { panic!("expected DLMM swap") };
+        assert_eq!((swap.lb_pair, swap.end_bin_id), (lb_pair, 7));
+    }
+
+    #[test]
+    fn test_events_update_pool_state_in_slot_order() {
+        let clmm_pubkey = Pubkey::new_unique();
+        let lb_pair = Pubkey::new_unique();
+        let dlmm_info = DlmmInfo::load_checked(&vec![0u8; 8 + size_of::<LbPair>()]).unwrap();
+        let registry: PoolRegistry = [
+            Arc::new(clmm_pool(clmm_pubkey)) as Arc<dyn PoolMints>,
+            Arc::new(MeteoraDlmmPoolInfo::from_dlmm_info(lb_pair, &dlmm_info, 6, 9).unwrap()),
+        ]
+        .into_iter()
+        .collect();
+
+        let mut dlmm_ix = EVENT_IX_TAG_LE.to_vec();
+        dlmm_ix.extend(dlmm_swap_payload(&lb_pair, 42));
+        let events = [
+            decode_event(&clmm_program_id(), &clmm_swap_payload(&clmm_pubkey, 2u128 << 64, 5_000, 13_863)).unwrap().unwrap(),
+            parse_event_cpi(&dlmm_program_id(), &dlmm_ix).unwrap().unwrap(),
+            // Событие пула, которого нет в реестре, пропускается
+            decode_event(&clmm_program_id(), &clmm_swap_payload(&Pubkey::new_unique(), 1, 1, 1)).unwrap().unwrap(),
+        ];
+        let updated = apply_events(&registry, &events, 101).unwrap();
+        assert_eq!(updated.len(), 2);
+
+        let clmm = registry.get(&clmm_pubkey).unwrap();
+        assert_eq!(clmm.state_slot(), 101);
+        // Цена 4, комиссия 25 bps: 1_000 * 9_975 / 10_000 = 997 на входе
+        assert_eq!(clmm.amount_out(1_000, clmm.mint_a()).unwrap(), 3_988);
+        assert_eq!(registry.get(&lb_pair).unwrap().state_slot(), 101);
+
+        // Событие из более старого слота состояние не откатывает
+        let stale = [decode_event(&clmm_program_id(), &clmm_swap_payload(&clmm_pubkey, 1u128 << 64, 5_000, 0)).unwrap().unwrap()];
+        assert!(apply_events(&registry, &stale, 100).unwrap().is_empty());
+        assert_eq!(clmm.amount_out(1_000, clmm.mint_a()).unwrap(), 3_988);
+
+        // Discriminator чужой программы или без префикса event CPI — не событие
+        assert_eq!(decode_event(&dlmm_program_id(), &clmm_swap_payload(&clmm_pubkey, 1, 1, 1)).unwrap(), None);
+        assert_eq!(parse_event_cpi(&dlmm_program_id(), &dlmm_swap_payload(&lb_pair, 1)).unwrap(), None);
+    }
+}
@@ -0,0 +1,10 @@
+/// Endpoint Yellowstone gRPC по умолчанию
+pub const DEFAULT_GEYSER_ENDPOINT: &str = "http://127.0.0.1:10000";
+/// Метод двунаправленной подписки сервиса `geyser.Geyser`
+pub const SUBSCRIBE_PATH: &str = "/geyser.Geyser/Subscribe";
+/// Имя фильтра аккаунтов в запросе подписки
+pub const ACCOUNTS_FILTER: &str = "pools";
+/// Заголовок авторизации Yellowstone
+pub const X_TOKEN_HEADER: &str = "x-token";
+/// Идентификатор ответного ping: сервер и балансировщики закрывают молчащие подписки
+pub const PING_ID: i32 = 1;
@@ -0,0 +1,347 @@
+pub mod constants;
+pub mod proto;
+
+use futures::channel::mpsc;
+use log::{info, warn};
+use serde::Deserialize;
+use solana_sdk::account::Account;
+use solana_sdk::pubkey::Pubkey;
+use std::collections::HashMap;
+use std::ops::ControlFlow;
+use std::sync::Arc;
+use std::time::Duration;
+use tonic::codegen::http::uri::PathAndQuery;
+use tonic::transport::{ClientTlsConfig, Endpoint};
+use tonic_prost::ProstCodec;
+
+use crate::cache::AccountCache;
+use crate::dex::PoolMints;
+use crate::error::ArbError;
+use crate::geyser::constants::{ACCOUNTS_FILTER, DEFAULT_GEYSER_ENDPOINT, PING_ID, SUBSCRIBE_PATH, X_TOKEN_HEADER};
+use crate::geyser::proto::{
+    CommitmentLevel, SubscribeRequest, SubscribeRequestFilterAccounts, SubscribeRequestPing, SubscribeUpdate,
+    SubscribeUpdateAccountInfo, UpdateOneof,
+};
+use crate::pipeline::{AsyncAccountProvider, PipelineConfig};
+use crate::pool_registry::PoolRegistry;
+use crate::stream::{apply_update, resync, AccountIndex, DEFAULT_RECONNECT_DELAY_MS};
+
+/// Параметры источника Yellowstone gRPC (секция `[geyser]` конфига)
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct GeyserConfig {
+    /// Получать обновления через Geyser gRPC вместо WebSocket
+    pub enabled: bool,
+    /// Адрес gRPC-сервера; для `https://` используется TLS
+    pub endpoint: String,
+    /// Токен доступа, передаётся в заголовке `x-token`
+    pub x_token: Option<String>,
+    /// Пауза перед переподключением, мс
+    pub reconnect_delay_ms: u64,
+}
+
+impl Default for GeyserConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            endpoint: DEFAULT_GEYSER_ENDPOINT.to_string(),
+            x_token: None,
+            reconnect_delay_ms: DEFAULT_RECONNECT_DELAY_MS,
+        }
+    }
+}
+
+impl GeyserConfig {
+    pub fn reconnect_delay(&self) -> Duration {
+        Duration::from_millis(self.reconnect_delay_ms)
+    }
+}
+
+/// Потоковый режим через Yellowstone Geyser gRPC: подписка на аккаунты состояния пулов
+/// (пулы, vault, bin array, tick array) по pubkey.
+///
+/// Как и в `run_account_stream`, после каждого (пере)подключения выполняется полная
+/// пересинхронизация через `provider`, а `ControlFlow::Break` из `on_update` останавливает поток.
+pub async fn run_geyser_stream<P, F>(
+    config: &GeyserConfig,
+    registry: &PoolRegistry,
+    cache: &AccountCache,
+    provider: &P,
+    pipeline: &PipelineConfig,
+    mut on_update: F,
+) -> Result<(), ArbError>
+where
+    P: AsyncAccountProvider,
+    F: FnMut(&[Arc<dyn PoolMints>]) -> ControlFlow<()>,
+{
+    let index = AccountIndex::new(registry);
+
+    loop {
+        match geyser_session(config, registry, &index, cache, provider, pipeline, &mut on_update).await {
+            Ok(ControlFlow::Break(())) => return Ok(()),
+            Ok(ControlFlow::Continue(())) => warn!("Geyser stream closed, reconnecting"),
+            Err(e) => warn!("Geyser stream failed: {}, reconnecting", e),
+        }
+        tokio::time::sleep(config.reconnect_delay()).await;
+    }
+}
+
+// Одно подключение: подписка, пересинхронизация, затем обработка обновлений до разрыва
+async fn geyser_session<P, F>(
+    config: &GeyserConfig,
+    registry: &PoolRegistry,
+    index: &AccountIndex,
+    cache: &AccountCache,
+    provider: &P,
+    pipeline: &PipelineConfig,
+    on_update: &mut F,
+) -> Result<ControlFlow<()>, ArbError>
+where
+    P: AsyncAccountProvider,
+    F: FnMut(&[Arc<dyn PoolMints>]) -> ControlFlow<()>,
+{
+    let mut endpoint = Endpoint::from_shared(config.endpoint.clone())?;
+    if config.endpoint.starts_with("https://") {
+        endpoint = endpoint.tls_config(ClientTlsConfig::new().with_webpki_roots())?;
+    }
+    let mut grpc = tonic::client::Grpc::new(endpoint.connect().await?);
+    grpc.ready().await?;
+
+    // Исходящий поток запросов остаётся открытым: по нему отправляются ответы на ping
+    let accounts = index.accounts();
+    let (requests, outbound) = mpsc::unbounded();
+    send_request(&requests, subscribe_request(&accounts))?;
+
+    let mut request = tonic::Request::new(outbound);
+    if let Some(token) = &config.x_token {
+        let token = token.parse().map_err(|_| ArbError::Config("invalid geyser x_token".to_string()))?;
+        request.metadata_mut().insert(X_TOKEN_HEADER, token);
+    }
+    let codec = ProstCodec::<SubscribeRequest, SubscribeUpdate>::default();
+    let mut updates = grpc
+        .streaming(request, PathAndQuery::from_static(SUBSCRIBE_PATH), codec)
+        .await?
+        .into_inner();
+    info!("Subscribed to {} accounts at {}", accounts.len(), config.endpoint);
+
+    // Подписка уже активна: пересинхронизация закрывает пропуск за время разрыва
+    if resync(registry, cache, provider, pipeline, on_update).await?.is_break() {
+        return Ok(ControlFlow::Break(()));
+    }
+
+    // Последняя применённая запись (slot, write_version) по аккаунту: внутри слота
+    // порядок задаёт write_version
+    let mut applied: HashMap<Pubkey, (u64, u64)> = HashMap::new();
+    while let Some(update) = updates.message().await? {
+        match update.update_oneof {
+            Some(UpdateOneof::Account(update)) => {
+                let Some(info) = update.account else { continue };
+                let version = (update.slot, info.write_version);
+                let (pubkey, account) = match decode_account(info) {
+                    Ok(decoded) => decoded,
+                    Err(e) => {
+                        warn!("Skipping undecodable geyser update: {}", e);
+                        continue;
+                    }
+                };
+                if applied.get(&pubkey).is_some_and(|last| *last >= version) {
+                    continue;
+                }
+                applied.insert(pubkey, version);
+
+                let updated = apply_update(cache, index, pubkey, account, update.slot);
+                if !updated.is_empty() && on_update(&updated).is_break() {
+                    return Ok(ControlFlow::Break(()));
+                }
+            }
+            Some(UpdateOneof::Ping(_)) => send_request(&requests, ping_request())?,
+            Some(UpdateOneof::Pong(_)) | None => {}
+        }
+    }
+
+    Ok(ControlFlow::Continue(()))
+}
+
+fn subscribe_request(accounts: &[Pubkey]) -> SubscribeRequest {
+    let filter = SubscribeRequestFilterAccounts {
+        account: accounts.iter().map(Pubkey::to_string).collect(),
+        owner: Vec::new(),
+    };
+    SubscribeRequest {
+        accounts: HashMap::from([(ACCOUNTS_FILTER.to_string(), filter)]),
+        commitment: Some(CommitmentLevel::Confirmed as i32),
+        ping: None,
+    }
+}
+
+fn ping_request() -> SubscribeRequest {
+    SubscribeRequest { ping: Some(SubscribeRequestPing { id: PING_ID }), ..SubscribeRequest::default() }
+}
+
+fn send_request(requests: &mpsc::UnboundedSender<SubscribeRequest>, request: SubscribeRequest) -> Result<(), ArbError> {
+    requests
+        .unbounded_send(request)
+        .map_err(|_| ArbError::Stream("geyser request stream closed".to_string()))
+}
+
+fn decode_account(info: SubscribeUpdateAccountInfo) -> Result<(Pubkey, Account), ArbError> {
+    let pubkey = Pubkey::try_from(info.pubkey.as_slice())?;
+    let account = Account {
+        lamports: info.lamports,
+        data: info.data,
+        owner: Pubkey::try_from(info.owner.as_slice())?,
+        executable: info.executable,
+        rent_epoch: info.rent_epoch,
+    };
+    Ok((pubkey, account))
+}
+
+#[cfg(all(test, feature = "raydium"))]
+mod tests {
+    use super::*;
+    use crate::geyser::proto::SubscribeUpdatePing;
+    use crate::provider::InMemoryAccountProvider;
+    use crate::test_utils::{spl_token_data, stop_after, AmmFixture};
+    use futures::stream::{self, StreamExt};
+    use std::collections::VecDeque;
+    use std::convert::Infallible;
+    use std::sync::Mutex;
+    use std::task::{Context, Poll};
+    use tokio::net::TcpListener;
+    use tonic::body::Body;
+    use tonic::codegen::{http, BoxFuture, BoxStream, Service};
+    use tonic::server::StreamingService;
+    use tonic::{Request, Response, Status, Streaming};
+
+    fn account_update(pubkey: Pubkey, slot: u64, write_version: u64, amount: u64) -> SubscribeUpdate {
+        let info = SubscribeUpdateAccountInfo {
+            pubkey: pubkey.to_bytes().to_vec(),
+            lamports: 2_039_280,
+            owner: Pubkey::new_unique().to_bytes().to_vec(),
+            data: spl_token_data(amount),
+            write_version,
+            ..SubscribeUpdateAccountInfo::default()
+        };
+        let update = proto::SubscribeUpdateAccount { account: Some(info), slot, is_startup: false };
+        SubscribeUpdate { filters: vec![ACCOUNTS_FILTER.to_string()], update_oneof: Some(UpdateOneof::Account(update)) }
+    }
+
+    fn ping() -> SubscribeUpdate {
+        SubscribeUpdate { filters: Vec::new(), update_oneof: Some(UpdateOneof::Ping(SubscribeUpdatePing {})) }
+    }
+
+    /// Записанные обновления на каждое подключение и всё, что прислал клиент
+    #[derive(Clone, Default)]
+    struct MockGeyser {
+        sessions: Arc<Mutex<VecDeque<Vec<SubscribeUpdate>>>>,
+        requests: Arc<Mutex<Vec<SubscribeRequest>>>,
+        tokens: Arc<Mutex<Vec<String>>>,
+    }
+
+    impl StreamingService<SubscribeRequest> for MockGeyser {
+        type Response = SubscribeUpdate;
+        type ResponseStream = BoxStream<SubscribeUpdate>;
+        type Future = BoxFuture<Response<Self::ResponseStream>, Status>;
+
+        // Проигрывает обновления сессии, затем ждёт следующий запрос клиента и закрывает поток
+        fn call(&mut self, request: Request<Streaming<SubscribeRequest>>) -> Self::Future {
+            let mock = self.clone();
+            Box::pin(async move {
+                if let Some(token) = request.metadata().get(X_TOKEN_HEADER) {
+                    mock.tokens.lock().unwrap().push(token.to_str().unwrap().to_string());
+                }
+                let mut inbound = request.into_inner();
+                let subscribe = inbound.message().await?.expect("subscribe request");
+                mock.requests.lock().unwrap().push(subscribe);
+
+                let recorded = mock.sessions.lock().unwrap().pop_front().unwrap_or_default();
+                let wait_reply = recorded.iter().any(|update| matches!(update.update_oneof, Some(UpdateOneof::Ping(_))));
+                let tail = stream::once(async move {
+                    if wait_reply && let Ok(Some(reply)) = inbound.message().await {
+                        mock.requests.lock().unwrap().push(reply);
+                    }
+                })
+                .filter_map(|_| async { None });
+                let updates = stream::iter(recorded.into_iter().map(Ok)).chain(tail);
+                Ok(Response::new(Box::pin(updates) as Self::ResponseStream))
+            })
+        }
+    }
+
+    impl Service<http::Request<Body>> for MockGeyser {
+        type Response = http::Response<Body>;
+        type Error = Infallible;
+        type Future = BoxFuture<Self::Response, Self::Error>;
+
+        fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn call(&mut self, request: http::Request<Body>) -> Self::Future {
+            assert_eq!(request.uri().path(), SUBSCRIBE_PATH);
+            let mock = self.clone();
+            Box::pin(async move {
+                let codec = ProstCodec::<SubscribeUpdate, SubscribeRequest>::default();
+                Ok(tonic::server::Grpc::new(codec).streaming(mock, request).await)
+            })
+        }
+    }
+
+    async fn serve(mock: MockGeyser) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let incoming = stream::unfold(listener, |listener| async move {
+            let accepted = listener.accept().await.map(|(tcp, _)| tcp);
+            Some((accepted, listener))
+        });
+        tokio::spawn(tonic::transport::Server::builder().serve_with_incoming(mock, incoming));
+        url
+    }
+
+    #[tokio::test]
+    async fn test_geyser_orders_updates_answers_ping_and_resyncs() {
+        let mut provider = InMemoryAccountProvider::new(100);
+        let cache = AccountCache::new();
+        let amm = AmmFixture::new();
+        let registry = amm.load(&mut provider, &cache).await;
+        let (base_vault, quote_vault) = (amm.base_vault, amm.quote_vault);
+
+        // Первая сессия: запись с меньшим write_version и запись из старого слота отбрасываются,
+        // затем ping; вторая сессия после переподключения приносит обновление quote vault
+        let mock = MockGeyser::default();
+        mock.sessions.lock().unwrap().extend([
+            vec![
+                account_update(base_vault, 101, 7, 4_000_000),
+                account_update(base_vault, 101, 6, 3_000_000),
+                account_update(base_vault, 100, 9, 5_000_000),
+                ping(),
+            ],
+            vec![account_update(quote_vault, 102, 1, 2_000_000)],
+        ]);
+        let url = serve(mock.clone()).await;
+        let config = GeyserConfig {
+            enabled: true,
+            endpoint: url,
+            x_token: Some("secret".to_string()),
+            reconnect_delay_ms: 10,
+        };
+
+        // Пересинхронизация, обновление, пересинхронизация после переподключения, обновление
+        run_geyser_stream(&config, &registry, &cache, &provider, &PipelineConfig::default(), stop_after(4))
+            .await
+            .unwrap();
+        amm.assert_streamed(&registry, &cache);
+        let pool = registry.get(&amm.pool).unwrap();
+
+        // Подписка по pubkey на все аккаунты пула, ответ на ping, токен в каждом подключении
+        let requests = mock.requests.lock().unwrap();
+        let mut subscribed = requests[0].accounts[ACCOUNTS_FILTER].account.clone();
+        subscribed.sort();
+        let mut expected: Vec<String> = pool.required_accounts().iter().map(Pubkey::to_string).collect();
+        expected.sort();
+        assert_eq!(subscribed, expected);
+        assert_eq!(requests[1].ping, Some(SubscribeRequestPing { id: PING_ID }));
+        assert_eq!(requests.len(), 3);
+        assert_eq!(*mock.tokens.lock().unwrap(), vec!["secret".to_string(); 2]);
+    }
+}
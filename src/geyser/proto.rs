@@ -0,0 +1,97 @@
+//! Подмножество сообщений `geyser.proto` (Yellowstone gRPC), нужное для подписки на аккаунты.
+//!
+//! Номера полей совпадают с upstream-схемой; поля и варианты, которые не используются,
+//! опущены — prost пропускает неизвестные теги при декодировании.
+
+use std::collections::HashMap;
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct SubscribeRequest {
+    /// Фильтры аккаунтов по имени фильтра
+    #[prost(map = "string, message", tag = "1")]
+    pub accounts: HashMap<String, SubscribeRequestFilterAccounts>,
+    #[prost(enumeration = "CommitmentLevel", optional, tag = "6")]
+    pub commitment: Option<i32>,
+    #[prost(message, optional, tag = "9")]
+    pub ping: Option<SubscribeRequestPing>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct SubscribeRequestFilterAccounts {
+    /// Адреса аккаунтов в base58
+    #[prost(string, repeated, tag = "2")]
+    pub account: Vec<String>,
+    #[prost(string, repeated, tag = "3")]
+    pub owner: Vec<String>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct SubscribeRequestPing {
+    #[prost(int32, tag = "1")]
+    pub id: i32,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct SubscribeUpdate {
+    /// Имена фильтров, под которые попало обновление
+    #[prost(string, repeated, tag = "1")]
+    pub filters: Vec<String>,
+    #[prost(oneof = "UpdateOneof", tags = "2, 6, 9")]
+    pub update_oneof: Option<UpdateOneof>,
+}
+
+#[derive(Clone, PartialEq, prost::Oneof)]
+pub enum UpdateOneof {
+    #[prost(message, tag = "2")]
+    Account(SubscribeUpdateAccount),
+    #[prost(message, tag = "6")]
+    Ping(SubscribeUpdatePing),
+    #[prost(message, tag = "9")]
+    Pong(SubscribeUpdatePong),
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct SubscribeUpdateAccount {
+    #[prost(message, optional, tag = "1")]
+    pub account: Option<SubscribeUpdateAccountInfo>,
+    #[prost(uint64, tag = "2")]
+    pub slot: u64,
+    #[prost(bool, tag = "3")]
+    pub is_startup: bool,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct SubscribeUpdateAccountInfo {
+    #[prost(bytes = "vec", tag = "1")]
+    pub pubkey: Vec<u8>,
+    #[prost(uint64, tag = "2")]
+    pub lamports: u64,
+    #[prost(bytes = "vec", tag = "3")]
+    pub owner: Vec<u8>,
+    #[prost(bool, tag = "4")]
+    pub executable: bool,
+    #[prost(uint64, tag = "5")]
+    pub rent_epoch: u64,
+    #[prost(bytes = "vec", tag = "6")]
+    pub data: Vec<u8>,
+    /// Порядковый номер записи аккаунта внутри слота
+    #[prost(uint64, tag = "7")]
+    pub write_version: u64,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct SubscribeUpdatePing {}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct SubscribeUpdatePong {
+    #[prost(int32, tag = "1")]
+    pub id: i32,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, prost::Enumeration)]
+#[repr(i32)]
+pub enum CommitmentLevel {
+    Processed = 0,
+    Confirmed = 1,
+    Finalized = 2,
+}
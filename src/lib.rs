@@ -0,0 +1,48 @@
+//! Off-chain котировки пулов Solana DEX и поиск арбитражных цепочек.
+//!
+//! Публичный API:
+//! - [`PoolMints`] — общий trait пула: аккаунты состояния, `ingest` из снапшота и чистый `amount_out`;
+//! - адаптеры в [`dex`] — каждый DEX за своей cargo feature (`raydium`, `meteora`, `phoenix`,
+//!   `openbook`, `saber`, `stake-pool`; по умолчанию включены все);
+//! - [`PoolRegistry`] — пулы, индексированные по обоим mint;
+//! - [`build_arbitrage_graph`] — поиск цепочек по реестру с логарифмическим префильтром ([`prefilter`]);
+//!   [`OpportunityTracker`] — перекотировка только циклов обновлённых пулов;
+//! - загрузка ([`loader`], [`pipeline`], пул RPC endpoint'ов [`rpc`], кэш метаданных [`metadata`]), потоковые обновления
+//!   ([`stream`], [`geyser`], [`events`]) и циклы с корректной остановкой ([`daemon`]).
+
+pub mod arb;
+pub mod cache;
+// Помощники разбора аккаунтов; при отключённых DEX часть из них не используется
+#[cfg_attr(not(all(feature = "raydium", feature = "meteora", feature = "phoenix", feature = "openbook", feature = "saber", feature = "stake-pool")), allow(dead_code))]
+mod common;
+pub mod config;
+pub mod cycles;
+pub mod daemon;
+pub mod dex;
+pub mod error;
+pub mod events;
+pub mod geyser;
+pub mod loader;
+pub mod metadata;
+pub mod opportunities;
+pub mod pipeline;
+pub mod pool_registry;
+pub mod prefilter;
+pub mod provider;
+pub mod rpc;
+pub mod stream;
+#[cfg(test)]
+// Фикстуры отдельных DEX; при отключённых DEX часть из них не используется
+#[cfg_attr(not(all(feature = "raydium", feature = "meteora", feature = "phoenix", feature = "openbook", feature = "saber", feature = "stake-pool")), allow(dead_code))]
+mod test_utils;
+
+pub use arb::{build_arbitrage_graph, quote_cycles};
+pub use cycles::CycleIndex;
+pub use cache::AccountCache;
+pub use dex::{PoolLoader, PoolMints, StateSlot};
+pub use error::ArbError;
+pub use loader::{load_pools, load_pools_by_address, PoolKind};
+pub use metadata::MetadataCache;
+pub use opportunities::{OpportunityChange, OpportunityTracker};
+pub use pool_registry::PoolRegistry;
+pub use provider::{AccountProvider, AccountSnapshot};
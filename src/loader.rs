@@ -0,0 +1,435 @@
+use solana_sdk::account::Account;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::sysvar::clock;
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use log::info;
+use serde::{Deserialize, Serialize};
+
+#[cfg_attr(
+    not(any(feature = "raydium", feature = "meteora", feature = "phoenix", feature = "openbook", feature = "saber", feature = "stake-pool")),
+    allow(unused_imports)
+)]
+use crate::dex::PoolLoader;
+use crate::dex::PoolMints;
+use crate::dex::registry::DexRegistry;
+#[cfg(feature = "raydium")]
+use crate::dex::raydium::{amm::RaydiumAmmPoolInfo, clmm::RaydiumClmmPoolInfo};
+#[cfg(feature = "meteora")]
+use crate::dex::meteora::{dbc::MeteoraDbcPoolInfo, dlmm::MeteoraDlmmPoolInfo};
+#[cfg(feature = "phoenix")]
+use crate::dex::phoenix::market::PhoenixMarketInfo;
+#[cfg(feature = "openbook")]
+use crate::dex::openbook::market::OpenbookMarketInfo;
+#[cfg(feature = "saber")]
+use crate::dex::saber::swap::SaberStableSwapPoolInfo;
+#[cfg(feature = "stake-pool")]
+use crate::dex::stake_pool::pool::StakePoolInfo;
+use crate::error::ArbError;
+use crate::cache::AccountCache;
+use crate::metadata::PoolMetadata;
+use crate::provider::{snapshot_account, AccountProvider, AccountSnapshot};
+
+/// Тип адаптера пула — соответствует спискам в конфиге
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum PoolKind {
+    RaydiumAmm,
+    RaydiumClmm,
+    MeteoraDlmm,
+    MeteoraDbc,
+    Phoenix,
+    Openbook,
+    Saber,
+    StakePool,
+}
+
+impl PoolKind {
+    /// Cargo feature, за которой находится адаптер
+    pub fn feature(self) -> &'static str {
+        match self {
+            PoolKind::RaydiumAmm | PoolKind::RaydiumClmm => "raydium",
+            PoolKind::MeteoraDlmm | PoolKind::MeteoraDbc => "meteora",
+            PoolKind::Phoenix => "phoenix",
+            PoolKind::Openbook => "openbook",
+            PoolKind::Saber => "saber",
+            PoolKind::StakePool => "stake-pool",
+        }
+    }
+
+    // Пул типа, адаптер которого не собран, отклоняется ошибкой конфига
+    fn disabled(self) -> ArbError {
+        ArbError::Config(format!("{:?} pools require the `{}` cargo feature", self, self.feature()))
+    }
+
+    fn dependent_accounts(self, pool_data: &[u8]) -> Result<Vec<Pubkey>, ArbError> {
+        match self {
+            #[cfg(feature = "raydium")]
+            PoolKind::RaydiumAmm => RaydiumAmmPoolInfo::dependent_accounts(pool_data),
+            #[cfg(feature = "raydium")]
+            PoolKind::RaydiumClmm => RaydiumClmmPoolInfo::dependent_accounts(pool_data),
+            #[cfg(feature = "meteora")]
+            PoolKind::MeteoraDlmm => MeteoraDlmmPoolInfo::dependent_accounts(pool_data),
+            #[cfg(feature = "meteora")]
+            PoolKind::MeteoraDbc => MeteoraDbcPoolInfo::dependent_accounts(pool_data),
+            #[cfg(feature = "phoenix")]
+            PoolKind::Phoenix => PhoenixMarketInfo::dependent_accounts(pool_data),
+            #[cfg(feature = "openbook")]
+            PoolKind::Openbook => OpenbookMarketInfo::dependent_accounts(pool_data),
+            #[cfg(feature = "saber")]
+            PoolKind::Saber => SaberStableSwapPoolInfo::dependent_accounts(pool_data),
+            #[cfg(feature = "stake-pool")]
+            PoolKind::StakePool => StakePoolInfo::dependent_accounts(pool_data),
+            #[allow(unreachable_patterns)]
+            _ => {
+                let _ = pool_data;
+                Err(self.disabled())
+            }
+        }
+    }
+
+    fn build(self, pool_pubkey: Pubkey, accounts: &AccountSnapshot) -> Result<Arc<dyn PoolMints>, ArbError> {
+        match self {
+            #[cfg(feature = "raydium")]
+            PoolKind::RaydiumAmm => Ok(Arc::new(RaydiumAmmPoolInfo::from_snapshot(pool_pubkey, accounts)?)),
+            #[cfg(feature = "raydium")]
+            PoolKind::RaydiumClmm => Ok(Arc::new(RaydiumClmmPoolInfo::from_snapshot(pool_pubkey, accounts)?)),
+            #[cfg(feature = "meteora")]
+            PoolKind::MeteoraDlmm => Ok(Arc::new(MeteoraDlmmPoolInfo::from_snapshot(pool_pubkey, accounts)?)),
+            #[cfg(feature = "meteora")]
+            PoolKind::MeteoraDbc => Ok(Arc::new(MeteoraDbcPoolInfo::from_snapshot(pool_pubkey, accounts)?)),
+            #[cfg(feature = "phoenix")]
+            PoolKind::Phoenix => Ok(Arc::new(PhoenixMarketInfo::from_snapshot(pool_pubkey, accounts)?)),
+            #[cfg(feature = "openbook")]
+            PoolKind::Openbook => Ok(Arc::new(OpenbookMarketInfo::from_snapshot(pool_pubkey, accounts)?)),
+            #[cfg(feature = "saber")]
+            PoolKind::Saber => Ok(Arc::new(SaberStableSwapPoolInfo::from_snapshot(pool_pubkey, accounts)?)),
+            #[cfg(feature = "stake-pool")]
+            PoolKind::StakePool => Ok(Arc::new(StakePoolInfo::from_snapshot(pool_pubkey, accounts)?)),
+            #[allow(unreachable_patterns)]
+            _ => {
+                let _ = (pool_pubkey, accounts);
+                Err(self.disabled())
+            }
+        }
+    }
+}
+
+/// Пакетная загрузка пулов через getMultipleAccounts.
+///
+/// Три прохода вместо запросов на каждый пул:
+/// 1. аккаунты пулов и sysvar Clock;
+/// 2. зависимости (mints, AmmConfig, PoolConfig, стороны стакана);
+/// 3. аккаунты состояния (`required_accounts`).
+///
+/// Все проходы идут через общий кэш: аккаунты, которые уже в нём есть (общие mints,
+/// vaults пулов из прошлых загрузок), повторно не запрашиваются.
+/// Каждый пул строится один раз, даже если он указан несколько раз.
+/// Владелец аккаунта пула должен совпадать с программой указанного типа.
+///
+/// Статические зависимости пулов, сохранённые в метаданных кэша, во втором проходе
+/// не запрашиваются; метаданные новых пулов записываются после построения.
+pub fn load_pools(
+    provider: &dyn AccountProvider,
+    cache: &AccountCache,
+    pools: &[(PoolKind, Pubkey)],
+) -> Result<HashMap<Pubkey, Arc<dyn PoolMints>>, ArbError> {
+    let pools = unique_requests(pools);
+
+    cache.load_missing(provider, &pool_keys(&pools))?;
+    restore_metadata(cache, &pools);
+    cache.load_missing(provider, &dependency_keys(cache, &pools)?)?;
+    let loaded = build_pools(cache, &pools)?;
+    record_metadata(cache, &pools, &loaded)?;
+    cache.load_missing(provider, &state_keys(&loaded))?;
+    ingest_pools(cache, &loaded)?;
+
+    Ok(loaded)
+}
+
+/// Загрузка пулов по одним адресам: тип адаптера определяется по программе-владельцу аккаунта.
+///
+/// Аккаунты неизвестных программ отклоняются ошибкой конфига.
+pub fn load_pools_by_address(
+    provider: &dyn AccountProvider,
+    cache: &AccountCache,
+    addresses: &[Pubkey],
+) -> Result<HashMap<Pubkey, Arc<dyn PoolMints>>, ArbError> {
+    cache.load_missing(provider, &address_keys(addresses))?;
+    load_pools(provider, cache, &resolve_kinds(cache, addresses)?)
+}
+
+/*
+Шаги загрузки без обращений к сети — общие для блокирующего загрузчика и async-пайплайна:
+между ними вызывающий код догружает в кэш аккаунты, которых не хватает.
+*/
+
+// Запросы без повторов по адресу пула
+pub(crate) fn unique_requests(pools: &[(PoolKind, Pubkey)]) -> Vec<(PoolKind, Pubkey)> {
+    let mut seen: HashSet<Pubkey> = HashSet::new();
+    pools.iter().copied().filter(|(_, pubkey)| seen.insert(*pubkey)).collect()
+}
+
+// Проход 1: аккаунты пулов и Clock
+pub(crate) fn pool_keys(pools: &[(PoolKind, Pubkey)]) -> Vec<Pubkey> {
+    let mut keys: Vec<Pubkey> = pools.iter().map(|(_, pubkey)| *pubkey).collect();
+    keys.push(clock::id());
+    keys
+}
+
+// Адреса пулов без типа и Clock
+pub(crate) fn address_keys(addresses: &[Pubkey]) -> Vec<Pubkey> {
+    let mut keys = addresses.to_vec();
+    keys.push(clock::id());
+    keys
+}
+
+// Тип адаптера по владельцу аккаунта пула из кэша
+pub(crate) fn resolve_kinds(cache: &AccountCache, addresses: &[Pubkey]) -> Result<Vec<(PoolKind, Pubkey)>, ArbError> {
+    let registry = DexRegistry::default();
+    let accounts = cache.read();
+    addresses
+        .iter()
+        .map(|pubkey| Ok((registry.resolve(pubkey, snapshot_account(&accounts, pubkey)?)?, *pubkey)))
+        .collect()
+}
+
+// Проход 2: проверка владельца и зависимости пулов
+pub(crate) fn dependency_keys(cache: &AccountCache, pools: &[(PoolKind, Pubkey)]) -> Result<Vec<Pubkey>, ArbError> {
+    let registry = DexRegistry::default();
+    let accounts = cache.read();
+    let mut dependencies: Vec<Pubkey> = Vec::new();
+    for (kind, pubkey) in pools {
+        let account = snapshot_account(&accounts, pubkey)?;
+        registry.check(pubkey, account, *kind)?;
+        dependencies.extend(kind.dependent_accounts(&account.data)?);
+    }
+    Ok(dependencies)
+}
+
+// Статические зависимости из метаданных — в кэш аккаунтов, до прохода 2.
+// Запись, не совпавшая со свежим аккаунтом пула, удаляется и собирается заново
+pub(crate) fn restore_metadata(cache: &AccountCache, pools: &[(PoolKind, Pubkey)]) {
+    let mut restored: Vec<(Pubkey, Account)> = Vec::new();
+    {
+        let accounts = cache.read();
+        let mut metadata = cache.metadata_mut();
+        for (kind, pubkey) in pools {
+            let Some(account) = accounts.get(pubkey) else { continue };
+            match metadata.validated(*kind, pubkey, account) {
+                Some(static_accounts) => restored.extend_from_slice(static_accounts),
+                None => {
+                    if metadata.remove(pubkey).is_some() {
+                        info!("Pool metadata for {} does not match the pool account, rebuilding", pubkey);
+                    }
+                }
+            }
+        }
+    }
+
+    // Слот 0: любая версия из сети считается свежее сохранённой
+    for (pubkey, account) in restored {
+        cache.insert(pubkey, account, 0);
+    }
+}
+
+// Метаданные построенных пулов: зависимости, не входящие в аккаунты состояния
+pub(crate) fn record_metadata(
+    cache: &AccountCache,
+    pools: &[(PoolKind, Pubkey)],
+    loaded: &HashMap<Pubkey, Arc<dyn PoolMints>>,
+) -> Result<(), ArbError> {
+    let accounts = cache.read();
+    let mut metadata = cache.metadata_mut();
+    for (kind, pubkey) in pools {
+        if metadata.get(pubkey).is_some() {
+            continue;
+        }
+
+        let account = snapshot_account(&accounts, pubkey)?;
+        let dynamic: HashSet<Pubkey> = loaded[pubkey].required_accounts().into_iter().collect();
+        let static_accounts = kind
+            .dependent_accounts(&account.data)?
+            .into_iter()
+            .filter(|key| !dynamic.contains(key) && *key != clock::id())
+            .map(|key| Ok((key, snapshot_account(&accounts, &key)?.clone())))
+            .collect::<Result<Vec<_>, ArbError>>()?;
+        metadata.insert(*pubkey, PoolMetadata {
+            kind: *kind,
+            owner: account.owner,
+            data_len: account.data.len(),
+            accounts: static_accounts,
+        });
+    }
+    Ok(())
+}
+
+// Построение адаптеров из кэша
+pub(crate) fn build_pools(
+    cache: &AccountCache,
+    pools: &[(PoolKind, Pubkey)],
+) -> Result<HashMap<Pubkey, Arc<dyn PoolMints>>, ArbError> {
+    let accounts = cache.read();
+    let mut loaded: HashMap<Pubkey, Arc<dyn PoolMints>> = HashMap::with_capacity(pools.len());
+    for (kind, pubkey) in pools {
+        loaded.insert(*pubkey, kind.build(*pubkey, &accounts)?);
+    }
+    Ok(loaded)
+}
+
+// Проход 3: аккаунты состояния построенных пулов
+pub(crate) fn state_keys(loaded: &HashMap<Pubkey, Arc<dyn PoolMints>>) -> Vec<Pubkey> {
+    loaded.values().flat_map(|pool| pool.required_accounts()).collect()
+}
+
+// Начальное состояние пулов из кэша
+pub(crate) fn ingest_pools(cache: &AccountCache, loaded: &HashMap<Pubkey, Arc<dyn PoolMints>>) -> Result<(), ArbError> {
+    let accounts = cache.read();
+    for pool in loaded.values() {
+        pool.ingest(&accounts)?;
+    }
+    info!("Loaded {} pools, {} accounts in cache", loaded.len(), accounts.len());
+    Ok(())
+}
+
+#[cfg(all(test, feature = "raydium"))]
+mod tests {
+    use super::*;
+    use crate::dex::raydium::constants::clmm_program_id;
+    use crate::test_utils::{clock_account, AmmFixture};
+    use crate::provider::{InMemoryAccountProvider, SlotAccounts};
+    use solana_sdk::account::Account;
+    use std::cell::RefCell;
+
+    /// Провайдер, считающий запросы getMultipleAccounts и размеры пачек
+    struct CountingProvider {
+        inner: InMemoryAccountProvider,
+        batches: RefCell<Vec<usize>>,
+    }
+
+    impl AccountProvider for CountingProvider {
+        fn get_account(&self, pubkey: &Pubkey) -> Result<Account, ArbError> {
+            self.inner.get_account(pubkey)
+        }
+
+        fn get_multiple_accounts(
+            &self,
+            pubkeys: &[Pubkey],
+            min_context_slot: Option<u64>,
+        ) -> Result<SlotAccounts, ArbError> {
+            self.batches.borrow_mut().push(pubkeys.len());
+            self.inner.get_multiple_accounts(pubkeys, min_context_slot)
+        }
+
+        fn get_slot(&self) -> Result<u64, ArbError> {
+            self.inner.get_slot()
+        }
+    }
+
+    #[test]
+    fn test_load_pools_in_chunked_batches() {
+        let mut inner = InMemoryAccountProvider::default();
+        inner.insert(clock::id(), clock_account());
+
+        // 150 AMM пулов: 150 аккаунтов пулов + 300 vault-аккаунтов
+        let mut requests = Vec::new();
+        for _ in 0..150 {
+            let amm = AmmFixture::new();
+            amm.insert(&mut inner, (0, 0));
+            requests.push((PoolKind::RaydiumAmm, amm.pool));
+        }
+        // Повтор пула не приводит к повторной загрузке
+        requests.push(requests[0]);
+
+        let provider = CountingProvider { inner, batches: RefCell::new(Vec::new()) };
+        let cache = AccountCache::new();
+        let loaded = load_pools(&provider, &cache, &requests).unwrap();
+
+        assert_eq!(loaded.len(), 150);
+        // Пулы + Clock: 100 + 51; зависимостей у AMM нет; vaults: 100 + 100 + 100
+        assert_eq!(*provider.batches.borrow(), vec![100, 51, 100, 100, 100]);
+
+        // Повторная загрузка тех же пулов берёт все аккаунты из кэша
+        provider.batches.borrow_mut().clear();
+        let reloaded = load_pools(&provider, &cache, &requests[..10]).unwrap();
+        assert_eq!(reloaded.len(), 10);
+        assert!(provider.batches.borrow().is_empty());
+    }
+
+    #[test]
+    fn test_load_pools_reports_missing_pool_account() {
+        let mut provider = InMemoryAccountProvider::default();
+        provider.insert(clock::id(), clock_account());
+        let missing = Pubkey::new_unique();
+
+        let result = load_pools(&provider, &AccountCache::new(), &[(PoolKind::Phoenix, missing)]);
+        assert!(matches!(result, Err(ArbError::AccountNotFound(key)) if key == missing));
+    }
+
+    #[test]
+    fn test_load_pools_by_address_detects_kind_and_rejects_unknown_owner() {
+        let mut provider = InMemoryAccountProvider::default();
+        provider.insert(clock::id(), clock_account());
+
+        let amm = AmmFixture::new();
+        let (pool, data) = (amm.pool, amm.pool_account().data);
+        provider.insert(pool, amm.pool_account());
+
+        let loaded = load_pools_by_address(&provider, &AccountCache::new(), &[pool]).unwrap();
+        assert_eq!(loaded[&pool].pool_pubkey(), &pool);
+
+        // Тот же пул в списке другого типа — ошибка, а не разбор по чужим смещениям
+        let result = load_pools(&provider, &AccountCache::new(), &[(PoolKind::RaydiumClmm, pool)]);
+        assert!(matches!(result, Err(ArbError::Config(_))));
+
+        let foreign = Pubkey::new_unique();
+        provider.insert(foreign, Account { data, owner: Pubkey::new_unique(), ..Account::default() });
+        let result = load_pools_by_address(&provider, &AccountCache::new(), &[foreign]);
+        assert!(matches!(result, Err(ArbError::Config(_))));
+    }
+
+    #[test]
+    fn test_load_pools_restores_static_accounts_from_metadata() {
+        let mut inner = InMemoryAccountProvider::default();
+        inner.insert(clock::id(), clock_account());
+
+        let (pool, mint_a, mint_b, amm_config) =
+            (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+        let mut data = vec![0u8; 1544];
+        data[9..41].copy_from_slice(amm_config.as_ref());
+        data[73..105].copy_from_slice(mint_a.as_ref());
+        data[105..137].copy_from_slice(mint_b.as_ref());
+        data[253..269].copy_from_slice(&(1u128 << 64).to_le_bytes());
+        let mut mint = vec![0u8; 82];
+        mint[44] = 6;
+        inner.insert(pool, Account { data: data.clone(), owner: clmm_program_id(), ..Account::default() });
+        inner.insert(mint_a, Account { data: mint.clone(), ..Account::default() });
+        inner.insert(mint_b, Account { data: mint, ..Account::default() });
+        inner.insert(amm_config, Account::default());
+
+        let provider = CountingProvider { inner, batches: RefCell::new(Vec::new()) };
+        let requests = [(PoolKind::RaydiumClmm, pool)];
+        let cache = AccountCache::new();
+        load_pools(&provider, &cache, &requests).unwrap();
+        // Пул + Clock, затем mints и AmmConfig; аккаунт состояния CLMM — сам пул
+        assert_eq!(*provider.batches.borrow(), vec![2, 3]);
+        let metadata = cache.metadata().clone();
+        assert_eq!(metadata.get(&pool).unwrap().accounts.len(), 3);
+
+        // После перезапуска статические зависимости берутся из метаданных
+        provider.batches.borrow_mut().clear();
+        let restarted = AccountCache::with_metadata(metadata.clone());
+        let loaded = load_pools(&provider, &restarted, &requests).unwrap();
+        assert_eq!(*provider.batches.borrow(), vec![2]);
+        assert_eq!(loaded[&pool].mint_a(), &mint_a);
+
+        // Аккаунт пула другой длины — запись устарела, зависимости запрашиваются заново
+        let mut provider = provider;
+        data.push(0);
+        provider.inner.insert(pool, Account { data, owner: clmm_program_id(), ..Account::default() });
+        provider.batches.borrow_mut().clear();
+        let resized = AccountCache::with_metadata(metadata);
+        load_pools(&provider, &resized, &requests).unwrap();
+        assert_eq!(*provider.batches.borrow(), vec![2, 3]);
+        assert_eq!(resized.metadata().get(&pool).unwrap().data_len, 1545);
+    }
+}
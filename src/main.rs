@@ -1,70 +1,134 @@
-use std::{thread::sleep, time::Duration};
+use std::io::Write;
+use std::ops::ControlFlow;
+use std::time::Instant;
 
-use solana_client::rpc_client::RpcClient;
 use solana_sdk::pubkey::Pubkey;
 
-mod common;
-mod dex;
-mod config;
-mod arb;
-
-use config::Config;
-use crate::arb::build_arbitrage_graph;
-use crate::dex::PoolMints;
-use std::collections::HashMap;
+use gpt_arb::config::Config;
+use gpt_arb::daemon::{run_daemon, CycleReport, Shutdown};
+use gpt_arb::geyser::run_geyser_stream;
+use gpt_arb::pipeline::{build_pool_registry_async, scan};
+use gpt_arb::rpc::RpcPool;
+use gpt_arb::stream::run_account_stream;
+use gpt_arb::prefilter::prefilter_cycles;
+use gpt_arb::{quote_cycles, AccountCache, CycleIndex, MetadataCache, OpportunityTracker, PoolMints};
 use std::sync::Arc;
 
-fn main() {
+#[tokio::main]
+async fn main() {
     env_logger::init();
 
+    // --rebuild-cache: не читать сохранённые метаданные пулов, собрать их заново
+    let rebuild_cache = std::env::args().skip(1).any(|arg| arg == "--rebuild-cache");
+
     let cfg = Config::from_file("config.toml").expect("Failed to read config");
     println!("{:#?}", cfg);
 
-    let rpc_url = "https://api.mainnet-beta.solana.com";
-    let client = RpcClient::new(rpc_url.to_string());
+    // Пул RPC endpoint'ов с лимитами, повторами и переключением при сбоях
+    let client = match RpcPool::new(&cfg.rpc) {
+        Ok(client) => Arc::new(client),
+        Err(e) => {
+            eprintln!("Error configuring RPC endpoints: {}", e);
+            return;
+        }
+    };
+    tokio::spawn({
+        let client = client.clone();
+        async move { client.run_health_checks().await }
+    });
+    // Общий кэш аккаунтов: все пулы читают из него одну версию данных.
+    // Статические зависимости пулов берутся из кэша метаданных прошлого запуска
+    let metadata = if rebuild_cache { MetadataCache::new() } else { MetadataCache::load(&cfg.metadata_cache) };
+    let cache = AccountCache::with_metadata(metadata);
 
-    let pools_map: HashMap<Pubkey, Vec<Arc<dyn PoolMints>>>;
-    // Строим HashMap пулов по mint-адресам
-    match cfg.build_pools_hashmap(&client) {
-        Ok(_pools_map) => {
-            println!("Built pools hashmap with {} mint entries", _pools_map.len());
-            pools_map = _pools_map;
+    // Строим реестр пулов, индексированный по обоим mint
+    let registry = match build_pool_registry_async(&cfg, client.as_ref(), &cache, &cfg.pipeline).await {
+        Ok(registry) => {
+            println!("Built pool registry with {} pools over {} mints", registry.len(), registry.mint_count());
+            if let Err(e) = cache.save_metadata(&cfg.metadata_cache) {
+                eprintln!("Error saving pool metadata: {}", e);
+            }
+            registry
         }
         Err(e) => {
-            println!("Error building pools hashmap: {}", e);
+            println!("Error building pool registry: {}", e);
             return;
         }
-    }
+    };
 
     // Хардкодные значения для построения графа арбитража
     let start_mint: Pubkey = "So11111111111111111111111111111111111111112".parse().expect("Invalid start_mint");
     let start_amount: u64 = 1_000_000_000; // 1 SOL (9 decimals)
 
-    // Построение графа арбитража
-    match build_arbitrage_graph(&start_mint, start_amount, &pools_map, &client) {
-        Ok(chains) => {
-            println!("Found {} arbitrage chains", chains.len());
+    // SIGINT/SIGTERM: начатая работа доводится до конца, затем процесс завершается
+    let shutdown = Shutdown::new();
+    shutdown.listen_for_signals();
+
+    if cfg.geyser.enabled || cfg.stream.enabled {
+        // Циклы перечисляются один раз без префильтра: он зависит от состояния пулов,
+        // а на обновлениях циклы только перекотируются
+        let cycles = CycleIndex::with_max_depth(&start_mint, &registry, cfg.max_depth);
+        println!("Indexed {} candidate cycles", cycles.len());
+
+        // После изменения состояния перекотируются только циклы обновлённых пулов.
+        // Подписки держат все пулы актуальными, поэтому разброс слотов не проверяется
+        let mut tracker = OpportunityTracker::new(cycles, start_amount, None);
+        let on_update = |updated: &[Arc<dyn PoolMints>]| {
+            let started = Instant::now();
+            let changes = tracker.update(&registry, updated);
+            for change in &changes {
+                println!("{}", change);
+            }
+            println!(
+                "{} pools updated, {} opportunities changed, {} open in {:?}",
+                updated.len(),
+                changes.len(),
+                tracker.len(),
+                started.elapsed()
+            );
+            if shutdown.is_requested() { ControlFlow::Break(()) } else { ControlFlow::Continue(()) }
+        };
+        let stream = async {
+            if cfg.geyser.enabled {
+                run_geyser_stream(&cfg.geyser, &registry, &cache, client.as_ref(), &cfg.pipeline, on_update).await
+            } else {
+                run_account_stream(&cfg.stream, &registry, &cache, client.as_ref(), &cfg.pipeline, on_update).await
+            }
+        };
+        // Ожидание уведомлений прерывается сигналом; `on_update` синхронен и доводится до конца
+        let result = tokio::select! {
+            result = stream => result,
+            _ = shutdown.wait() => Ok(()),
+        };
+        if let Err(e) = result {
+            eprintln!("Account stream stopped: {}", e);
         }
-        Err(e) => {
-            eprintln!("Error building arbitrage graph: {}", e);
+    } else if cfg.daemon.enabled {
+        // Циклы обновление → поиск → отчёт с периодом из конфига. Топология циклов
+        // перечисляется один раз, каждый поиск отбирает из неё кандидатов логарифмическим
+        // префильтром по свежему состоянию
+        let cycles = CycleIndex::with_max_depth(&start_mint, &registry, cfg.max_depth);
+        let search = || {
+            let candidates = prefilter_cycles(&cycles, start_amount, &registry);
+            quote_cycles(&candidates, start_amount, &registry, cfg.max_slot_spread)
+        };
+        let on_cycle = |report: &CycleReport| println!("{}", report);
+        if let Err(e) = run_daemon(&cfg.daemon, &registry, &cache, client.as_ref(), &cfg.pipeline, &shutdown, search, on_cycle).await {
+            eprintln!("Daemon stopped: {}", e);
         }
-    }
-
-    //loop {
-        /*for pool_config in &cfg.pools {
-            // Обрабатываем AMM пулы
-            for amm_address in &pool_config.raydium_amm {
-                let pubkey: Pubkey = amm_address.parse().unwrap();
-                let _ = get_pool_price(&client, &pubkey);
+    } else {
+        // Параллельное обновление пулов и построение графа арбитража по свежему снапшоту
+        match scan(&registry, client.as_ref(), &cache, &start_mint, start_amount, cfg.max_slot_spread, cfg.max_depth, &cfg.pipeline).await {
+            Ok(chains) => {
+                println!("Found {} arbitrage chains", chains.len());
             }
-            
-            // Обрабатываем CLMM пулы
-            for clmm_address in &pool_config.raydium_clmm {
-                let pubkey: Pubkey = clmm_address.parse().unwrap();
-                let _ = get_pool_price(&client, &pubkey);
+            Err(e) => {
+                eprintln!("Error building arbitrage graph: {}", e);
             }
         }
+    }
 
-        sleep(Duration::from_secs(1)); */
-    //}
+    // Перед выходом сбрасываем буферы лога и stdout
+    log::logger().flush();
+    let _ = std::io::stdout().flush();
 }
@@ -0,0 +1,242 @@
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use log::warn;
+use serde::{Deserialize, Serialize};
+use solana_sdk::account::Account;
+use solana_sdk::pubkey::Pubkey;
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+
+use crate::error::ArbError;
+use crate::loader::PoolKind;
+
+/// Версия формата файла: файл другой версии игнорируется и пересобирается
+pub const METADATA_VERSION: u32 = 1;
+/// Файл кэша метаданных по умолчанию
+pub const DEFAULT_METADATA_PATH: &str = "pool_metadata.json";
+
+/// Статические данные одного пула: его тип, владелец и длина данных аккаунта пула
+/// на момент сохранения и статические зависимости (mints, AmmConfig, PoolConfig).
+#[derive(Clone, Debug, PartialEq)]
+pub struct PoolMetadata {
+    pub kind: PoolKind,
+    pub owner: Pubkey,
+    pub data_len: usize,
+    pub accounts: Vec<(Pubkey, Account)>,
+}
+
+/// Персистентный кэш статических метаданных пулов.
+///
+/// После перезапуска запись пула используется, только если свежий аккаунт пула совпадает
+/// с сохранённым по типу, владельцу и длине данных; иначе зависимости запрашиваются заново.
+/// Изменение статических аккаунтов без смены аккаунта пула (например, комиссии AmmConfig)
+/// не отслеживается — для этого кэш пересобирается флагом `--rebuild-cache`.
+#[derive(Clone, Debug, Default)]
+pub struct MetadataCache {
+    pools: HashMap<Pubkey, PoolMetadata>,
+}
+
+impl MetadataCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Прочитать кэш из файла.
+    ///
+    /// Отсутствующий, повреждённый или устаревший по версии файл даёт пустой кэш:
+    /// метаданные просто будут собраны заново.
+    pub fn load<P: AsRef<Path>>(path: P) -> Self {
+        let path = path.as_ref();
+        let content = match fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Self::default(),
+            Err(e) => {
+                warn!("Failed to read pool metadata {}: {}", path.display(), e);
+                return Self::default();
+            }
+        };
+
+        match parse_metadata(&content) {
+            Ok(cache) => cache,
+            Err(e) => {
+                warn!("Ignoring pool metadata {}: {}", path.display(), e);
+                Self::default()
+            }
+        }
+    }
+
+    /// Записать кэш в файл через временный файл, чтобы прерванная запись не портила прежний
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), ArbError> {
+        let path = path.as_ref();
+        let pools = self
+            .pools
+            .iter()
+            .map(|(pubkey, metadata)| (pubkey.to_string(), MetadataEntry::from(metadata)))
+            .collect();
+        let file = MetadataFile { version: METADATA_VERSION, pools };
+        let content = serde_json::to_string_pretty(&file).map_err(|e| ArbError::Config(e.to_string()))?;
+
+        let tmp = path.with_extension("tmp");
+        fs::write(&tmp, content)?;
+        fs::rename(&tmp, path)?;
+        Ok(())
+    }
+
+    pub fn get(&self, pool: &Pubkey) -> Option<&PoolMetadata> {
+        self.pools.get(pool)
+    }
+
+    /// Статические аккаунты пула, если запись соответствует свежему аккаунту пула
+    pub fn validated(&self, kind: PoolKind, pool: &Pubkey, account: &Account) -> Option<&[(Pubkey, Account)]> {
+        self.pools
+            .get(pool)
+            .filter(|metadata| {
+                metadata.kind == kind && metadata.owner == account.owner && metadata.data_len == account.data.len()
+            })
+            .map(|metadata| metadata.accounts.as_slice())
+    }
+
+    pub fn insert(&mut self, pool: Pubkey, metadata: PoolMetadata) {
+        self.pools.insert(pool, metadata);
+    }
+
+    pub fn remove(&mut self, pool: &Pubkey) -> Option<PoolMetadata> {
+        self.pools.remove(pool)
+    }
+
+    pub fn len(&self) -> usize {
+        self.pools.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pools.is_empty()
+    }
+}
+
+/*
+Формат файла — JSON, ключи и владельцы в base58, данные аккаунтов в base64:
+  {"version": 1, "pools": {"<pool>": {"kind": "RaydiumClmm", "owner": "...", "data_len": 1544,
+   "accounts": [{"pubkey": "...", "owner": "...", "lamports": 0, "data": "<base64>"}]}}}
+*/
+
+#[derive(Serialize, Deserialize)]
+struct MetadataFile {
+    version: u32,
+    pools: BTreeMap<String, MetadataEntry>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct MetadataEntry {
+    kind: PoolKind,
+    owner: String,
+    data_len: usize,
+    accounts: Vec<MetadataAccount>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct MetadataAccount {
+    pubkey: String,
+    owner: String,
+    lamports: u64,
+    data: String,
+}
+
+impl From<&PoolMetadata> for MetadataEntry {
+    fn from(metadata: &PoolMetadata) -> Self {
+        Self {
+            kind: metadata.kind,
+            owner: metadata.owner.to_string(),
+            data_len: metadata.data_len,
+            accounts: metadata
+                .accounts
+                .iter()
+                .map(|(pubkey, account)| MetadataAccount {
+                    pubkey: pubkey.to_string(),
+                    owner: account.owner.to_string(),
+                    lamports: account.lamports,
+                    data: BASE64.encode(&account.data),
+                })
+                .collect(),
+        }
+    }
+}
+
+// Разбор файла кэша; ошибка любой записи отбрасывает весь файл
+fn parse_metadata(content: &str) -> Result<MetadataCache, String> {
+    let file: MetadataFile = serde_json::from_str(content).map_err(|e| e.to_string())?;
+    if file.version != METADATA_VERSION {
+        return Err(format!("unsupported version {}", file.version));
+    }
+
+    let mut cache = MetadataCache::default();
+    for (pool, entry) in file.pools {
+        let accounts = entry
+            .accounts
+            .into_iter()
+            .map(|account| {
+                Ok((
+                    Pubkey::from_str(&account.pubkey).map_err(|e| e.to_string())?,
+                    Account {
+                        lamports: account.lamports,
+                        data: BASE64.decode(account.data).map_err(|e| e.to_string())?,
+                        owner: Pubkey::from_str(&account.owner).map_err(|e| e.to_string())?,
+                        ..Account::default()
+                    },
+                ))
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+
+        cache.insert(
+            Pubkey::from_str(&pool).map_err(|e| e.to_string())?,
+            PoolMetadata {
+                kind: entry.kind,
+                owner: Pubkey::from_str(&entry.owner).map_err(|e| e.to_string())?,
+                data_len: entry.data_len,
+                accounts,
+            },
+        );
+    }
+
+    Ok(cache)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_metadata_round_trip_and_validation() {
+        let (pool, mint, owner) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+        let mint_account = Account { lamports: 7, data: vec![1, 2, 3], owner: Pubkey::new_unique(), ..Account::default() };
+        let mut cache = MetadataCache::new();
+        cache.insert(pool, PoolMetadata {
+            kind: PoolKind::MeteoraDlmm,
+            owner,
+            data_len: 904,
+            accounts: vec![(mint, mint_account.clone())],
+        });
+
+        let path = std::env::temp_dir().join(format!("pool_metadata_{}.json", Pubkey::new_unique()));
+        cache.save(&path).unwrap();
+        let restored = MetadataCache::load(&path);
+        fs::remove_file(&path).unwrap();
+        assert_eq!(restored.get(&pool), cache.get(&pool));
+
+        let account = Account { data: vec![0u8; 904], owner, ..Account::default() };
+        assert_eq!(restored.validated(PoolKind::MeteoraDlmm, &pool, &account), Some(&[(mint, mint_account)][..]));
+        // Другой тип, владелец или длина данных — запись не используется
+        assert!(restored.validated(PoolKind::MeteoraDbc, &pool, &account).is_none());
+        let resized = Account { data: vec![0u8; 905], ..account.clone() };
+        assert!(restored.validated(PoolKind::MeteoraDlmm, &pool, &resized).is_none());
+        let foreign = Account { owner: Pubkey::new_unique(), ..account };
+        assert!(restored.validated(PoolKind::MeteoraDlmm, &pool, &foreign).is_none());
+
+        // Отсутствующий и повреждённый файлы дают пустой кэш
+        assert!(MetadataCache::load(&path).is_empty());
+        fs::write(&path, "{\"version\": 0, \"pools\": {}}").unwrap();
+        assert!(MetadataCache::load(&path).is_empty());
+        fs::remove_file(&path).unwrap();
+    }
+}
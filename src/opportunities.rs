@@ -0,0 +1,197 @@
+use log::debug;
+use rayon::prelude::*;
+use std::fmt;
+use std::sync::Arc;
+
+use crate::arb::{Branch, Quote};
+use crate::cycles::CycleIndex;
+use crate::dex::PoolMints;
+use crate::pool_registry::PoolRegistry;
+
+/// Изменение набора арбитражных возможностей после обновления пулов
+#[derive(Clone)]
+pub enum OpportunityChange {
+    /// Цикл стал прибыльным
+    Appeared { cycle: usize, chain: Vec<Arc<dyn PoolMints>>, amount_out: u64 },
+    /// Цикл перестал быть прибыльным
+    Disappeared { cycle: usize, chain: Vec<Arc<dyn PoolMints>> },
+}
+
+impl OpportunityChange {
+    pub fn cycle(&self) -> usize {
+        match self {
+            Self::Appeared { cycle, .. } | Self::Disappeared { cycle, .. } => *cycle,
+        }
+    }
+
+    pub fn chain(&self) -> &[Arc<dyn PoolMints>] {
+        match self {
+            Self::Appeared { chain, .. } | Self::Disappeared { chain, .. } => chain,
+        }
+    }
+}
+
+impl fmt::Display for OpportunityChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Appeared { cycle, amount_out, .. } => write!(f, "+ cycle {} (amount_out {}):", cycle, amount_out)?,
+            Self::Disappeared { cycle, .. } => write!(f, "- cycle {}:", cycle)?,
+        }
+        for pool in self.chain() {
+            write!(f, " {}", pool.pool_pubkey())?;
+        }
+        Ok(())
+    }
+}
+
+/// Текущие арбитражные возможности по предвычисленным циклам.
+///
+/// Изменение состояния пула влияет только на циклы, проходящие через него, поэтому после
+/// обновления перекотируются лишь они (обратный индекс [`CycleIndex::cycles_with_pool`]),
+/// а наружу отдаются появившиеся и исчезнувшие возможности. Индекс должен быть построен
+/// по текущему набору пулов реестра.
+///
+/// В потоковых режимах `max_slot_spread` — `None`: подписки живые, и пул без уведомлений
+/// не изменился с последней пересинхронизации, хотя его слот состояния отстаёт от соседей.
+pub struct OpportunityTracker {
+    index: CycleIndex,
+    start_amount: u64,
+    max_slot_spread: Option<u64>,
+    /// Итоговая сумма каждого цикла, прибыльного на последней котировке
+    amounts: Vec<Option<u64>>,
+}
+
+impl OpportunityTracker {
+    /// Трекер без известных возможностей; первое обновление всеми пулами котирует все циклы
+    pub fn new(index: CycleIndex, start_amount: u64, max_slot_spread: Option<u64>) -> Self {
+        let amounts = vec![None; index.len()];
+        Self { index, start_amount, max_slot_spread, amounts }
+    }
+
+    pub fn index(&self) -> &CycleIndex {
+        &self.index
+    }
+
+    /// Прибыльные циклы: номер и итоговая сумма
+    pub fn opportunities(&self) -> impl Iterator<Item = (usize, u64)> + '_ {
+        self.amounts.iter().enumerate().filter_map(|(cycle, amount)| amount.map(|amount| (cycle, amount)))
+    }
+
+    /// Число прибыльных циклов
+    pub fn len(&self) -> usize {
+        self.amounts.iter().filter(|amount| amount.is_some()).count()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.amounts.iter().all(Option::is_none)
+    }
+
+    /// Перекотировать циклы, проходящие через обновлённые пулы; изменения в порядке номеров циклов
+    pub fn update(&mut self, registry: &PoolRegistry, updated: &[Arc<dyn PoolMints>]) -> Vec<OpportunityChange> {
+        let affected = self.index.affected(updated);
+        if affected.is_empty() {
+            return Vec::new();
+        }
+
+        let index = &self.index;
+        let quote = Quote { index, start_amount: self.start_amount, registry, max_slot_spread: self.max_slot_spread };
+        // Затронутые циклы с общим первым пулом делят префиксы и котируются вместе, группы — параллельно
+        let groups: Vec<&[usize]> = affected.chunk_by(|a, b| index.cycle(*a)[0] == index.cycle(*b)[0]).collect();
+        let branch = Branch::merge(groups.into_par_iter().map(|group| quote.cycles(group.iter().copied())).collect());
+        debug!("Re-quoted {} of {} cycles after {} pool updates", affected.len(), index.len(), updated.len());
+        if !branch.skipped.is_empty() {
+            debug!("Skipped pool quotes by reason: {:?}", branch.skipped);
+        }
+
+        let mut profitable = branch.profitable.into_iter().peekable();
+        let mut changes = Vec::new();
+        for cycle in affected {
+            let amount = profitable.next_if(|(i, _)| *i == cycle).map(|(_, amount)| amount);
+            match (self.amounts[cycle], amount) {
+                (None, Some(amount_out)) => {
+                    changes.push(OpportunityChange::Appeared { cycle, chain: quote.chain(cycle), amount_out })
+                }
+                (Some(_), None) => changes.push(OpportunityChange::Disappeared { cycle, chain: quote.chain(cycle) }),
+                _ => {}
+            }
+            self.amounts[cycle] = amount;
+        }
+        changes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::arb::quote_cycles;
+    use crate::test_utils::{as_dyn, chain_keys, pair_pools, stub_registry};
+    use solana_sdk::pubkey::Pubkey;
+
+    #[test]
+    fn test_tracker_requotes_only_cycles_of_updated_pools() {
+        // По два пула на каждую пару из трёх mint
+        let mints: Vec<Pubkey> = (0..3).map(|_| Pubkey::new_unique()).collect();
+        let pools = pair_pools(&mints, 2);
+        let all = as_dyn(&pools);
+        let registry = stub_registry(&pools);
+        let mut tracker = OpportunityTracker::new(CycleIndex::build(&mints[0], &registry), 1_000, Some(2));
+
+        // Курс 1:1 — возможностей нет
+        assert!(tracker.update(&registry, &all).is_empty());
+        assert!(tracker.is_empty());
+
+        // Рост курса пула 0 открывает циклы через него — те же, что находит полная котировка
+        pools[0].set_rate(10_100);
+        let changes = tracker.update(&registry, &all[..1]);
+        assert!(changes.iter().all(|change| matches!(change, OpportunityChange::Appeared { amount_out, .. } if *amount_out > 1_000)));
+        let full = quote_cycles(tracker.index(), 1_000, &registry, 2).unwrap();
+        let appeared: Vec<Vec<Arc<dyn PoolMints>>> = changes.iter().map(|change| change.chain().to_vec()).collect();
+        assert_eq!(chain_keys(&appeared), chain_keys(&full));
+        assert_eq!(tracker.len(), changes.len());
+
+        // Пул 1 изменился, но обновление пришло только для пула 5: перекотированы лишь циклы через пул 5
+        pools[1].set_rate(10_100);
+        let requoted = tracker.index().cycles_with_pool(all[5].pool_pubkey()).to_vec();
+        let changes = tracker.update(&registry, &all[5..]);
+        assert!(!changes.is_empty());
+        assert!(changes.iter().all(|change| requoted.contains(&(change.cycle() as u32))));
+        assert!(tracker.len() < quote_cycles(tracker.index(), 1_000, &registry, 2).unwrap().len());
+
+        // Возврат курсов закрывает все возможности
+        pools[0].set_rate(10_000);
+        pools[1].set_rate(10_000);
+        let open = tracker.len();
+        let changes = tracker.update(&registry, &all[..2]);
+        assert_eq!(changes.len(), open);
+        assert!(changes.iter().all(|change| matches!(change, OpportunityChange::Disappeared { .. })));
+        assert!(tracker.is_empty());
+        assert!(tracker.update(&registry, &all[2..]).is_empty());
+    }
+
+    #[test]
+    fn test_tracker_keeps_cycles_open_while_other_pools_stay_quiet() {
+        // Все пулы пересинхронизированы в слоте 100, затем приходят уведомления только для пула 0
+        let mints: Vec<Pubkey> = (0..3).map(|_| Pubkey::new_unique()).collect();
+        let pools = pair_pools(&mints, 1);
+        let all = as_dyn(&pools);
+        let registry = stub_registry(&pools);
+        let mut tracker = OpportunityTracker::new(CycleIndex::build(&mints[0], &registry), 1_000, None);
+
+        pools[0].set_rate(10_100);
+        let opened = tracker.update(&registry, &all);
+        assert!(!opened.is_empty());
+        let open = tracker.len();
+
+        // Курс пула 0 меняется, но остаётся прибыльным; остальные пулы не менялись с пересинхронизации
+        for slot in 101..=110 {
+            pools[0].set_slot(slot);
+            pools[0].set_rate(10_100 + slot);
+            assert!(tracker.update(&registry, &all[..1]).is_empty());
+        }
+        assert_eq!(tracker.len(), open);
+
+        // При проверке разброса слотов те же циклы закрылись бы
+        let mut bounded = OpportunityTracker::new(tracker.index().clone(), 1_000, Some(2));
+        assert!(bounded.update(&registry, &all).is_empty());
+    }
+}
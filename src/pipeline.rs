@@ -0,0 +1,396 @@
+use futures::stream::{self, StreamExt};
+use log::{debug, warn};
+use serde::Deserialize;
+use solana_account_decoder_client_types::UiAccountEncoding;
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_client::rpc_config::{RpcAccountInfoConfig, RpcTransactionConfig};
+use solana_commitment_config::CommitmentConfig;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::Signature;
+use solana_transaction_status_client_types::UiTransactionEncoding;
+use std::collections::{HashMap, HashSet};
+use std::future::Future;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::time::timeout;
+
+use crate::arb::build_arbitrage_graph;
+use crate::cache::AccountCache;
+use crate::config::{assemble_registry, Config};
+use crate::dex::PoolMints;
+use crate::error::ArbError;
+use crate::events::{inner_instructions, InnerInstruction};
+use crate::loader::{
+    address_keys, build_pools, dependency_keys, ingest_pools, pool_keys, record_metadata, resolve_kinds,
+    restore_metadata, state_keys, unique_requests, PoolKind,
+};
+use crate::pool_registry::PoolRegistry;
+use crate::provider::{
+    AccountProvider, AccountSnapshot, FixtureAccountProvider, InMemoryAccountProvider, SlotAccounts,
+    MAX_MULTIPLE_ACCOUNTS,
+};
+
+/// Асинхронный источник данных аккаунтов — аналог `AccountProvider` для tokio
+pub trait AsyncAccountProvider {
+    /// Получить несколько аккаунтов; порядок результата совпадает с порядком ключей
+    fn get_multiple_accounts(
+        &self,
+        pubkeys: &[Pubkey],
+        min_context_slot: Option<u64>,
+    ) -> impl Future<Output = Result<SlotAccounts, ArbError>>;
+}
+
+impl AsyncAccountProvider for RpcClient {
+    async fn get_multiple_accounts(
+        &self,
+        pubkeys: &[Pubkey],
+        min_context_slot: Option<u64>,
+    ) -> Result<SlotAccounts, ArbError> {
+        let config = RpcAccountInfoConfig {
+            encoding: Some(UiAccountEncoding::Base64Zstd),
+            commitment: Some(self.commitment()),
+            data_slice: None,
+            min_context_slot,
+        };
+        let response = self.get_multiple_accounts_with_config(pubkeys, config).await?;
+        Ok(SlotAccounts { context_slot: response.context.slot, accounts: response.value })
+    }
+}
+
+impl AsyncAccountProvider for InMemoryAccountProvider {
+    async fn get_multiple_accounts(
+        &self,
+        pubkeys: &[Pubkey],
+        min_context_slot: Option<u64>,
+    ) -> Result<SlotAccounts, ArbError> {
+        AccountProvider::get_multiple_accounts(self, pubkeys, min_context_slot)
+    }
+}
+
+/// Асинхронный источник подтверждённых транзакций: события event CPI (`emit_cpi!`) есть только
+/// во внутренних инструкциях транзакции, но не в её логах
+pub trait AsyncTransactionProvider {
+    /// Внутренние инструкции транзакции по подписи
+    fn get_inner_instructions(&self, signature: &str) -> impl Future<Output = Result<Vec<InnerInstruction>, ArbError>>;
+}
+
+impl AsyncTransactionProvider for RpcClient {
+    async fn get_inner_instructions(&self, signature: &str) -> Result<Vec<InnerInstruction>, ArbError> {
+        let signature: Signature =
+            signature.parse().map_err(|_| ArbError::Decode(format!("invalid transaction signature {}", signature)))?;
+        let config = RpcTransactionConfig {
+            encoding: Some(UiTransactionEncoding::Json),
+            // getTransaction не поддерживает processed
+            commitment: Some(CommitmentConfig::confirmed()),
+            max_supported_transaction_version: Some(0),
+        };
+        inner_instructions(&self.get_transaction_with_config(&signature, config).await?)
+    }
+}
+
+impl AsyncTransactionProvider for InMemoryAccountProvider {
+    async fn get_inner_instructions(&self, signature: &str) -> Result<Vec<InnerInstruction>, ArbError> {
+        self.inner_instructions(signature)
+            .map(<[InnerInstruction]>::to_vec)
+            .ok_or_else(|| ArbError::Stream(format!("transaction {} not found", signature)))
+    }
+}
+
+impl AsyncAccountProvider for FixtureAccountProvider {
+    async fn get_multiple_accounts(
+        &self,
+        pubkeys: &[Pubkey],
+        min_context_slot: Option<u64>,
+    ) -> Result<SlotAccounts, ArbError> {
+        AccountProvider::get_multiple_accounts(self, pubkeys, min_context_slot)
+    }
+}
+
+/// Число одновременных запросов getMultipleAccounts по умолчанию
+pub const DEFAULT_MAX_CONCURRENCY: usize = 16;
+/// Таймаут одного запроса по умолчанию, мс
+pub const DEFAULT_REQUEST_TIMEOUT_MS: u64 = 5_000;
+
+/// Параметры async-пайплайна (секция `[pipeline]` конфига)
+#[derive(Clone, Copy, Debug, Deserialize)]
+#[serde(default)]
+pub struct PipelineConfig {
+    /// Сколько пачек getMultipleAccounts выполняется одновременно
+    pub max_concurrency: usize,
+    /// Таймаут одного запроса, мс
+    pub request_timeout_ms: u64,
+}
+
+impl Default for PipelineConfig {
+    fn default() -> Self {
+        Self {
+            max_concurrency: DEFAULT_MAX_CONCURRENCY,
+            request_timeout_ms: DEFAULT_REQUEST_TIMEOUT_MS,
+        }
+    }
+}
+
+impl PipelineConfig {
+    pub fn request_timeout(&self) -> Duration {
+        Duration::from_millis(self.request_timeout_ms)
+    }
+}
+
+/// Конкурентная загрузка пачками по MAX_MULTIPLE_ACCOUNTS.
+///
+/// Одновременно выполняется не более `max_concurrency` запросов, каждый ограничен таймаутом.
+/// Возвращает снапшот по успешным пачкам и ошибки остальных.
+async fn fetch_chunks<P: AsyncAccountProvider>(
+    provider: &P,
+    pubkeys: &[Pubkey],
+    min_context_slot: Option<u64>,
+    config: &PipelineConfig,
+) -> (AccountSnapshot, Vec<ArbError>) {
+    let mut seen: HashSet<Pubkey> = HashSet::with_capacity(pubkeys.len());
+    let unique: Vec<Pubkey> = pubkeys.iter().copied().filter(|pubkey| seen.insert(*pubkey)).collect();
+
+    let responses: Vec<(&[Pubkey], Result<SlotAccounts, ArbError>)> = stream::iter(unique.chunks(MAX_MULTIPLE_ACCOUNTS))
+        .map(|chunk| async move {
+            let request = provider.get_multiple_accounts(chunk, min_context_slot);
+            let result = match timeout(config.request_timeout(), request).await {
+                Ok(result) => result,
+                Err(_) => Err(ArbError::Timeout(format!(
+                    "getMultipleAccounts for {} accounts took longer than {:?}",
+                    chunk.len(),
+                    config.request_timeout()
+                ))),
+            };
+            (chunk, result)
+        })
+        .buffer_unordered(config.max_concurrency.max(1))
+        .collect()
+        .await;
+
+    let mut snapshot = AccountSnapshot::with_capacity(unique.len());
+    let mut errors = Vec::new();
+    for (chunk, result) in responses {
+        match result {
+            Ok(response) => {
+                for (pubkey, account) in chunk.iter().zip(response.accounts) {
+                    if let Some(account) = account {
+                        snapshot.insert(*pubkey, account, response.context_slot);
+                    }
+                }
+            }
+            Err(e) => errors.push(e),
+        }
+    }
+
+    (snapshot, errors)
+}
+
+/// Конкурентно получить снапшот; ошибка любой пачки — ошибка всей загрузки
+pub async fn fetch_snapshot_async<P: AsyncAccountProvider>(
+    provider: &P,
+    pubkeys: &[Pubkey],
+    min_context_slot: Option<u64>,
+    config: &PipelineConfig,
+) -> Result<AccountSnapshot, ArbError> {
+    let (snapshot, errors) = fetch_chunks(provider, pubkeys, min_context_slot, config).await;
+    match errors.into_iter().next() {
+        Some(e) => Err(e),
+        None => Ok(snapshot),
+    }
+}
+
+/// Догрузить в кэш аккаунты, которых в нём ещё нет
+pub async fn load_missing_async<P: AsyncAccountProvider>(
+    provider: &P,
+    cache: &AccountCache,
+    pubkeys: &[Pubkey],
+    config: &PipelineConfig,
+) -> Result<(), ArbError> {
+    let missing = cache.missing(pubkeys);
+    if missing.is_empty() {
+        return Ok(());
+    }
+
+    cache.update(fetch_snapshot_async(provider, &missing, cache.max_slot(), config).await?);
+    Ok(())
+}
+
+/// Async-вариант `load_pools`: те же три прохода, пачки каждого прохода идут параллельно
+pub async fn load_pools_async<P: AsyncAccountProvider>(
+    provider: &P,
+    cache: &AccountCache,
+    pools: &[(PoolKind, Pubkey)],
+    config: &PipelineConfig,
+) -> Result<HashMap<Pubkey, Arc<dyn PoolMints>>, ArbError> {
+    let pools = unique_requests(pools);
+
+    load_missing_async(provider, cache, &pool_keys(&pools), config).await?;
+    restore_metadata(cache, &pools);
+    load_missing_async(provider, cache, &dependency_keys(cache, &pools)?, config).await?;
+    let loaded = build_pools(cache, &pools)?;
+    record_metadata(cache, &pools, &loaded)?;
+    load_missing_async(provider, cache, &state_keys(&loaded), config).await?;
+    ingest_pools(cache, &loaded)?;
+
+    Ok(loaded)
+}
+
+/// Async-вариант `load_pools_by_address`
+pub async fn load_pools_by_address_async<P: AsyncAccountProvider>(
+    provider: &P,
+    cache: &AccountCache,
+    addresses: &[Pubkey],
+    config: &PipelineConfig,
+) -> Result<HashMap<Pubkey, Arc<dyn PoolMints>>, ArbError> {
+    load_missing_async(provider, cache, &address_keys(addresses), config).await?;
+    load_pools_async(provider, cache, &resolve_kinds(cache, addresses)?, config).await
+}
+
+/// Async-вариант `Config::build_pool_registry`
+pub async fn build_pool_registry_async<P: AsyncAccountProvider>(
+    cfg: &Config,
+    provider: &P,
+    cache: &AccountCache,
+    config: &PipelineConfig,
+) -> Result<PoolRegistry, ArbError> {
+    let entries = cfg.typed_pools()?;
+    let requests: Vec<(PoolKind, Pubkey)> = entries.iter().map(|(_, kind, pubkey)| (*kind, *pubkey)).collect();
+    let loaded = load_pools_async(provider, cache, &requests, config).await?;
+    let detected = load_pools_by_address_async(provider, cache, &cfg.parsed_pool_addresses()?, config).await?;
+
+    Ok(assemble_registry(&entries, loaded, detected))
+}
+
+/// Параллельно обновить аккаунты состояния всех пулов и сами пулы.
+///
+/// Пачки, не уложившиеся в таймаут или завершившиеся ошибкой, пропускаются с предупреждением:
+/// их пулы сохраняют прежнее состояние и прежний слот, а поиск отсечёт их по разбросу слотов.
+/// Так же пропускаются пулы, чьё состояние не удалось разобрать. Возвращает число пропущенных пачек.
+pub async fn refresh_pools_async<P: AsyncAccountProvider>(
+    registry: &PoolRegistry,
+    provider: &P,
+    cache: &AccountCache,
+    config: &PipelineConfig,
+) -> Result<usize, ArbError> {
+    let keys: Vec<Pubkey> = registry.pools().flat_map(|pool| pool.required_accounts()).collect();
+    let min_context_slot = cache.read().max_slot_of(&keys);
+
+    let (snapshot, errors) = fetch_chunks(provider, &keys, min_context_slot, config).await;
+    for e in &errors {
+        warn!("Skipping account batch during refresh: {}", e);
+    }
+    cache.update(snapshot);
+
+    // Один пул с повреждённым аккаунтом не должен останавливать обновление остальных
+    let accounts = cache.read();
+    let mut failed: HashMap<&'static str, usize> = HashMap::new();
+    for pool in registry.pools() {
+        if let Err(e) = pool.ingest(&accounts) {
+            debug!("Skipping pool {} during refresh: {}", pool.pool_pubkey(), e);
+            *failed.entry(e.reason()).or_insert(0) += 1;
+        }
+    }
+    if !failed.is_empty() {
+        warn!("Skipped pool updates by reason: {:?}", failed);
+    }
+
+    Ok(errors.len())
+}
+
+/// Один проход сканирования: параллельное обновление пулов и поиск цепочек по свежему снапшоту
+#[allow(clippy::too_many_arguments)]
+pub async fn scan<P: AsyncAccountProvider>(
+    registry: &PoolRegistry,
+    provider: &P,
+    cache: &AccountCache,
+    start_mint: &Pubkey,
+    start_amount: u64,
+    max_slot_spread: u64,
+    max_depth: usize,
+    config: &PipelineConfig,
+) -> Result<Vec<Vec<Arc<dyn PoolMints>>>, ArbError> {
+    let skipped_batches = refresh_pools_async(registry, provider, cache, config).await?;
+    if skipped_batches > 0 {
+        warn!("{} account batches were skipped, affected pools keep their previous state", skipped_batches);
+    }
+
+    build_arbitrage_graph(start_mint, start_amount, registry, max_slot_spread, max_depth)
+}
+
+#[cfg(all(test, feature = "raydium"))]
+mod tests {
+    use super::*;
+    use crate::test_utils::{clock_account, AmmFixture};
+    use solana_sdk::account::Account;
+    use solana_sdk::sysvar::clock;
+
+    /// Провайдер, не отвечающий на запросы с указанными ключами дольше таймаута
+    struct SlowProvider {
+        inner: InMemoryAccountProvider,
+        slow: HashSet<Pubkey>,
+    }
+
+    impl AsyncAccountProvider for SlowProvider {
+        async fn get_multiple_accounts(
+            &self,
+            pubkeys: &[Pubkey],
+            min_context_slot: Option<u64>,
+        ) -> Result<SlotAccounts, ArbError> {
+            if pubkeys.iter().any(|pubkey| self.slow.contains(pubkey)) {
+                tokio::time::sleep(Duration::from_secs(60)).await;
+            }
+            AccountProvider::get_multiple_accounts(&self.inner, pubkeys, min_context_slot)
+        }
+    }
+
+    // AMM пул с vault-аккаунтами по 1_000_000 единиц
+    fn insert_amm_pool(provider: &mut InMemoryAccountProvider) -> AmmFixture {
+        let amm = AmmFixture::new();
+        amm.insert(provider, (1_000_000, 1_000_000));
+        amm
+    }
+
+    #[tokio::test]
+    async fn test_refresh_skips_slow_batch() {
+        let mut inner = InMemoryAccountProvider::new(100);
+        inner.insert(clock::id(), clock_account());
+        let requests: Vec<(PoolKind, Pubkey)> =
+            (0..60).map(|_| (PoolKind::RaydiumAmm, insert_amm_pool(&mut inner).pool)).collect();
+
+        let config = PipelineConfig { max_concurrency: 4, request_timeout_ms: 50 };
+        let cache = AccountCache::new();
+        let loaded = load_pools_async(&inner, &cache, &requests, &config).await.unwrap();
+        let registry: PoolRegistry = loaded.into_values().collect();
+        assert_eq!(registry.len(), 60);
+        assert!(registry.pools().all(|pool| pool.state_slot() == 100));
+
+        // 120 vault-аккаунтов — две пачки (100 + 20); вторая не укладывается в таймаут
+        inner.set_slot(105);
+        let keys: Vec<Pubkey> = registry.pools().flat_map(|pool| pool.required_accounts()).collect();
+        let provider = SlowProvider { inner, slow: HashSet::from([keys[keys.len() - 1]]) };
+        let skipped = refresh_pools_async(&registry, &provider, &cache, &config).await.unwrap();
+
+        assert_eq!(skipped, 1);
+        assert_eq!(registry.pools().filter(|pool| pool.state_slot() == 105).count(), 50);
+        assert_eq!(registry.pools().filter(|pool| pool.state_slot() == 100).count(), 10);
+    }
+
+    #[tokio::test]
+    async fn test_refresh_skips_pools_that_fail_to_ingest() {
+        let mut provider = InMemoryAccountProvider::new(100);
+        provider.insert(clock::id(), clock_account());
+        let broken = insert_amm_pool(&mut provider);
+        let healthy = insert_amm_pool(&mut provider).pool;
+
+        let config = PipelineConfig::default();
+        let cache = AccountCache::new();
+        let requests = [(PoolKind::RaydiumAmm, broken.pool), (PoolKind::RaydiumAmm, healthy)];
+        let registry: PoolRegistry = load_pools_async(&provider, &cache, &requests, &config).await.unwrap().into_values().collect();
+
+        // Закрытый vault приходит с пустыми данными: пул пропускается, остальные обновляются
+        provider.set_slot(105);
+        provider.insert(broken.quote_vault, Account::default());
+        assert_eq!(refresh_pools_async(&registry, &provider, &cache, &config).await.unwrap(), 0);
+        assert_eq!(registry.get(&broken.pool).unwrap().state_slot(), 100);
+        assert_eq!(registry.get(&healthy).unwrap().state_slot(), 105);
+    }
+}
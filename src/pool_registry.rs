@@ -0,0 +1,132 @@
+use solana_sdk::pubkey::Pubkey;
+use std::collections::HashMap;
+use std::sync::Arc;
+use crate::dex::PoolMints;
+
+/// Реестр загруженных пулов.
+///
+/// Каждый пул хранится один раз (по pubkey) и индексируется под обоими mint и по паре,
+/// поэтому поиск видит пул с любой его стороны независимо от того, под каким mint он указан в конфиге.
+/// Пулы нумеруются в порядке добавления; номер пула не меняется, пока реестр существует.
+#[derive(Default)]
+pub struct PoolRegistry {
+    pools: Vec<Arc<dyn PoolMints>>,
+    indices: HashMap<Pubkey, u32>,
+    by_mint: HashMap<Pubkey, Vec<Arc<dyn PoolMints>>>,
+    by_pair: HashMap<(Pubkey, Pubkey), Vec<Arc<dyn PoolMints>>>,
+}
+
+// Ключ пары не зависит от порядка mint
+fn pair_key(mint_x: &Pubkey, mint_y: &Pubkey) -> (Pubkey, Pubkey) {
+    if mint_x <= mint_y {
+        (*mint_x, *mint_y)
+    } else {
+        (*mint_y, *mint_x)
+    }
+}
+
+impl PoolRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Добавить пул; повторное добавление того же pubkey игнорируется (возвращает false)
+    pub fn insert(&mut self, pool: Arc<dyn PoolMints>) -> bool {
+        let pubkey = *pool.pool_pubkey();
+        if self.indices.contains_key(&pubkey) {
+            return false;
+        }
+
+        let (mint_a, mint_b) = (*pool.mint_a(), *pool.mint_b());
+        self.by_mint.entry(mint_a).or_default().push(Arc::clone(&pool));
+        if mint_b != mint_a {
+            self.by_mint.entry(mint_b).or_default().push(Arc::clone(&pool));
+        }
+        self.by_pair.entry(pair_key(&mint_a, &mint_b)).or_default().push(Arc::clone(&pool));
+        self.indices.insert(pubkey, self.pools.len() as u32);
+        self.pools.push(pool);
+        true
+    }
+
+    /// Пул по адресу
+    pub fn get(&self, pubkey: &Pubkey) -> Option<&Arc<dyn PoolMints>> {
+        self.index_of(pubkey).map(|index| &self.pools[index as usize])
+    }
+
+    /// Номер пула в порядке добавления
+    pub fn index_of(&self, pubkey: &Pubkey) -> Option<u32> {
+        self.indices.get(pubkey).copied()
+    }
+
+    /// Пул по номеру
+    pub fn pool(&self, index: u32) -> Option<&Arc<dyn PoolMints>> {
+        self.pools.get(index as usize)
+    }
+
+    /// Все пулы, в которых участвует mint
+    pub fn pools_for_mint(&self, mint: &Pubkey) -> &[Arc<dyn PoolMints>] {
+        self.by_mint.get(mint).map(Vec::as_slice).unwrap_or(&[])
+    }
+
+    /// Все пулы пары; порядок mint не важен
+    pub fn pools_for_pair(&self, mint_x: &Pubkey, mint_y: &Pubkey) -> &[Arc<dyn PoolMints>] {
+        self.by_pair.get(&pair_key(mint_x, mint_y)).map(Vec::as_slice).unwrap_or(&[])
+    }
+
+    /// Все пулы, каждый один раз, в порядке добавления
+    pub fn pools(&self) -> impl Iterator<Item = &Arc<dyn PoolMints>> {
+        self.pools.iter()
+    }
+
+    /// Количество mint, для которых есть хотя бы один пул
+    pub fn mint_count(&self) -> usize {
+        self.by_mint.len()
+    }
+
+    pub fn len(&self) -> usize {
+        self.pools.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pools.is_empty()
+    }
+}
+
+impl FromIterator<Arc<dyn PoolMints>> for PoolRegistry {
+    fn from_iter<I: IntoIterator<Item = Arc<dyn PoolMints>>>(iter: I) -> Self {
+        let mut registry = PoolRegistry::new();
+        for pool in iter {
+            registry.insert(pool);
+        }
+        registry
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils;
+
+    #[test]
+    fn test_registry_indexes_both_mints_once() {
+        let (wsol, fartcoin, usdc) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+        let pool = test_utils::pool(wsol, fartcoin);
+        let other = test_utils::pool(usdc, wsol);
+
+        let mut registry = PoolRegistry::new();
+        assert!(registry.insert(Arc::clone(&pool)));
+        assert!(!registry.insert(Arc::clone(&pool)));
+        assert!(registry.insert(other));
+
+        assert_eq!(registry.len(), 2);
+        assert_eq!(registry.mint_count(), 3);
+        assert_eq!(registry.pools_for_mint(&fartcoin).len(), 1);
+        assert_eq!(registry.pools_for_mint(&wsol).len(), 2);
+        assert_eq!(registry.pools_for_pair(&fartcoin, &wsol).len(), 1);
+        assert!(registry.pools_for_pair(&fartcoin, &usdc).is_empty());
+        assert!(registry.get(pool.pool_pubkey()).is_some());
+        assert_eq!(registry.index_of(pool.pool_pubkey()), Some(0));
+        assert_eq!(registry.pool(1).unwrap().mint_a(), &usdc);
+        assert!(registry.pool(2).is_none());
+    }
+}
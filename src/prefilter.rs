@@ -0,0 +1,292 @@
+use log::debug;
+use rayon::prelude::*;
+use solana_sdk::pubkey::Pubkey;
+use std::collections::{HashMap, VecDeque};
+
+use crate::cycles::{CycleIndex, Hop};
+use crate::dex::PoolMints;
+use crate::pool_registry::PoolRegistry;
+
+/// Во сколько раз пробный объём маржинального курса меньше стартовой суммы
+pub const PROBE_DIVISOR: u64 = 1_000;
+
+/// Граф логарифмических цен: вес направления пула — `-ln(маржинальный курс с комиссией)`.
+///
+/// Цикл прибылен на малом объёме, только если сумма весов отрицательна. Проскальзывание лишь
+/// ухудшает курс, поэтому цикл с неотрицательной суммой не прибылен и на полном объёме — его
+/// можно не котировать. Bellman-Ford с ограничением числа шагов от стартового mint даёт нижнюю
+/// оценку веса пути из каждого mint обратно к стартовому; по ней обход отсекает ветви, которые
+/// не могут замкнуться в отрицательный цикл.
+///
+/// Пробная котировка округляется вниз, поэтому вес каждого направления завышен не более чем
+/// на свой допуск `ln(1 + 1 / выход пробы)`. Отсечение идёт по весам за вычетом допусков:
+/// цикл отбрасывается, только если он неприбылен при любой погрешности округления.
+///
+/// Если маржинальный курс на пробе не получен (проба меньше лота стакана, состояние не загружено),
+/// вес направления `-inf`: котировка большего объёма даёт средний курс, а не оценку маржинального,
+/// поэтому такое направление циклы не отсекает.
+pub struct LogPriceGraph {
+    start_mint: Pubkey,
+    max_depth: usize,
+    /// Веса по номеру пула: `[mint_a -> mint_b, mint_b -> mint_a]`; `-inf` — курс неизвестен
+    weights: Vec<[f64; 2]>,
+    /// Допуски весов на округление пробных котировок, в том же порядке
+    tolerances: Vec<[f64; 2]>,
+    /// `to_start[h][mint]` — наименьший вес за вычетом допусков пути из mint к стартовому
+    /// не длиннее `h` шагов
+    to_start: Vec<HashMap<Pubkey, f64>>,
+}
+
+impl LogPriceGraph {
+    /// Оценить маржинальные курсы всех пулов и рассчитать оценки путей не длиннее `max_depth`
+    pub fn build(start_mint: &Pubkey, start_amount: u64, registry: &PoolRegistry, max_depth: usize) -> Self {
+        let references = reference_amounts(start_mint, start_amount, registry);
+        let (weights, tolerances): (Vec<[f64; 2]>, Vec<[f64; 2]>) = (0..registry.len() as u32)
+            .into_par_iter()
+            .map(|index| {
+                let pool = registry.pool(index).unwrap();
+                let (a_to_b, a_to_b_tolerance) = weight(pool.as_ref(), pool.mint_a(), &references);
+                let (b_to_a, b_to_a_tolerance) = weight(pool.as_ref(), pool.mint_b(), &references);
+                ([a_to_b, b_to_a], [a_to_b_tolerance, b_to_a_tolerance])
+            })
+            .unzip();
+
+        // Слой h — релаксация всех направлений пулов по слою h - 1 (обратные рёбра к стартовому mint)
+        let mut to_start: Vec<HashMap<Pubkey, f64>> = vec![HashMap::from([(*start_mint, 0.0)])];
+        for _ in 0..max_depth {
+            let previous = to_start.last().unwrap();
+            let mut layer = previous.clone();
+            for (pool, ([a_to_b, b_to_a], [a_to_b_tolerance, b_to_a_tolerance])) in
+                registry.pools().zip(weights.iter().zip(&tolerances))
+            {
+                for (token_in, token_out, weight) in [
+                    (pool.mint_a(), pool.mint_b(), a_to_b - a_to_b_tolerance),
+                    (pool.mint_b(), pool.mint_a(), b_to_a - b_to_a_tolerance),
+                ] {
+                    if let Some(rest) = previous.get(token_out) {
+                        let distance = layer.entry(*token_in).or_insert(f64::INFINITY);
+                        *distance = distance.min(weight + rest);
+                    }
+                }
+            }
+            to_start.push(layer);
+        }
+
+        Self { start_mint: *start_mint, max_depth, weights, tolerances, to_start }
+    }
+
+    /// Вес шага цикла
+    pub fn weight(&self, hop: Hop) -> f64 {
+        self.weights[hop.pool as usize][if hop.a_to_b { 0 } else { 1 }]
+    }
+
+    /// Допуск веса шага цикла на округление пробной котировки
+    pub fn tolerance(&self, hop: Hop) -> f64 {
+        self.tolerances[hop.pool as usize][if hop.a_to_b { 0 } else { 1 }]
+    }
+
+    /// Нижняя оценка (с учётом допусков) веса пути из `mint` к стартовому mint не длиннее `hops` шагов
+    pub fn bound(&self, mint: &Pubkey, hops: usize) -> f64 {
+        self.to_start[hops.min(self.max_depth)].get(mint).copied().unwrap_or(f64::INFINITY)
+    }
+
+    /// Через стартовый mint может проходить отрицательный цикл не длиннее `max_depth`
+    pub fn has_negative_cycle(&self) -> bool {
+        self.bound(&self.start_mint, self.max_depth) < 0.0
+    }
+
+    /// Сумма весов цикла за вычетом допусков отрицательна
+    pub fn is_candidate(&self, cycle: &[Hop]) -> bool {
+        cycle.iter().map(|hop| self.weight(*hop) - self.tolerance(*hop)).sum::<f64>() < 0.0
+    }
+
+    /// Циклы с отрицательной (в пределах допусков) суммой весов — кандидаты на полную котировку.
+    ///
+    /// Индекс отражает состояние пулов на момент построения графа и годится для одного скана.
+    pub fn candidates(&self, registry: &PoolRegistry) -> CycleIndex {
+        let keep = |path: &[Hop], token_out: &Pubkey| {
+            let weight: f64 = path.iter().map(|hop| self.weight(*hop) - self.tolerance(*hop)).sum();
+            if *token_out == self.start_mint {
+                weight < 0.0
+            } else {
+                weight + self.bound(token_out, self.max_depth.saturating_sub(path.len())) < 0.0
+            }
+        };
+        CycleIndex::build_pruned(&self.start_mint, registry, self.max_depth, &keep)
+    }
+}
+
+/// Циклы из `start_mint` не длиннее `max_depth`, прошедшие логарифмический префильтр
+pub fn candidate_cycles(start_mint: &Pubkey, start_amount: u64, registry: &PoolRegistry, max_depth: usize) -> CycleIndex {
+    let graph = LogPriceGraph::build(start_mint, start_amount, registry, max_depth);
+    let candidates = graph.candidates(registry);
+    debug!(
+        "Log-price prefilter: {} candidate cycles through {} (negative cycle: {})",
+        candidates.len(),
+        start_mint,
+        graph.has_negative_cycle()
+    );
+    candidates
+}
+
+/// Кандидаты из готового индекса циклов по текущему состоянию пулов — без повторного обхода графа.
+///
+/// Для долгоживущего индекса, который перекотируется на каждом скане.
+pub fn prefilter_cycles(index: &CycleIndex, start_amount: u64, registry: &PoolRegistry) -> CycleIndex {
+    let graph = LogPriceGraph::build(index.start_mint(), start_amount, registry, index.max_depth());
+    let candidates = index.filter(|cycle| graph.is_candidate(cycle));
+    debug!("Log-price prefilter: {} of {} cycles are candidates", candidates.len(), index.len());
+    candidates
+}
+
+// Объём, сопоставимый по стоимости со стартовой суммой, для каждого достижимого mint:
+// стартовая сумма, переведённая по первому найденному при обходе в ширину пулу
+fn reference_amounts(start_mint: &Pubkey, start_amount: u64, registry: &PoolRegistry) -> HashMap<Pubkey, u64> {
+    let mut amounts = HashMap::from([(*start_mint, start_amount)]);
+    let mut queue = VecDeque::from([*start_mint]);
+    while let Some(mint) = queue.pop_front() {
+        let amount_in = amounts[&mint];
+        for pool in registry.pools_for_mint(&mint) {
+            let token_out = if pool.mint_a() == &mint { *pool.mint_b() } else { *pool.mint_a() };
+            if amounts.contains_key(&token_out) {
+                continue;
+            }
+            if let Ok(amount_out @ 1..) = pool.amount_out(amount_in, &mint) {
+                amounts.insert(token_out, amount_out);
+                queue.push_back(token_out);
+            }
+        }
+    }
+    amounts
+}
+
+// Вес направления по курсу на пробном объёме и его допуск: выход пробы теряет при округлении
+// меньше единицы. Без курса на пробе вес `-inf` — направление не отсекает циклы
+fn weight(pool: &dyn PoolMints, token_in: &Pubkey, references: &HashMap<Pubkey, u64>) -> (f64, f64) {
+    let Some(reference) = references.get(token_in) else {
+        return (f64::NEG_INFINITY, 0.0);
+    };
+    let probe = (reference / PROBE_DIVISOR).max(1);
+    match pool.marginal_rate(token_in, probe).filter(|rate| rate.is_finite() && *rate > 0.0) {
+        Some(rate) => (-rate.ln(), (1.0 / (rate * probe as f64)).ln_1p()),
+        None => (f64::NEG_INFINITY, 0.0),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::arb::quote_cycles;
+    use crate::test_utils::{chain_keys, StubPool};
+    use std::sync::Arc;
+
+    fn pool(mint_a: Pubkey, mint_b: Pubkey, a_to_b_bps: u64, b_to_a_bps: u64) -> Arc<dyn PoolMints> {
+        Arc::new(StubPool::new(mint_a, mint_b).with_rates(a_to_b_bps, b_to_a_bps))
+    }
+
+    #[test]
+    fn test_prefilter_keeps_exactly_profitable_cycles_at_depth_six() {
+        // Кольцо из шести mint, прибыльное по направлению mint_a -> mint_b, и убыточные хорды
+        let mints: Vec<Pubkey> = (0..6).map(|_| Pubkey::new_unique()).collect();
+        let mut pools: Vec<Arc<dyn PoolMints>> = Vec::new();
+        for i in 0..6 {
+            pools.push(pool(mints[i], mints[(i + 1) % 6], 10_050, 9_900));
+            for j in i + 2..6 {
+                if (i, j) != (0, 5) {
+                    pools.push(pool(mints[i], mints[j], 9_970, 9_970));
+                }
+            }
+        }
+        let registry: PoolRegistry = pools.into_iter().collect();
+
+        let graph = LogPriceGraph::build(&mints[0], 1_000_000_000, &registry, 6);
+        assert!(graph.has_negative_cycle());
+        // Выход каждой пробы — около 10^6 единиц, допуски не превышают 10^-6 на шаг
+        assert!((graph.bound(&mints[0], 6) - 6.0 * -(1.005f64).ln()).abs() < 1e-5);
+
+        // Кандидаты — те же прибыльные цепочки, что у полного перебора
+        let all = CycleIndex::build_pruned(&mints[0], &registry, 6, &|_: &[Hop], _: &Pubkey| true);
+        let candidates = graph.candidates(&registry);
+        let profitable = quote_cycles(&all, 1_000_000_000, &registry, 2).unwrap();
+        assert!(!profitable.is_empty());
+        // Курсы пулов не зависят от объёма, поэтому префильтр точен
+        assert_eq!(candidates.len(), profitable.len());
+        assert!(candidates.len() * 5 < all.len());
+        assert_eq!(chain_keys(&quote_cycles(&candidates, 1_000_000_000, &registry, 2).unwrap()), chain_keys(&profitable));
+
+        // Отбор из полного индекса даёт тех же кандидатов, что и обход с отсечением
+        let filtered = prefilter_cycles(&all, 1_000_000_000, &registry);
+        assert_eq!(filtered.cycles().collect::<Vec<_>>(), candidates.cycles().collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_prefilter_rejects_all_cycles_without_negative_cycle() {
+        let (sol, usdc, bonk) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+        // Курсы согласованы (1 SOL = 1.5 USDC = 3 BONK), комиссия 0.3% на каждом шаге
+        let registry: PoolRegistry = vec![
+            pool(sol, usdc, 14_955, 6_646),
+            pool(usdc, bonk, 19_940, 4_985),
+            pool(bonk, sol, 3_323, 29_910),
+            pool(sol, bonk, 29_910, 3_323),
+        ]
+        .into_iter()
+        .collect();
+
+        assert!(!CycleIndex::build(&sol, &registry).is_empty());
+        assert!(!LogPriceGraph::build(&sol, 1_000_000_000, &registry, 4).has_negative_cycle());
+        assert!(candidate_cycles(&sol, 1_000_000_000, &registry, 4).is_empty());
+        // Без свопов циклов нет
+        assert!(candidate_cycles(&sol, 1_000_000_000, &registry, 0).is_empty());
+    }
+
+    #[test]
+    fn test_prefilter_keeps_cycle_with_coarse_probe_output() {
+        let (sol, usdc, btc) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+        // 1 BTC = 300 USDC: проба USDC -> BTC в 1000 единиц даёт 3.3 BTC и округляется до 3
+        let registry: PoolRegistry = vec![
+            pool(sol, usdc, 10_000, 10_000),
+            pool(usdc, btc, 33, 3_000_000),
+            pool(btc, sol, 3_040_000, 33),
+        ]
+        .into_iter()
+        .collect();
+
+        let all = CycleIndex::build(&sol, &registry);
+        let profitable = quote_cycles(&all, 1_000_000, &registry, 2).unwrap();
+        assert_eq!(profitable.len(), 1);
+
+        // По округлённым пробам прибыльный цикл выглядит убыточным, но укладывается в допуски
+        let graph = LogPriceGraph::build(&sol, 1_000_000, &registry, 3);
+        let cycle = [0, 1, 2].map(|pool| Hop { pool, a_to_b: true });
+        assert!(cycle.iter().map(|hop| graph.weight(*hop)).sum::<f64>() > 0.0);
+        assert!(cycle.iter().map(|hop| graph.weight(*hop) - graph.tolerance(*hop)).sum::<f64>() < 0.0);
+
+        let candidates = candidate_cycles(&sol, 1_000_000, &registry, 3);
+        assert_eq!(chain_keys(&quote_cycles(&candidates, 1_000_000, &registry, 2).unwrap()), chain_keys(&profitable));
+    }
+
+    #[test]
+    fn test_prefilter_keeps_cycle_through_lot_sized_pool() {
+        let (sol, usdc) = (Pubkey::new_unique(), Pubkey::new_unique());
+        // Опорный объём USDC берётся из первого пула (1_999_000) и на полной котировке теряет почти
+        // половину лота; цикл через второй пул приносит ровно два лота и прибылен
+        let registry: PoolRegistry = vec![
+            pool(sol, usdc, 1_999, 40_000),
+            pool(sol, usdc, 2_000, 40_000),
+            Arc::new(StubPool::new(usdc, sol).with_rates(51_000, 1_900).with_lot(1_000_000)) as Arc<dyn PoolMints>,
+        ]
+        .into_iter()
+        .collect();
+
+        let all = CycleIndex::build(&sol, &registry);
+        let profitable = quote_cycles(&all, 10_000_000, &registry, 2).unwrap();
+        assert_eq!(profitable.len(), 1);
+
+        // Проба USDC меньше лота: курс неизвестен, и направление не отсекает цикл
+        let graph = LogPriceGraph::build(&sol, 10_000_000, &registry, 2);
+        assert_eq!(graph.weight(Hop { pool: 2, a_to_b: true }), f64::NEG_INFINITY);
+
+        let candidates = candidate_cycles(&sol, 10_000_000, &registry, 2);
+        assert_eq!(chain_keys(&quote_cycles(&candidates, 10_000_000, &registry, 2).unwrap()), chain_keys(&profitable));
+    }
+}
@@ -0,0 +1,407 @@
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use serde::Deserialize;
+use solana_account_decoder_client_types::UiAccountEncoding;
+use solana_client::rpc_client::RpcClient;
+use solana_client::rpc_config::RpcAccountInfoConfig;
+use solana_sdk::account::Account;
+use solana_sdk::pubkey::Pubkey;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+use crate::error::ArbError;
+use crate::events::InnerInstruction;
+
+/// Источник данных аккаунтов для адаптеров пулов.
+///
+/// Отвязывает пулы от конкретного транспорта: в проде это RPC,
+/// в тестах — in-memory снапшот или каталог фикстур.
+pub trait AccountProvider {
+    /// Получить один аккаунт; отсутствующий аккаунт — ошибка `AccountNotFound`
+    fn get_account(&self, pubkey: &Pubkey) -> Result<Account, ArbError>;
+
+    /// Получить несколько аккаунтов; порядок результата совпадает с порядком ключей.
+    ///
+    /// `min_context_slot` — источник не должен отвечать данными старше этого слота.
+    fn get_multiple_accounts(
+        &self,
+        pubkeys: &[Pubkey],
+        min_context_slot: Option<u64>,
+    ) -> Result<SlotAccounts, ArbError>;
+
+    /// Текущий слот источника данных
+    fn get_slot(&self) -> Result<u64, ArbError>;
+}
+
+impl AccountProvider for RpcClient {
+    fn get_account(&self, pubkey: &Pubkey) -> Result<Account, ArbError> {
+        Ok(RpcClient::get_account(self, pubkey)?)
+    }
+
+    fn get_multiple_accounts(
+        &self,
+        pubkeys: &[Pubkey],
+        min_context_slot: Option<u64>,
+    ) -> Result<SlotAccounts, ArbError> {
+        let config = RpcAccountInfoConfig {
+            encoding: Some(UiAccountEncoding::Base64Zstd),
+            commitment: Some(self.commitment()),
+            data_slice: None,
+            min_context_slot,
+        };
+        let response = self.get_multiple_accounts_with_config(pubkeys, config)?;
+        Ok(SlotAccounts { context_slot: response.context.slot, accounts: response.value })
+    }
+
+    fn get_slot(&self) -> Result<u64, ArbError> {
+        Ok(RpcClient::get_slot(self)?)
+    }
+}
+
+/// Лимит ключей в одном запросе getMultipleAccounts
+pub const MAX_MULTIPLE_ACCOUNTS: usize = 100;
+
+/// Ответ getMultipleAccounts вместе со слотом контекста
+#[derive(Clone, Debug)]
+pub struct SlotAccounts {
+    pub context_slot: u64,
+    pub accounts: Vec<Option<Account>>,
+}
+
+/// Аккаунт снапшота и слот контекста ответа, в котором он получен
+#[derive(Clone, Debug)]
+struct SlotAccount {
+    account: Account,
+    slot: u64,
+}
+
+/// Снапшот аккаунтов, из которого пулы считывают своё состояние.
+///
+/// Для каждого аккаунта хранится слот контекста ответа — по нему пул знает,
+/// к какому слоту относится его состояние.
+#[derive(Clone, Debug, Default)]
+pub struct AccountSnapshot {
+    accounts: HashMap<Pubkey, SlotAccount>,
+}
+
+impl AccountSnapshot {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self { accounts: HashMap::with_capacity(capacity) }
+    }
+
+    /// Добавить аккаунт; данные из более старого слота не перезаписывают более свежие
+    pub fn insert(&mut self, pubkey: Pubkey, account: Account, slot: u64) {
+        match self.accounts.get(&pubkey) {
+            Some(existing) if existing.slot > slot => {}
+            _ => {
+                self.accounts.insert(pubkey, SlotAccount { account, slot });
+            }
+        }
+    }
+
+    pub fn get(&self, pubkey: &Pubkey) -> Option<&Account> {
+        self.accounts.get(pubkey).map(|entry| &entry.account)
+    }
+
+    /// Слот, в котором получен аккаунт
+    pub fn slot(&self, pubkey: &Pubkey) -> Option<u64> {
+        self.accounts.get(pubkey).map(|entry| entry.slot)
+    }
+
+    /// Наибольший слот среди указанных аккаунтов, присутствующих в снапшоте
+    pub fn max_slot_of(&self, pubkeys: &[Pubkey]) -> Option<u64> {
+        pubkeys.iter().filter_map(|pubkey| self.slot(pubkey)).max()
+    }
+
+    /// Наибольший слот среди всех аккаунтов снапшота
+    pub fn max_slot(&self) -> Option<u64> {
+        self.accounts.values().map(|entry| entry.slot).max()
+    }
+
+    pub fn contains_key(&self, pubkey: &Pubkey) -> bool {
+        self.accounts.contains_key(pubkey)
+    }
+
+    pub fn extend(&mut self, other: AccountSnapshot) {
+        for (pubkey, entry) in other.accounts {
+            self.insert(pubkey, entry.account, entry.slot);
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.accounts.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.accounts.is_empty()
+    }
+}
+
+/// Получить снапшот указанных аккаунтов пачками по MAX_MULTIPLE_ACCOUNTS.
+///
+/// Повторяющиеся ключи запрашиваются один раз, отсутствующие аккаунты в снапшот не попадают.
+/// Каждая следующая пачка запрашивается с `min_context_slot` не ниже слота предыдущих ответов,
+/// поэтому снапшот не откатывается назад между пачками.
+pub fn fetch_snapshot(
+    provider: &dyn AccountProvider,
+    pubkeys: &[Pubkey],
+    min_context_slot: Option<u64>,
+) -> Result<AccountSnapshot, ArbError> {
+    let mut seen: HashSet<Pubkey> = HashSet::with_capacity(pubkeys.len());
+    let unique: Vec<Pubkey> = pubkeys.iter().copied().filter(|pubkey| seen.insert(*pubkey)).collect();
+
+    let mut snapshot = AccountSnapshot::with_capacity(unique.len());
+    let mut min_slot = min_context_slot;
+    for chunk in unique.chunks(MAX_MULTIPLE_ACCOUNTS) {
+        let response = provider.get_multiple_accounts(chunk, min_slot)?;
+        for (pubkey, account) in chunk.iter().zip(response.accounts) {
+            if let Some(account) = account {
+                snapshot.insert(*pubkey, account, response.context_slot);
+            }
+        }
+        min_slot = min_slot.max(Some(response.context_slot));
+    }
+
+    Ok(snapshot)
+}
+
+/// Аккаунт из снапшота; отсутствующий аккаунт — ошибка `AccountNotFound`
+pub fn snapshot_account<'a>(accounts: &'a AccountSnapshot, pubkey: &Pubkey) -> Result<&'a Account, ArbError> {
+    accounts.get(pubkey).ok_or(ArbError::AccountNotFound(*pubkey))
+}
+
+/// Аккаунты и транзакции в памяти — для офлайн-тестов и заранее снятых снапшотов
+#[derive(Clone, Debug, Default)]
+pub struct InMemoryAccountProvider {
+    accounts: HashMap<Pubkey, Account>,
+    /// Внутренние инструкции транзакций по подписи
+    transactions: HashMap<String, Vec<InnerInstruction>>,
+    slot: u64,
+}
+
+impl InMemoryAccountProvider {
+    pub fn new(slot: u64) -> Self {
+        Self { slot, ..Self::default() }
+    }
+
+    pub fn insert(&mut self, pubkey: Pubkey, account: Account) {
+        self.accounts.insert(pubkey, account);
+    }
+
+    pub fn insert_transaction(&mut self, signature: &str, inner_instructions: Vec<InnerInstruction>) {
+        self.transactions.insert(signature.to_string(), inner_instructions);
+    }
+
+    pub fn inner_instructions(&self, signature: &str) -> Option<&[InnerInstruction]> {
+        self.transactions.get(signature).map(Vec::as_slice)
+    }
+
+    pub fn set_slot(&mut self, slot: u64) {
+        self.slot = slot;
+    }
+
+    pub fn len(&self) -> usize {
+        self.accounts.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.accounts.is_empty()
+    }
+}
+
+impl AccountProvider for InMemoryAccountProvider {
+    fn get_account(&self, pubkey: &Pubkey) -> Result<Account, ArbError> {
+        self.accounts
+            .get(pubkey)
+            .cloned()
+            .ok_or(ArbError::AccountNotFound(*pubkey))
+    }
+
+    fn get_multiple_accounts(
+        &self,
+        pubkeys: &[Pubkey],
+        min_context_slot: Option<u64>,
+    ) -> Result<SlotAccounts, ArbError> {
+        if let Some(min_slot) = min_context_slot.filter(|min_slot| *min_slot > self.slot) {
+            return Err(ArbError::StaleSnapshot(format!(
+                "minimum context slot {} not reached, source is at slot {}",
+                min_slot, self.slot
+            )));
+        }
+
+        Ok(SlotAccounts {
+            context_slot: self.slot,
+            accounts: pubkeys.iter().map(|pubkey| self.accounts.get(pubkey).cloned()).collect(),
+        })
+    }
+
+    fn get_slot(&self) -> Result<u64, ArbError> {
+        Ok(self.slot)
+    }
+}
+
+/*
+Формат фикстуры — вывод `solana account <ADDRESS> --output json`:
+  {"pubkey": "...", "account": {"lamports": 0, "data": ["<base64>", "base64"],
+   "owner": "...", "executable": false, "rentEpoch": 0, "space": 0}}
+*/
+
+#[derive(Deserialize)]
+struct FixtureFile {
+    pubkey: String,
+    account: FixtureAccount,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct FixtureAccount {
+    lamports: u64,
+    data: (String, String),
+    owner: String,
+    executable: bool,
+    rent_epoch: u64,
+}
+
+/// Аккаунты из каталога JSON-фикстур, снятых через solana CLI
+#[derive(Clone, Debug, Default)]
+pub struct FixtureAccountProvider {
+    inner: InMemoryAccountProvider,
+}
+
+impl FixtureAccountProvider {
+    /// Загрузить все `*.json` файлы каталога; слот снапшота задаётся явно
+    pub fn load<P: AsRef<Path>>(dir: P, slot: u64) -> Result<Self, ArbError> {
+        let mut inner = InMemoryAccountProvider::new(slot);
+
+        for entry in fs::read_dir(dir)? {
+            let path = entry?.path();
+            if path.extension().and_then(|ext| ext.to_str()) != Some("json") {
+                continue;
+            }
+
+            let content = fs::read_to_string(&path)?;
+            let (pubkey, account) = parse_fixture(&content)
+                .map_err(|e| ArbError::Decode(format!("{}: {}", path.display(), e)))?;
+            inner.insert(pubkey, account);
+        }
+
+        Ok(Self { inner })
+    }
+
+    pub fn len(&self) -> usize {
+        self.inner.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.inner.is_empty()
+    }
+}
+
+impl AccountProvider for FixtureAccountProvider {
+    fn get_account(&self, pubkey: &Pubkey) -> Result<Account, ArbError> {
+        self.inner.get_account(pubkey)
+    }
+
+    fn get_multiple_accounts(
+        &self,
+        pubkeys: &[Pubkey],
+        min_context_slot: Option<u64>,
+    ) -> Result<SlotAccounts, ArbError> {
+        self.inner.get_multiple_accounts(pubkeys, min_context_slot)
+    }
+
+    fn get_slot(&self) -> Result<u64, ArbError> {
+        self.inner.get_slot()
+    }
+}
+
+// Разбор одной фикстуры solana CLI в пару (pubkey, Account)
+fn parse_fixture(content: &str) -> Result<(Pubkey, Account), String> {
+    let fixture: FixtureFile = serde_json::from_str(content).map_err(|e| e.to_string())?;
+    let (data, encoding) = fixture.account.data;
+    if encoding != "base64" {
+        return Err(format!("unsupported data encoding {}", encoding));
+    }
+
+    let pubkey = Pubkey::from_str(&fixture.pubkey).map_err(|e| e.to_string())?;
+    let account = Account {
+        lamports: fixture.account.lamports,
+        data: BASE64.decode(data).map_err(|e| e.to_string())?,
+        owner: Pubkey::from_str(&fixture.account.owner).map_err(|e| e.to_string())?,
+        executable: fixture.account.executable,
+        rent_epoch: fixture.account.rent_epoch,
+    };
+
+    Ok((pubkey, account))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_in_memory_provider() {
+        let key = Pubkey::new_unique();
+        let missing = Pubkey::new_unique();
+        let mut provider = InMemoryAccountProvider::new(42);
+        provider.insert(key, Account { lamports: 7, ..Account::default() });
+
+        assert_eq!(provider.get_account(&key).unwrap().lamports, 7);
+        assert!(matches!(provider.get_account(&missing), Err(ArbError::AccountNotFound(k)) if k == missing));
+
+        let response = provider.get_multiple_accounts(&[missing, key], None).unwrap();
+        assert_eq!(response.context_slot, 42);
+        assert!(response.accounts[0].is_none());
+        assert_eq!(response.accounts[1].as_ref().unwrap().lamports, 7);
+        assert_eq!(provider.get_slot().unwrap(), 42);
+
+        assert!(matches!(
+            provider.get_multiple_accounts(&[key], Some(43)),
+            Err(ArbError::StaleSnapshot(_))
+        ));
+    }
+
+    #[test]
+    fn test_snapshot_keeps_newer_slot() {
+        let key = Pubkey::new_unique();
+        let mut snapshot = AccountSnapshot::new();
+        snapshot.insert(key, Account { lamports: 2, ..Account::default() }, 110);
+        snapshot.insert(key, Account { lamports: 1, ..Account::default() }, 100);
+
+        assert_eq!(snapshot.get(&key).unwrap().lamports, 2);
+        assert_eq!(snapshot.slot(&key), Some(110));
+        assert_eq!(snapshot.max_slot_of(&[key, Pubkey::new_unique()]), Some(110));
+    }
+
+    #[test]
+    fn test_fixture_directory_provider() {
+        let key = Pubkey::new_unique();
+        let owner = Pubkey::new_unique();
+        let dir = std::env::temp_dir().join(format!("gpt_arb_fixtures_{}", key));
+        fs::create_dir_all(&dir).unwrap();
+
+        let fixture = format!(
+            r#"{{"pubkey":"{}","account":{{"lamports":1461600,"data":["{}","base64"],"owner":"{}","executable":false,"rentEpoch":18446744073709551615,"space":4}}}}"#,
+            key,
+            BASE64.encode([1u8, 2, 3, 4]),
+            owner
+        );
+        fs::write(dir.join(format!("{}.json", key)), fixture).unwrap();
+        fs::write(dir.join("README.md"), "not a fixture").unwrap();
+
+        let provider = FixtureAccountProvider::load(&dir, 100).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(provider.len(), 1);
+        let account = provider.get_account(&key).unwrap();
+        assert_eq!(account.data, vec![1, 2, 3, 4]);
+        assert_eq!(account.owner, owner);
+        assert_eq!(account.lamports, 1_461_600);
+        assert_eq!(provider.get_slot().unwrap(), 100);
+    }
+}
@@ -0,0 +1,490 @@
+use log::{info, warn};
+use serde::Deserialize;
+use solana_client::client_error::ClientErrorKind;
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_client::rpc_request::RpcError;
+use solana_sdk::pubkey::Pubkey;
+use std::collections::hash_map::RandomState;
+use std::future::Future;
+use std::hash::{BuildHasher, Hasher};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::time::{sleep, timeout};
+
+use crate::error::ArbError;
+use crate::events::InnerInstruction;
+use crate::pipeline::{AsyncAccountProvider, AsyncTransactionProvider};
+use crate::provider::SlotAccounts;
+
+/// RPC endpoint по умолчанию
+pub const DEFAULT_RPC_URL: &str = "https://api.mainnet-beta.solana.com";
+/// Повторов запроса после первой попытки по умолчанию
+pub const DEFAULT_MAX_RETRIES: u32 = 2;
+/// Таймаут одной попытки по умолчанию, мс
+pub const DEFAULT_ATTEMPT_TIMEOUT_MS: u64 = 1_000;
+/// Начальная и предельная пауза между попытками по умолчанию, мс
+pub const DEFAULT_BACKOFF_BASE_MS: u64 = 100;
+pub const DEFAULT_BACKOFF_MAX_MS: u64 = 2_000;
+/// Начальный и предельный срок исключения endpoint'а из ротации по умолчанию, мс
+pub const DEFAULT_COOLDOWN_MS: u64 = 1_000;
+pub const DEFAULT_COOLDOWN_MAX_MS: u64 = 60_000;
+/// Период проверки getHealth по умолчанию, мс
+pub const DEFAULT_HEALTH_CHECK_INTERVAL_MS: u64 = 10_000;
+
+// Ошибки самого запроса: другой endpoint ответит так же, повтор бесполезен
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+
+/// Параметры пула RPC endpoint'ов (секция `[rpc]` конфига)
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct RpcConfig {
+    /// Endpoint'ы в порядке предпочтения; запросы распределяются между здоровыми по кругу
+    pub endpoints: Vec<String>,
+    /// Лимит запросов в секунду на один endpoint; 0 — без лимита
+    pub requests_per_second: u32,
+    /// Сколько раз повторить запрос на других endpoint'ах после неудачной попытки
+    pub max_retries: u32,
+    /// Таймаут одной попытки, мс
+    pub attempt_timeout_ms: u64,
+    /// Пауза перед повтором растёт от `backoff_base_ms` вдвое до `backoff_max_ms`, со случайным разбросом
+    pub backoff_base_ms: u64,
+    pub backoff_max_ms: u64,
+    /// Срок исключения endpoint'а из ротации после неудачи: растёт вдвое с каждой неудачей подряд
+    pub cooldown_ms: u64,
+    pub cooldown_max_ms: u64,
+    /// Период проверки getHealth, мс; 0 — только пассивная проверка по ошибкам запросов
+    pub health_check_interval_ms: u64,
+}
+
+impl Default for RpcConfig {
+    fn default() -> Self {
+        Self {
+            endpoints: vec![DEFAULT_RPC_URL.to_string()],
+            requests_per_second: 0,
+            max_retries: DEFAULT_MAX_RETRIES,
+            attempt_timeout_ms: DEFAULT_ATTEMPT_TIMEOUT_MS,
+            backoff_base_ms: DEFAULT_BACKOFF_BASE_MS,
+            backoff_max_ms: DEFAULT_BACKOFF_MAX_MS,
+            cooldown_ms: DEFAULT_COOLDOWN_MS,
+            cooldown_max_ms: DEFAULT_COOLDOWN_MAX_MS,
+            health_check_interval_ms: DEFAULT_HEALTH_CHECK_INTERVAL_MS,
+        }
+    }
+}
+
+impl RpcConfig {
+    pub fn attempt_timeout(&self) -> Duration {
+        Duration::from_millis(self.attempt_timeout_ms)
+    }
+
+    pub fn health_check_interval(&self) -> Duration {
+        Duration::from_millis(self.health_check_interval_ms)
+    }
+
+    // Пауза перед повтором после `attempt`-й неудачной попытки, без разброса
+    fn backoff(&self, attempt: u32) -> Duration {
+        exponential(self.backoff_base_ms, self.backoff_max_ms, attempt)
+    }
+
+    // Срок исключения endpoint'а после `failures` неудач подряд
+    fn cooldown(&self, failures: u32) -> Duration {
+        exponential(self.cooldown_ms, self.cooldown_max_ms, failures)
+    }
+}
+
+// base * 2^n, не больше max
+fn exponential(base_ms: u64, max_ms: u64, n: u32) -> Duration {
+    Duration::from_millis(base_ms)
+        .saturating_mul(1u32.checked_shl(n).unwrap_or(u32::MAX))
+        .min(Duration::from_millis(max_ms))
+}
+
+// Разброс паузы в [delay / 2, delay]: клиенты не повторяют запросы синхронно
+fn with_jitter(delay: Duration) -> Duration {
+    let half = delay / 2;
+    let spread = half.as_nanos() as u64 + 1;
+    half + Duration::from_nanos(RandomState::new().build_hasher().finish() % spread)
+}
+
+/// Равномерный лимит запросов: каждый следующий запрос не раньше чем через `period` после предыдущего
+#[derive(Debug)]
+struct RateLimiter {
+    period: Option<Duration>,
+    next: Mutex<Instant>,
+}
+
+impl RateLimiter {
+    fn new(requests_per_second: u32) -> Self {
+        let period = (requests_per_second > 0).then(|| Duration::from_secs(1) / requests_per_second);
+        Self { period, next: Mutex::new(Instant::now()) }
+    }
+
+    async fn acquire(&self) {
+        let Some(period) = self.period else { return };
+        let wait = {
+            let mut next = self.next.lock().unwrap();
+            let now = Instant::now();
+            let at = (*next).max(now);
+            *next = at + period;
+            at - now
+        };
+        if !wait.is_zero() {
+            sleep(wait).await;
+        }
+    }
+}
+
+/// Состояние endpoint'а: после неудачи он исключается из ротации на растущий срок
+#[derive(Debug, Default)]
+struct Health {
+    failures: u32,
+    down_until: Option<Instant>,
+}
+
+struct Endpoint {
+    url: String,
+    client: Arc<RpcClient>,
+    limiter: RateLimiter,
+    health: Mutex<Health>,
+}
+
+impl Endpoint {
+    // Момент возвращения в ротацию; `None` — endpoint здоров
+    fn down_until(&self, now: Instant) -> Option<Instant> {
+        self.health.lock().unwrap().down_until.filter(|until| *until > now)
+    }
+
+    fn mark_up(&self) {
+        *self.health.lock().unwrap() = Health::default();
+    }
+
+    fn mark_down(&self, config: &RpcConfig) {
+        let mut health = self.health.lock().unwrap();
+        health.down_until = Some(Instant::now() + config.cooldown(health.failures));
+        health.failures += 1;
+    }
+}
+
+/// Пул RPC endpoint'ов с лимитом запросов, повторами и переключением.
+///
+/// Неудачная попытка (таймаут, транспорт, 429/5xx, ошибка сервера) исключает endpoint из ротации
+/// на экспоненциально растущий срок `cooldown_ms`, запрос повторяется на следующем endpoint'е после паузы
+/// с разбросом. Когда все endpoint'ы исключены, используется тот, что вернётся в ротацию раньше.
+///
+/// Ответы 429 клиент solana сначала повторяет сам, выдерживая `Retry-After`; таймаут попытки
+/// ограничивает это ожидание, после чего запрос уходит на другой endpoint.
+pub struct RpcPool {
+    config: RpcConfig,
+    endpoints: Vec<Endpoint>,
+    cursor: AtomicUsize,
+}
+
+impl RpcPool {
+    pub fn new(config: &RpcConfig) -> Result<Self, ArbError> {
+        if config.endpoints.is_empty() {
+            return Err(ArbError::Config("[rpc] endpoints must not be empty".into()));
+        }
+
+        let endpoints = config
+            .endpoints
+            .iter()
+            .map(|url| Endpoint {
+                url: url.clone(),
+                client: Arc::new(RpcClient::new_with_timeout(url.clone(), config.attempt_timeout())),
+                limiter: RateLimiter::new(config.requests_per_second),
+                health: Mutex::new(Health::default()),
+            })
+            .collect();
+        Ok(Self { config: config.clone(), endpoints, cursor: AtomicUsize::new(0) })
+    }
+
+    /// Endpoint'ы, которые сейчас в ротации
+    pub fn healthy_endpoints(&self) -> Vec<&str> {
+        let now = Instant::now();
+        self.endpoints
+            .iter()
+            .filter(|endpoint| endpoint.down_until(now).is_none())
+            .map(|endpoint| endpoint.url.as_str())
+            .collect()
+    }
+
+    /// Проверить все endpoint'ы через getHealth; возвращает число здоровых
+    pub async fn check_health(&self) -> usize {
+        let checks = self.endpoints.iter().map(|endpoint| async move {
+            match timeout(self.config.attempt_timeout(), endpoint.client.get_health()).await {
+                Ok(Ok(())) => {
+                    endpoint.mark_up();
+                    true
+                }
+                Ok(Err(e)) => {
+                    warn!("RPC endpoint {} is unhealthy: {}", endpoint.url, e);
+                    endpoint.mark_down(&self.config);
+                    false
+                }
+                Err(_) => {
+                    warn!("RPC endpoint {} health check timed out", endpoint.url);
+                    endpoint.mark_down(&self.config);
+                    false
+                }
+            }
+        });
+        futures::future::join_all(checks).await.into_iter().filter(|healthy| *healthy).count()
+    }
+
+    /// Периодические проверки getHealth; при нулевом периоде сразу завершается
+    pub async fn run_health_checks(&self) {
+        let interval = self.config.health_check_interval();
+        if interval.is_zero() {
+            return;
+        }
+        loop {
+            sleep(interval).await;
+            let healthy = self.check_health().await;
+            info!("{} of {} RPC endpoints are healthy", healthy, self.endpoints.len());
+        }
+    }
+
+    // Следующий endpoint: сначала не пробованные в этом запросе, среди них — здоровые,
+    // затем раньше возвращающиеся в ротацию; при равенстве — по кругу
+    fn pick(&self, tried: &[bool]) -> usize {
+        let now = Instant::now();
+        let start = self.cursor.fetch_add(1, Ordering::Relaxed);
+        let len = self.endpoints.len();
+        (0..len)
+            .map(|offset| (start + offset) % len)
+            .min_by_key(|&index| (tried[index], self.endpoints[index].down_until(now)))
+            .unwrap()
+    }
+
+    /// Выполнить запрос с повторами и переключением endpoint'ов
+    pub async fn request<T, F, Fut>(&self, mut call: F) -> Result<T, ArbError>
+    where
+        F: FnMut(Arc<RpcClient>) -> Fut,
+        Fut: Future<Output = Result<T, ArbError>>,
+    {
+        let mut tried = vec![false; self.endpoints.len()];
+        let mut attempt = 0;
+        loop {
+            let index = self.pick(&tried);
+            let endpoint = &self.endpoints[index];
+            tried[index] = true;
+            if tried.iter().all(|tried| *tried) {
+                tried.fill(false);
+            }
+
+            endpoint.limiter.acquire().await;
+            let result = match timeout(self.config.attempt_timeout(), call(endpoint.client.clone())).await {
+                Ok(result) => result,
+                Err(_) => Err(ArbError::Timeout(format!(
+                    "{} did not respond within {:?}",
+                    endpoint.url,
+                    self.config.attempt_timeout()
+                ))),
+            };
+
+            match result {
+                Ok(value) => {
+                    endpoint.mark_up();
+                    return Ok(value);
+                }
+                Err(e) if !is_retryable(&e) => return Err(e),
+                Err(e) => {
+                    endpoint.mark_down(&self.config);
+                    if attempt >= self.config.max_retries {
+                        return Err(e);
+                    }
+                    warn!("RPC request to {} failed: {}, retrying on another endpoint", endpoint.url, e);
+                    sleep(with_jitter(self.config.backoff(attempt))).await;
+                    attempt += 1;
+                }
+            }
+        }
+    }
+}
+
+// Повторять всё, кроме ошибок самого запроса
+fn is_retryable(error: &ArbError) -> bool {
+    match error {
+        ArbError::Rpc(e) => !matches!(
+            e.kind(),
+            ClientErrorKind::RpcError(RpcError::RpcResponseError { code: INVALID_REQUEST | METHOD_NOT_FOUND | INVALID_PARAMS, .. })
+        ),
+        ArbError::Timeout(_) | ArbError::StaleSnapshot(_) => true,
+        _ => false,
+    }
+}
+
+impl AsyncAccountProvider for RpcPool {
+    async fn get_multiple_accounts(
+        &self,
+        pubkeys: &[Pubkey],
+        min_context_slot: Option<u64>,
+    ) -> Result<SlotAccounts, ArbError> {
+        self.request(|client| async move {
+            AsyncAccountProvider::get_multiple_accounts(client.as_ref(), pubkeys, min_context_slot).await
+        })
+        .await
+    }
+}
+
+impl AsyncTransactionProvider for RpcPool {
+    async fn get_inner_instructions(&self, signature: &str) -> Result<Vec<InnerInstruction>, ArbError> {
+        self.request(|client| async move { client.get_inner_instructions(signature).await }).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::{TcpListener, TcpStream};
+
+    /// Поведение локальной заглушки RPC
+    #[derive(Clone, Copy)]
+    enum StandIn {
+        /// Всегда 429 Too Many Requests
+        RateLimited,
+        /// Принимает соединение и не отвечает
+        Hanging,
+        /// getHealth — "ok", getMultipleAccounts — один аккаунт с lamports = 42
+        Healthy,
+    }
+
+    // Прочитать HTTP-запрос целиком: заголовки и тело по Content-Length
+    async fn read_request(socket: &mut TcpStream) -> String {
+        let mut request = Vec::new();
+        let mut buf = [0u8; 4096];
+        loop {
+            let read = socket.read(&mut buf).await.unwrap_or(0);
+            if read == 0 {
+                break;
+            }
+            request.extend_from_slice(&buf[..read]);
+            let text = String::from_utf8_lossy(&request).to_string();
+            if let Some(header_end) = text.find("\r\n\r\n") {
+                let content_length = text[..header_end]
+                    .lines()
+                    .find_map(|line| line.to_ascii_lowercase().strip_prefix("content-length:").map(|v| v.trim().parse::<usize>().unwrap()))
+                    .unwrap_or(0);
+                if request.len() >= header_end + 4 + content_length {
+                    break;
+                }
+            }
+        }
+        String::from_utf8_lossy(&request).to_string()
+    }
+
+    async fn spawn_stand_in(behavior: StandIn) -> (String, Arc<AtomicUsize>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let hits = Arc::new(AtomicUsize::new(0));
+        let counter = hits.clone();
+        tokio::spawn(async move {
+            loop {
+                let (mut socket, _) = listener.accept().await.unwrap();
+                let counter = counter.clone();
+                tokio::spawn(async move {
+                    let request = read_request(&mut socket).await;
+                    counter.fetch_add(1, Ordering::SeqCst);
+                    let body = match behavior {
+                        StandIn::RateLimited => {
+                            let response = "HTTP/1.1 429 Too Many Requests\r\nRetry-After: 0\r\nContent-Length: 0\r\nConnection: close\r\n\r\n";
+                            let _ = socket.write_all(response.as_bytes()).await;
+                            return;
+                        }
+                        StandIn::Hanging => {
+                            sleep(Duration::from_secs(60)).await;
+                            return;
+                        }
+                        StandIn::Healthy if request.contains("getHealth") => r#"{"jsonrpc":"2.0","result":"ok","id":1}"#.to_string(),
+                        StandIn::Healthy => r#"{"jsonrpc":"2.0","result":{"context":{"slot":100},"value":[{"data":["","base64"],"executable":false,"lamports":42,"owner":"11111111111111111111111111111111","rentEpoch":0,"space":0}]},"id":1}"#.to_string(),
+                    };
+                    let response = format!(
+                        "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                        body.len(),
+                        body
+                    );
+                    let _ = socket.write_all(response.as_bytes()).await;
+                });
+            }
+        });
+        (url, hits)
+    }
+
+    fn test_config(endpoints: Vec<String>) -> RpcConfig {
+        RpcConfig {
+            endpoints,
+            requests_per_second: 0,
+            max_retries: 3,
+            attempt_timeout_ms: 300,
+            backoff_base_ms: 10,
+            backoff_max_ms: 50,
+            cooldown_ms: 5_000,
+            cooldown_max_ms: 60_000,
+            health_check_interval_ms: 0,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_rpc_pool_fails_over_from_rate_limited_and_hanging_endpoints() {
+        let (limited, limited_hits) = spawn_stand_in(StandIn::RateLimited).await;
+        let (hanging, _) = spawn_stand_in(StandIn::Hanging).await;
+        let (healthy, healthy_hits) = spawn_stand_in(StandIn::Healthy).await;
+        let pool = RpcPool::new(&test_config(vec![limited.clone(), hanging.clone(), healthy.clone()])).unwrap();
+
+        let key = Pubkey::new_unique();
+        let response = pool.get_multiple_accounts(&[key], None).await.unwrap();
+        assert_eq!(response.context_slot, 100);
+        assert_eq!(response.accounts[0].as_ref().unwrap().lamports, 42);
+        assert_eq!(pool.healthy_endpoints(), vec![healthy.as_str()]);
+
+        // Исключённые endpoint'ы больше не получают запросов, пока не вернутся в ротацию
+        let limited_before = limited_hits.load(Ordering::SeqCst);
+        assert!(limited_before > 0);
+        pool.get_multiple_accounts(&[key], None).await.unwrap();
+        assert_eq!(limited_hits.load(Ordering::SeqCst), limited_before);
+        assert_eq!(healthy_hits.load(Ordering::SeqCst), 2);
+
+        // getHealth возвращает здоровые endpoint'ы в ротацию и исключает зависший
+        assert_eq!(pool.check_health().await, 1);
+
+        // Без единого рабочего endpoint'а запрос завершается ошибкой после всех повторов
+        let pool = RpcPool::new(&test_config(vec![limited, hanging])).unwrap();
+        let result = pool.get_multiple_accounts(&[key], None).await;
+        assert!(matches!(result, Err(ArbError::Rpc(_)) | Err(ArbError::Timeout(_))));
+    }
+
+    #[tokio::test]
+    async fn test_rpc_pool_rate_limits_each_endpoint() {
+        let (healthy, hits) = spawn_stand_in(StandIn::Healthy).await;
+        let mut config = test_config(vec![healthy]);
+        config.requests_per_second = 20;
+        let pool = RpcPool::new(&config).unwrap();
+
+        let started = Instant::now();
+        for _ in 0..5 {
+            pool.get_multiple_accounts(&[Pubkey::new_unique()], None).await.unwrap();
+        }
+        // Первый запрос сразу, остальные — не чаще раза в 50 мс
+        assert!(started.elapsed() >= Duration::from_millis(200));
+        assert_eq!(hits.load(Ordering::SeqCst), 5);
+    }
+
+    #[test]
+    fn test_backoff_grows_to_limit_with_jitter() {
+        let config = test_config(Vec::new());
+        assert_eq!(config.backoff(0), Duration::from_millis(10));
+        assert_eq!(config.backoff(2), Duration::from_millis(40));
+        assert_eq!(config.backoff(10), Duration::from_millis(50));
+        assert_eq!(config.backoff(64), Duration::from_millis(50));
+        assert_eq!(config.cooldown(1), Duration::from_secs(10));
+        for _ in 0..100 {
+            let delay = with_jitter(Duration::from_millis(40));
+            assert!(delay >= Duration::from_millis(20) && delay <= Duration::from_millis(40));
+        }
+        assert!(RpcPool::new(&config).is_err());
+    }
+}
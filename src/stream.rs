@@ -0,0 +1,440 @@
+use futures::stream::{select_all, BoxStream, StreamExt};
+use log::{info, warn};
+use serde::Deserialize;
+use solana_account_decoder_client_types::UiAccountEncoding;
+use solana_client::nonblocking::pubsub_client::PubsubClient;
+use solana_account_decoder_client_types::UiAccount;
+use solana_client::rpc_config::{RpcAccountInfoConfig, RpcTransactionLogsConfig, RpcTransactionLogsFilter};
+use solana_client::rpc_response::{Response, RpcLogsResponse};
+use solana_commitment_config::CommitmentConfig;
+use solana_sdk::account::Account;
+use solana_sdk::pubkey::Pubkey;
+use std::collections::HashMap;
+use std::ops::ControlFlow;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::cache::AccountCache;
+use crate::dex::PoolMints;
+use crate::error::ArbError;
+use crate::events::{apply_events, event_programs, parse_inner_instructions, parse_logs};
+use crate::pipeline::{refresh_pools_async, AsyncAccountProvider, AsyncTransactionProvider, PipelineConfig};
+use crate::pool_registry::PoolRegistry;
+
+/// WebSocket endpoint по умолчанию
+pub const DEFAULT_WS_URL: &str = "wss://api.mainnet-beta.solana.com";
+/// Пауза перед переподключением по умолчанию, мс
+pub const DEFAULT_RECONNECT_DELAY_MS: u64 = 1_000;
+
+/// Параметры потокового режима (секция `[stream]` конфига)
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct StreamConfig {
+    /// Включить подписку accountSubscribe вместо разового снапшота
+    pub enabled: bool,
+    pub ws_url: String,
+    /// Дополнительно подписаться на логи CLMM/DLMM (logsSubscribe) и обновлять пулы
+    /// по событиям свопа, не дожидаясь уведомлений об аккаунтах.
+    ///
+    /// DLMM публикует события через event CPI, которых нет в логах: для таких транзакций
+    /// внутренние инструкции запрашиваются через getTransaction.
+    pub logs: bool,
+    /// Пауза перед переподключением, мс
+    pub reconnect_delay_ms: u64,
+}
+
+impl Default for StreamConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            ws_url: DEFAULT_WS_URL.to_string(),
+            logs: false,
+            reconnect_delay_ms: DEFAULT_RECONNECT_DELAY_MS,
+        }
+    }
+}
+
+impl StreamConfig {
+    pub fn reconnect_delay(&self) -> Duration {
+        Duration::from_millis(self.reconnect_delay_ms)
+    }
+}
+
+/// Индекс аккаунт -> пулы, чьё состояние от него зависит
+pub struct AccountIndex {
+    by_account: HashMap<Pubkey, Vec<Arc<dyn PoolMints>>>,
+}
+
+impl AccountIndex {
+    pub fn new(registry: &PoolRegistry) -> Self {
+        let mut by_account: HashMap<Pubkey, Vec<Arc<dyn PoolMints>>> = HashMap::new();
+        for pool in registry.pools() {
+            for pubkey in pool.required_accounts() {
+                by_account.entry(pubkey).or_default().push(Arc::clone(pool));
+            }
+        }
+        Self { by_account }
+    }
+
+    /// Все аккаунты, на которые нужно подписаться
+    pub fn accounts(&self) -> Vec<Pubkey> {
+        self.by_account.keys().copied().collect()
+    }
+
+    pub fn pools_for(&self, pubkey: &Pubkey) -> &[Arc<dyn PoolMints>] {
+        self.by_account.get(pubkey).map(Vec::as_slice).unwrap_or(&[])
+    }
+}
+
+// Уведомление любой из подписок сессии
+enum Notification {
+    Account(Pubkey, Response<UiAccount>),
+    Logs(Response<RpcLogsResponse>),
+}
+
+/// Применить обновление аккаунта: записать в кэш и обновить зависящие от него пулы.
+///
+/// Обновление из более старого слота, чем версия в кэше, игнорируется. Пул, чьё состояние
+/// не удалось разобрать, пропускается с предупреждением и сохраняет прежнее состояние.
+/// Возвращает пулы, состояние которых обновлено.
+pub fn apply_update(
+    cache: &AccountCache,
+    index: &AccountIndex,
+    pubkey: Pubkey,
+    account: Account,
+    slot: u64,
+) -> Vec<Arc<dyn PoolMints>> {
+    cache.insert(pubkey, account, slot);
+    if cache.slot(&pubkey) != Some(slot) {
+        return Vec::new();
+    }
+
+    let accounts = cache.read();
+    let mut updated = Vec::new();
+    for pool in index.pools_for(&pubkey) {
+        match pool.ingest(&accounts) {
+            Ok(()) => updated.push(Arc::clone(pool)),
+            Err(e) => warn!("Skipping update of pool {} from {}: {}", pool.pool_pubkey(), pubkey, e),
+        }
+    }
+    updated
+}
+
+/// Потоковый режим: подписка accountSubscribe на все аккаунты состояния пулов.
+///
+/// После каждого (пере)подключения выполняется полная пересинхронизация через `provider`:
+/// обновления за время разрыва потеряны. `on_update` получает пулы, состояние которых изменилось
+/// (после пересинхронизации — все пулы); `ControlFlow::Break` останавливает поток.
+/// Транзакции с событиями event CPI при `config.logs` дочитываются через `provider`.
+pub async fn run_account_stream<P, F>(
+    config: &StreamConfig,
+    registry: &PoolRegistry,
+    cache: &AccountCache,
+    provider: &P,
+    pipeline: &PipelineConfig,
+    mut on_update: F,
+) -> Result<(), ArbError>
+where
+    P: AsyncAccountProvider + AsyncTransactionProvider,
+    F: FnMut(&[Arc<dyn PoolMints>]) -> ControlFlow<()>,
+{
+    let index = AccountIndex::new(registry);
+
+    loop {
+        match stream_session(config, registry, &index, cache, provider, pipeline, &mut on_update).await {
+            Ok(ControlFlow::Break(())) => return Ok(()),
+            Ok(ControlFlow::Continue(())) => warn!("Account stream closed, reconnecting"),
+            Err(e) => warn!("Account stream failed: {}, reconnecting", e),
+        }
+        tokio::time::sleep(config.reconnect_delay()).await;
+    }
+}
+
+/// Полная пересинхронизация всех пулов через RPC после (пере)подключения потока;
+/// `on_update` получает все пулы реестра
+pub(crate) async fn resync<P, F>(
+    registry: &PoolRegistry,
+    cache: &AccountCache,
+    provider: &P,
+    pipeline: &PipelineConfig,
+    on_update: &mut F,
+) -> Result<ControlFlow<()>, ArbError>
+where
+    P: AsyncAccountProvider,
+    F: FnMut(&[Arc<dyn PoolMints>]) -> ControlFlow<()>,
+{
+    refresh_pools_async(registry, provider, cache, pipeline).await?;
+    let pools: Vec<Arc<dyn PoolMints>> = registry.pools().cloned().collect();
+    Ok(on_update(&pools))
+}
+
+// Одно подключение: подписки, пересинхронизация, затем обработка уведомлений до разрыва
+async fn stream_session<P, F>(
+    config: &StreamConfig,
+    registry: &PoolRegistry,
+    index: &AccountIndex,
+    cache: &AccountCache,
+    provider: &P,
+    pipeline: &PipelineConfig,
+    on_update: &mut F,
+) -> Result<ControlFlow<()>, ArbError>
+where
+    P: AsyncAccountProvider + AsyncTransactionProvider,
+    F: FnMut(&[Arc<dyn PoolMints>]) -> ControlFlow<()>,
+{
+    let client = PubsubClient::new(&config.ws_url).await?;
+    let subscribe_config = RpcAccountInfoConfig {
+        encoding: Some(UiAccountEncoding::Base64),
+        commitment: Some(CommitmentConfig::confirmed()),
+        data_slice: None,
+        min_context_slot: None,
+    };
+
+    let accounts = index.accounts();
+    let mut streams: Vec<BoxStream<'_, Notification>> = Vec::with_capacity(accounts.len());
+    for pubkey in accounts {
+        let (notifications, _unsubscribe) = client.account_subscribe(&pubkey, Some(subscribe_config.clone())).await?;
+        streams.push(notifications.map(move |response| Notification::Account(pubkey, response)).boxed());
+    }
+    info!("Subscribed to {} accounts at {}", streams.len(), config.ws_url);
+
+    if config.logs {
+        for program_id in event_programs() {
+            let filter = RpcTransactionLogsFilter::Mentions(vec![program_id.to_string()]);
+            let logs_config = RpcTransactionLogsConfig { commitment: Some(CommitmentConfig::confirmed()) };
+            let (notifications, _unsubscribe) = client.logs_subscribe(filter, logs_config).await?;
+            streams.push(notifications.map(Notification::Logs).boxed());
+        }
+        info!("Subscribed to swap events of {} programs", event_programs().len());
+    }
+
+    // Подписки уже активны: пересинхронизация закрывает пропуск за время разрыва
+    if resync(registry, cache, provider, pipeline, on_update).await?.is_break() {
+        return Ok(ControlFlow::Break(()));
+    }
+
+    let mut updates = select_all(streams);
+    while let Some(notification) = updates.next().await {
+        let flow = match notification {
+            Notification::Account(pubkey, response) => {
+                let Some(account) = response.value.decode::<Account>() else {
+                    warn!("Skipping undecodable notification for {}", pubkey);
+                    continue;
+                };
+
+                let updated = apply_update(cache, index, pubkey, account, response.context.slot);
+                if updated.is_empty() {
+                    continue;
+                }
+                on_update(&updated)
+            }
+            Notification::Logs(response) => {
+                // Неуспешная транзакция состояние пулов не меняет
+                if response.value.err.is_some() {
+                    continue;
+                }
+
+                let mut parsed = parse_logs(&response.value.logs);
+                if parsed.event_cpi && !parsed.gap {
+                    // События emit_cpi! есть только во внутренних инструкциях транзакции
+                    match provider.get_inner_instructions(&response.value.signature).await {
+                        Ok(instructions) => {
+                            let inner = parse_inner_instructions(&instructions);
+                            parsed.events.extend(inner.events);
+                            parsed.gap |= inner.gap;
+                        }
+                        Err(e) => {
+                            warn!("Failed to fetch transaction {}: {}", response.value.signature, e);
+                            parsed.gap = true;
+                        }
+                    }
+                }
+                if parsed.gap {
+                    warn!("Incomplete logs in {}, falling back to full refresh", response.value.signature);
+                    resync(registry, cache, provider, pipeline, on_update).await?
+                } else {
+                    let updated = apply_events(registry, &parsed.events, response.context.slot)?;
+                    if updated.is_empty() {
+                        continue;
+                    }
+                    on_update(&updated)
+                }
+            }
+        };
+        if flow.is_break() {
+            return Ok(ControlFlow::Break(()));
+        }
+    }
+
+    Ok(ControlFlow::Continue(()))
+}
+
+#[cfg(all(test, feature = "raydium"))]
+mod tests {
+    use super::*;
+    use crate::provider::InMemoryAccountProvider;
+    use crate::test_utils::{spl_token_account, spl_token_data, stop_after, AmmFixture};
+    use base64::Engine;
+    use base64::engine::general_purpose::STANDARD as BASE64;
+    use futures::SinkExt;
+    use tokio::net::TcpListener;
+    use tokio_tungstenite::tungstenite::Message;
+
+    // Уведомление accountNotification в формате RPC-ноды
+    fn notification(subscription: u64, slot: u64, amount: u64) -> String {
+        format!(
+            r#"{{"jsonrpc":"2.0","method":"accountNotification","params":{{"result":{{"context":{{"slot":{}}},"value":{{"lamports":2039280,"data":["{}","base64"],"owner":"TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA","executable":false,"rentEpoch":18446744073709551615,"space":165}}}},"subscription":{}}}}}"#,
+            slot,
+            BASE64.encode(spl_token_data(amount)),
+            subscription
+        )
+    }
+
+    // Уведомление logsNotification успешной транзакции
+    fn logs_notification(subscription: u64, slot: u64, signature: &str, logs: &[String]) -> String {
+        let value = serde_json::json!({ "signature": signature, "err": null, "logs": logs });
+        format!(
+            r#"{{"jsonrpc":"2.0","method":"logsNotification","params":{{"result":{{"context":{{"slot":{}}},"value":{}}},"subscription":{}}}}}"#,
+            slot, value, subscription
+        )
+    }
+
+    /// Записанное уведомление
+    enum Replay {
+        /// Аккаунт SPL token: pubkey, slot, amount
+        Account(Pubkey, u64, u64),
+        /// Логи транзакции, упоминающей программу: program, slot, signature, logs
+        #[cfg_attr(not(feature = "meteora"), allow(dead_code))]
+        Logs(Pubkey, u64, &'static str, Vec<String>),
+    }
+
+    /// WebSocket-заглушка: на каждое подключение отвечает на accountSubscribe и logsSubscribe,
+    /// проигрывает записанные уведомления и закрывает соединение
+    async fn replay_server(sessions: Vec<Vec<Replay>>, subscriptions: usize) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("ws://{}", listener.local_addr().unwrap());
+
+        tokio::spawn(async move {
+            for recorded in sessions {
+                let (tcp, _) = listener.accept().await.unwrap();
+                let mut ws = tokio_tungstenite::accept_async(tcp).await.unwrap();
+
+                // Подписка на аккаунт или на логи с упоминанием программы
+                let mut subscription_ids: HashMap<Pubkey, u64> = HashMap::new();
+                while subscription_ids.len() < subscriptions {
+                    let Some(Ok(Message::Text(text))) = ws.next().await else { return };
+                    let request: serde_json::Value = serde_json::from_str(&text).unwrap();
+                    let id = request["id"].as_u64().unwrap();
+                    let params = &request["params"][0];
+                    let pubkey = params.as_str().or_else(|| params["mentions"][0].as_str()).unwrap();
+                    subscription_ids.insert(pubkey.parse().unwrap(), id + 1000);
+                    let response = format!(r#"{{"jsonrpc":"2.0","result":{},"id":{}}}"#, id + 1000, id);
+                    ws.send(Message::Text(response)).await.unwrap();
+                }
+
+                for replay in recorded {
+                    let message = match replay {
+                        Replay::Account(pubkey, slot, amount) => notification(subscription_ids[&pubkey], slot, amount),
+                        Replay::Logs(program, slot, signature, logs) => {
+                            logs_notification(subscription_ids[&program], slot, signature, &logs)
+                        }
+                    };
+                    ws.send(Message::Text(message)).await.unwrap();
+                }
+                let _ = ws.close(None).await;
+            }
+        });
+
+        url
+    }
+
+    #[tokio::test]
+    async fn test_stream_applies_updates_and_resyncs_after_reconnect() {
+        let mut provider = InMemoryAccountProvider::new(100);
+        let cache = AccountCache::new();
+        let amm = AmmFixture::new();
+        let registry = amm.load(&mut provider, &cache).await;
+
+        // Первое подключение обрывается после одного уведомления, второе приносит следующее
+        let sessions =
+            vec![vec![Replay::Account(amm.base_vault, 101, 4_000_000)], vec![Replay::Account(amm.quote_vault, 102, 2_000_000)]];
+        let url = replay_server(sessions, 2).await;
+        let config = StreamConfig { enabled: true, ws_url: url, logs: false, reconnect_delay_ms: 10 };
+
+        // Пересинхронизация, уведомление, пересинхронизация после переподключения, уведомление
+        run_account_stream(&config, &registry, &cache, &provider, &PipelineConfig::default(), stop_after(4))
+            .await
+            .unwrap();
+        amm.assert_streamed(&registry, &cache);
+    }
+
+    #[cfg(feature = "meteora")]
+    #[tokio::test]
+    async fn test_logs_stream_applies_dlmm_event_cpi_from_transaction() {
+        use crate::dex::meteora::constants::{dlmm_program_id, DLMM_SWAP_EVENT_DISCRIMINATOR};
+        use crate::dex::meteora::dlmm::{DlmmInfo, LbPair, MeteoraDlmmPoolInfo};
+        use crate::events::{InnerInstruction, EVENT_IX_TAG_LE};
+        use crate::test_utils::clock_account;
+        use solana_sdk::sysvar::clock;
+        use std::mem::size_of;
+
+        let lb_pair = Pubkey::new_unique();
+        let lb_pair_data = vec![0u8; 8 + size_of::<LbPair>()];
+        let dlmm = Arc::new(MeteoraDlmmPoolInfo::from_dlmm_info(lb_pair, &DlmmInfo::load_checked(&lb_pair_data).unwrap(), 6, 9).unwrap());
+        let registry: PoolRegistry = [Arc::clone(&dlmm) as Arc<dyn PoolMints>].into_iter().collect();
+
+        // Событие Swap есть только во внутренней инструкции event CPI
+        let mut body = vec![0u8; 129];
+        body[0..32].copy_from_slice(lb_pair.as_ref());
+        body[68..72].copy_from_slice(&42i32.to_le_bytes());
+        let data = [EVENT_IX_TAG_LE.as_slice(), DLMM_SWAP_EVENT_DISCRIMINATOR.as_slice(), &body].concat();
+        let mut provider = InMemoryAccountProvider::new(100);
+        provider.insert(lb_pair, Account { data: lb_pair_data, ..Account::default() });
+        provider.insert(clock::id(), clock_account());
+        provider.insert_transaction("swap", vec![InnerInstruction { program_id: dlmm_program_id(), data }]);
+
+        let program = dlmm_program_id().to_string();
+        let logs = vec![
+            format!("Program {} invoke [1]", program),
+            "Program log: Instruction: Swap".to_string(),
+            format!("Program {} invoke [2]", program),
+            format!("Program {} success", program),
+            format!("Program {} success", program),
+        ];
+        let url = replay_server(vec![vec![Replay::Logs(dlmm_program_id(), 105, "swap", logs)]], 1 + event_programs().len()).await;
+        let config = StreamConfig { enabled: true, ws_url: url, logs: true, reconnect_delay_ms: 10 };
+
+        // Пересинхронизация, затем событие из транзакции
+        run_account_stream(&config, &registry, &AccountCache::new(), &provider, &PipelineConfig::default(), stop_after(2))
+            .await
+            .unwrap();
+        assert_eq!(dlmm.active_id(), 42);
+        assert_eq!(dlmm.state_slot(), 105);
+    }
+
+    #[test]
+    fn test_apply_update_skips_pools_that_fail_to_ingest() {
+        use crate::dex::raydium::amm::RaydiumAmmPoolInfo;
+        use crate::dex::PoolLoader;
+        use crate::provider::AccountSnapshot;
+
+        // Два пула с общим base vault; у второго закрыт quote vault
+        let healthy = AmmFixture::new();
+        let broken = AmmFixture { base_vault: healthy.base_vault, ..AmmFixture::new() };
+        let mut snapshot = AccountSnapshot::new();
+        let mut pools: Vec<Arc<dyn PoolMints>> = Vec::new();
+        for amm in [&healthy, &broken] {
+            snapshot.insert(amm.pool, amm.pool_account(), 100);
+            pools.push(Arc::new(RaydiumAmmPoolInfo::from_snapshot(amm.pool, &snapshot).unwrap()));
+        }
+        let registry: PoolRegistry = pools.iter().cloned().collect();
+        let index = AccountIndex::new(&registry);
+        let cache = AccountCache::new();
+        cache.insert(broken.quote_vault, Account::default(), 100);
+
+        let updated = apply_update(&cache, &index, healthy.base_vault, spl_token_account(1_000), 101);
+        assert_eq!(updated.len(), 1);
+        assert_eq!(updated[0].pool_pubkey(), pools[0].pool_pubkey());
+        assert_eq!(pools[0].state_slot(), 101);
+    }
+}
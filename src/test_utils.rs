@@ -0,0 +1,236 @@
+//! Общие заглушки и фикстуры тестов: пулы для поиска и реестра, запись полей аккаунтов.
+
+use solana_sdk::account::Account;
+use solana_sdk::pubkey::Pubkey;
+use std::ops::ControlFlow;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+#[cfg(feature = "raydium")]
+use crate::cache::AccountCache;
+use crate::dex::PoolMints;
+use crate::error::ArbError;
+use crate::pool_registry::PoolRegistry;
+use crate::provider::AccountSnapshot;
+#[cfg(feature = "raydium")]
+use crate::provider::InMemoryAccountProvider;
+
+/// Пул с курсами в базисных пунктах для каждого направления и изменяемым слотом состояния.
+///
+/// По умолчанию курс 1:1 в обе стороны, слот 100.
+pub(crate) struct StubPool {
+    pubkey: Pubkey,
+    mint_a: Pubkey,
+    mint_b: Pubkey,
+    a_to_b_bps: AtomicU64,
+    b_to_a_bps: AtomicU64,
+    /// Шаг входного объёма: остаток меньше лота не исполняется
+    lot: u64,
+    slot: AtomicU64,
+}
+
+impl StubPool {
+    pub(crate) fn new(mint_a: Pubkey, mint_b: Pubkey) -> Self {
+        Self {
+            pubkey: Pubkey::new_unique(),
+            mint_a,
+            mint_b,
+            a_to_b_bps: AtomicU64::new(10_000),
+            b_to_a_bps: AtomicU64::new(10_000),
+            lot: 1,
+            slot: AtomicU64::new(100),
+        }
+    }
+
+    pub(crate) fn with_rates(self, a_to_b_bps: u64, b_to_a_bps: u64) -> Self {
+        self.a_to_b_bps.store(a_to_b_bps, Ordering::Relaxed);
+        self.b_to_a_bps.store(b_to_a_bps, Ordering::Relaxed);
+        self
+    }
+
+    /// Входной объём исполняется целыми лотами, как в стакане Phoenix/OpenBook
+    pub(crate) fn with_lot(self, lot: u64) -> Self {
+        Self { lot, ..self }
+    }
+
+    pub(crate) fn with_slot(self, slot: u64) -> Self {
+        self.slot.store(slot, Ordering::Relaxed);
+        self
+    }
+
+    pub(crate) fn set_slot(&self, slot: u64) {
+        self.slot.store(slot, Ordering::Relaxed);
+    }
+
+    /// Одинаковый курс в обе стороны
+    pub(crate) fn set_rate(&self, rate_bps: u64) {
+        self.a_to_b_bps.store(rate_bps, Ordering::Relaxed);
+        self.b_to_a_bps.store(rate_bps, Ordering::Relaxed);
+    }
+}
+
+impl PoolMints for StubPool {
+    fn pool_pubkey(&self) -> &Pubkey {
+        &self.pubkey
+    }
+
+    fn mint_a(&self) -> &Pubkey {
+        &self.mint_a
+    }
+
+    fn mint_b(&self) -> &Pubkey {
+        &self.mint_b
+    }
+
+    fn required_accounts(&self) -> Vec<Pubkey> {
+        Vec::new()
+    }
+
+    fn ingest(&self, _accounts: &AccountSnapshot) -> Result<(), ArbError> {
+        Ok(())
+    }
+
+    fn state_slot(&self) -> u64 {
+        self.slot.load(Ordering::Relaxed)
+    }
+
+    fn amount_out(&self, amount_in: u64, token_in: &Pubkey) -> Result<u64, ArbError> {
+        let rate_bps = if *token_in == self.mint_a { &self.a_to_b_bps } else { &self.b_to_a_bps };
+        let amount_in = amount_in / self.lot * self.lot;
+        Ok((amount_in as u128 * rate_bps.load(Ordering::Relaxed) as u128 / 10_000) as u64)
+    }
+}
+
+/// Заглушка 1:1 как `Arc<dyn PoolMints>`
+pub(crate) fn pool(mint_a: Pubkey, mint_b: Pubkey) -> Arc<dyn PoolMints> {
+    Arc::new(StubPool::new(mint_a, mint_b))
+}
+
+/// По `per_pair` пулов 1:1 на каждую пару mint
+pub(crate) fn pair_pools(mints: &[Pubkey], per_pair: usize) -> Vec<Arc<StubPool>> {
+    let mut pools = Vec::new();
+    for (i, mint_a) in mints.iter().enumerate() {
+        for mint_b in &mints[i + 1..] {
+            for _ in 0..per_pair {
+                pools.push(Arc::new(StubPool::new(*mint_a, *mint_b)));
+            }
+        }
+    }
+    pools
+}
+
+/// Те же пулы как `Arc<dyn PoolMints>`
+pub(crate) fn as_dyn(pools: &[Arc<StubPool>]) -> Vec<Arc<dyn PoolMints>> {
+    pools.iter().map(|pool| Arc::clone(pool) as Arc<dyn PoolMints>).collect()
+}
+
+/// Реестр из пулов-заглушек
+pub(crate) fn stub_registry(pools: &[Arc<StubPool>]) -> PoolRegistry {
+    as_dyn(pools).into_iter().collect()
+}
+
+/// Адреса пулов каждой цепочки
+pub(crate) fn chain_keys(chains: &[Vec<Arc<dyn PoolMints>>]) -> Vec<Vec<Pubkey>> {
+    chains.iter().map(|chain| chain.iter().map(|pool| *pool.pool_pubkey()).collect()).collect()
+}
+
+// Запись u32 (LE)
+pub(crate) fn write_u32(data: &mut [u8], offset: usize, value: u32) {
+    data[offset..offset + 4].copy_from_slice(&value.to_le_bytes());
+}
+
+// Запись u64 (LE)
+pub(crate) fn write_u64(data: &mut [u8], offset: usize, value: u64) {
+    data[offset..offset + 8].copy_from_slice(&value.to_le_bytes());
+}
+
+// Запись u128 (LE)
+pub(crate) fn write_u128(data: &mut [u8], offset: usize, value: u128) {
+    data[offset..offset + 16].copy_from_slice(&value.to_le_bytes());
+}
+
+/// Данные SPL Token Account с балансом `amount`
+pub(crate) fn spl_token_data(amount: u64) -> Vec<u8> {
+    let mut data = vec![0u8; 165];
+    write_u64(&mut data, 64, amount);
+    data
+}
+
+/// SPL Token Account с балансом `amount`
+pub(crate) fn spl_token_account(amount: u64) -> Account {
+    Account { data: spl_token_data(amount), ..Account::default() }
+}
+
+/// Sysvar Clock с нулевыми полями
+pub(crate) fn clock_account() -> Account {
+    Account { data: vec![0u8; 40], ..Account::default() }
+}
+
+/// Пул Raydium AMM v4 без комиссии (swap_fee 0 / 10_000) с новыми адресами vault и mint
+#[cfg(feature = "raydium")]
+pub(crate) struct AmmFixture {
+    pub(crate) pool: Pubkey,
+    pub(crate) base_vault: Pubkey,
+    pub(crate) quote_vault: Pubkey,
+    pub(crate) base_mint: Pubkey,
+    pub(crate) quote_mint: Pubkey,
+}
+
+#[cfg(feature = "raydium")]
+impl AmmFixture {
+    pub(crate) fn new() -> Self {
+        Self {
+            pool: Pubkey::new_unique(),
+            base_vault: Pubkey::new_unique(),
+            quote_vault: Pubkey::new_unique(),
+            base_mint: Pubkey::new_unique(),
+            quote_mint: Pubkey::new_unique(),
+        }
+    }
+
+    /// Аккаунт пула, принадлежащий программе AMM v4
+    pub(crate) fn pool_account(&self) -> Account {
+        let mut data = vec![0u8; 752];
+        for (offset, pubkey) in [(336, self.base_vault), (368, self.quote_vault), (400, self.base_mint), (432, self.quote_mint)] {
+            data[offset..offset + 32].copy_from_slice(pubkey.as_ref());
+        }
+        write_u64(&mut data, 184, 10_000);
+        Account { data, owner: crate::dex::raydium::constants::amm_v4_program_id(), ..Account::default() }
+    }
+
+    /// Записать в провайдер аккаунт пула и оба vault с резервами `(base, quote)`
+    pub(crate) fn insert(&self, provider: &mut InMemoryAccountProvider, (base, quote): (u64, u64)) {
+        provider.insert(self.pool, self.pool_account());
+        provider.insert(self.base_vault, spl_token_account(base));
+        provider.insert(self.quote_vault, spl_token_account(quote));
+    }
+
+    /// Реестр из одного пула с резервами 1_000_000 : 1_000_000, загруженного async-пайплайном
+    pub(crate) async fn load(&self, provider: &mut InMemoryAccountProvider, cache: &AccountCache) -> PoolRegistry {
+        provider.insert(solana_sdk::sysvar::clock::id(), clock_account());
+        self.insert(provider, (1_000_000, 1_000_000));
+        let requests = [(crate::loader::PoolKind::RaydiumAmm, self.pool)];
+        let loaded = crate::pipeline::load_pools_async(&*provider, cache, &requests, &Default::default()).await.unwrap();
+        loaded.into_values().collect()
+    }
+
+    /// Состояние после сценария потоковых тестов: base vault — 4_000_000 в слоте 101,
+    /// после переподключения quote vault — 2_000_000 в слоте 102
+    pub(crate) fn assert_streamed(&self, registry: &PoolRegistry, cache: &AccountCache) {
+        let pool = registry.get(&self.pool).unwrap();
+        assert_eq!(pool.state_slot(), 102);
+        assert_eq!(cache.get(&self.base_vault).unwrap().data, spl_token_data(4_000_000));
+        // Резервы 4:2 без комиссии: 2_000_000 - 4_000_000 * 2_000_000 / 4_001_000
+        assert_eq!(pool.amount_out(1_000, &self.base_mint).unwrap(), 500);
+    }
+}
+
+/// `on_update` потоковых тестов с одним пулом: остановить поток на `calls`-м обновлении
+pub(crate) fn stop_after(calls: usize) -> impl FnMut(&[Arc<dyn PoolMints>]) -> ControlFlow<()> {
+    let mut seen = 0;
+    move |pools| {
+        assert_eq!(pools.len(), 1);
+        seen += 1;
+        if seen == calls { ControlFlow::Break(()) } else { ControlFlow::Continue(()) }
+    }
+}